@@ -0,0 +1,2 @@
+drop table refresh_tokens;
+drop table revoked_tokens;
\ No newline at end of file
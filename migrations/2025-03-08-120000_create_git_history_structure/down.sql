@@ -0,0 +1,2 @@
+drop table git_commits;
+drop table git_lookups;
\ No newline at end of file
@@ -0,0 +1,17 @@
+-- the HEAD commit the history of an article was looked up at, also set for untracked articles
+CREATE TABLE git_lookups (
+  src_file_name TEXT PRIMARY KEY NOT NULL,
+  head TEXT NOT NULL
+);
+
+CREATE TABLE git_commits (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  src_file_name TEXT NOT NULL,
+  commit_id TEXT NOT NULL,
+  author TEXT NOT NULL,
+  date TIMESTAMP NOT NULL,
+  message TEXT NOT NULL,
+  FOREIGN KEY (src_file_name) REFERENCES git_lookups(src_file_name) ON DELETE CASCADE
+);
+
+CREATE INDEX git_commits_src_file_name ON git_commits(src_file_name);
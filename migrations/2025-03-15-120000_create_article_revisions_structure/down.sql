@@ -0,0 +1 @@
+drop table article_revisions;
\ No newline at end of file
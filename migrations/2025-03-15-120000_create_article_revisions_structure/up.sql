@@ -0,0 +1,11 @@
+-- snapshots taken by parse_article whenever the refined markdown of an article changes
+CREATE TABLE article_revisions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  src_file_name TEXT NOT NULL,
+  hash TEXT NOT NULL,
+  markdown TEXT NOT NULL,
+  source TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX article_revisions_src_file_name ON article_revisions(src_file_name);
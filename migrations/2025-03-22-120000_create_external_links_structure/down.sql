@@ -0,0 +1 @@
+drop table external_links;
\ No newline at end of file
@@ -0,0 +1,7 @@
+-- results of the link checker for external urls, reused until they are older than [link_check] external_cache_hours
+CREATE TABLE external_links (
+  url TEXT PRIMARY KEY NOT NULL,
+  status INTEGER,
+  error TEXT,
+  checked_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,2 @@
+drop table image_variants;
+drop table images;
\ No newline at end of file
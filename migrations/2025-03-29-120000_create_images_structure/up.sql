@@ -0,0 +1,18 @@
+-- source images of [[!img]] by their path relative to the input directory, `hash` is the hash of the file content
+CREATE TABLE images (
+  src_file_name TEXT PRIMARY KEY NOT NULL,
+  hash TEXT NOT NULL,
+  width INTEGER NOT NULL,
+  height INTEGER NOT NULL
+);
+
+-- resized variants of an image in the output directory, shared by all images with the same hash
+CREATE TABLE image_variants (
+  file_name TEXT PRIMARY KEY NOT NULL,
+  hash TEXT NOT NULL,
+  format TEXT NOT NULL,
+  width INTEGER NOT NULL,
+  height INTEGER NOT NULL
+);
+
+CREATE INDEX image_variants_hash ON image_variants (hash);
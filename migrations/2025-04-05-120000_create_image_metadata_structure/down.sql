@@ -0,0 +1 @@
+drop table image_metadata;
\ No newline at end of file
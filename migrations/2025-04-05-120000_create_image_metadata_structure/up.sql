@@ -0,0 +1,9 @@
+-- metadata of an image by the hash of its content, width and height are after applying the exif orientation
+CREATE TABLE image_metadata (
+  hash TEXT PRIMARY KEY NOT NULL,
+  width INTEGER NOT NULL,
+  height INTEGER NOT NULL,
+  orientation INTEGER NOT NULL,
+  caption TEXT,
+  date TIMESTAMP
+);
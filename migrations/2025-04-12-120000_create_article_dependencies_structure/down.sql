@@ -0,0 +1 @@
+drop table article_dependencies;
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- files and directories (relative to the input directory) an article is built from besides its own
+-- source, e.g. the directory of a [[!gallery]], so the file monitor can re-render the article
+CREATE TABLE article_dependencies (
+  src_file_name TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  path TEXT NOT NULL,
+  PRIMARY KEY (src_file_name, kind, path)
+);
+CREATE INDEX article_dependencies_path ON article_dependencies (path);
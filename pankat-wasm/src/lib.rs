@@ -74,6 +74,67 @@ fn ws_open() {
     }
 }
 
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Shows the problems of the article (missing images, broken links, unknown plugins) in an overlay,
+/// an empty list removes it
+fn show_diagnostics(diagnostics: &[Value]) {
+    let window = web_sys::window().expect("no global `window` exists");
+    let document = window.document().expect("should have a document on window");
+    let existing = document.get_element_by_id("pankatDiagnostics");
+
+    if diagnostics.is_empty() {
+        if let Some(overlay) = existing {
+            overlay.remove();
+        }
+        return;
+    }
+
+    let overlay = match existing {
+        Some(overlay) => overlay,
+        None => {
+            let overlay = document
+                .create_element("div")
+                .expect("Failed to create element");
+            overlay.set_id("pankatDiagnostics");
+            overlay
+                .set_attribute(
+                    "style",
+                    "position: fixed; bottom: 1em; right: 1em; z-index: 10000; max-width: 40em; \
+                     max-height: 50%; overflow: auto; padding: 0.5em 1em; background: #fff3f3; \
+                     border: 1px solid #d9534f; border-radius: 4px; font-size: 90%;",
+                )
+                .expect("Failed to set style");
+            let body = document.body().expect("document should have a body");
+            body.append_child(&overlay)
+                .expect("Failed to append overlay");
+            overlay
+        }
+    };
+
+    let items: String = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let message = diagnostic["message"].as_str().unwrap_or_default();
+            let position = match (diagnostic["line"].as_u64(), diagnostic["column"].as_u64()) {
+                (Some(line), Some(column)) => format!("{}:{}: ", line, column),
+                _ => String::new(),
+            };
+            format!("<li>{}{}</li>", position, escape_html(message))
+        })
+        .collect();
+    overlay.set_inner_html(&format!(
+        r#"<strong>{} problem(s) in this article</strong><ul style="margin: 0.5em 0 0 0; padding-left: 1.5em;">{}</ul>"#,
+        diagnostics.len(),
+        items
+    ));
+}
+
 #[wasm_bindgen]
 pub fn main_js(subdir: String) -> Result<(), JsValue> {
     console_log::init_with_level(Level::Debug).expect("error initializing logger");
@@ -117,9 +178,12 @@ pub fn main_js(subdir: String) -> Result<(), JsValue> {
                         let parsed: Value =
                             serde_json::from_str(txt_string.as_str()).expect("Invalid JSON");
 
-                        if let Some((key, value)) =
-                            parsed.as_object().and_then(|obj| obj.iter().next())
-                        {
+                        let Some(obj) = parsed.as_object() else {
+                            println!("Invalid JSON format");
+                            return;
+                        };
+                        // an update comes with its diagnostics, so the keys are handled independently
+                        for (key, value) in obj {
                             match key.as_str() {
                                 "ping" => {
                                     match cloned_ws.send_with_str(r#"{"pong" : ""}"#) {
@@ -149,10 +213,13 @@ pub fn main_js(subdir: String) -> Result<(), JsValue> {
                                         ));
                                     }
                                 }
+                                "diagnostics" => {
+                                    if let Some(diagnostics) = value.as_array() {
+                                        show_diagnostics(diagnostics);
+                                    }
+                                }
                                 _ => println!("unknown key"),
                             }
-                        } else {
-                            println!("Invalid JSON format");
                         }
                     } else {
                         log::info!("message event, received Unknown: {:?}", e.data());
@@ -0,0 +1,54 @@
+use crate::links::check::{check_page, Site};
+use serde::Serialize;
+use std::path::Path;
+
+/// A problem of an article shown to the author in the live preview, `line` and `column` are
+/// 1-based positions in the `.mdwn` source if they could be determined
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub kind: &'static str,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        kind: &'static str,
+        message: String,
+        source: &String,
+        position: Option<usize>,
+    ) -> Self {
+        let mut diagnostic = Diagnostic {
+            kind,
+            message,
+            line: None,
+            column: None,
+        };
+        if let Some(position) = position {
+            diagnostic.locate(source, position);
+        }
+        diagnostic
+    }
+
+    /// Sets `line` and `column` to the byte `position` in `source`
+    pub fn locate(&mut self, source: &String, position: usize) {
+        if let Ok((line, col)) = super::utils::position_to_line_and_col_number(source, position) {
+            self.line = Some(line + 1);
+            self.column = Some(col + 1);
+        }
+    }
+}
+
+/// Missing images, links to missing articles and anchors in the rendered `html` of an article,
+/// located by the first occurrence of the link in the `source`
+pub fn check_article_links(page: &Path, html: &str, source: &String) -> Vec<Diagnostic> {
+    check_page(&Site::from_config(), page, html)
+        .into_iter()
+        .map(|broken| {
+            let message = format!("Broken link '{}': {}", broken.link, broken.reason);
+            let position = source.find(broken.link.as_str());
+            Diagnostic::new("link", message, source, position)
+        })
+        .collect()
+}
@@ -0,0 +1,314 @@
+use std::ops::Range;
+
+/// A `[[!...]]` in the source of an article, `escaped` if it is written as `\[[!...]]` and shown
+/// as it is (without the backslash) instead of being executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    /// Byte range of the directive, including the backslash of an escaped one
+    pub range: Range<usize>,
+    pub escaped: bool,
+}
+
+fn indentation(line: &str) -> usize {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += 4 - width % 4,
+            _ => break,
+        }
+    }
+    width
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let marker = match digits {
+        0 => line
+            .get(..1)
+            .filter(|marker| ["-", "*", "+"].contains(marker)),
+        _ => line
+            .get(digits..digits + 1)
+            .filter(|marker| [".", ")"].contains(marker)),
+    };
+    marker.is_some() && {
+        let rest = &line[digits + 1..];
+        rest.is_empty() || rest.starts_with([' ', '\t', '\n', '\r'])
+    }
+}
+
+/// The ``` or ~~~ of a line opening or closing a fenced code block and the rest of the line
+fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let line = line.trim_start();
+    let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = line.chars().take_while(|x| *x == c).count();
+    (length >= 3).then(|| (c, length, &line[length..]))
+}
+
+/// Byte ranges of the fenced and indented code blocks of the markdown `source`
+///
+/// An indented line only starts a code block after a blank line which doesn't belong to a list,
+/// the continuation paragraphs of list items are indented as well.
+pub fn code_blocks(source: &str) -> Vec<Range<usize>> {
+    let mut blocks: Vec<Range<usize>> = Vec::new();
+    let mut fenced: Option<(char, usize, usize)> = None;
+    let mut indented: Option<usize> = None;
+    let mut previous_blank = true;
+    let mut in_list = false;
+
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        if let Some((c, length, block_start)) = fenced {
+            if let Some((close, close_length, rest)) = fence(line) {
+                if close == c && close_length >= length && is_blank(rest) {
+                    blocks.push(block_start..offset);
+                    fenced = None;
+                }
+            }
+            continue;
+        }
+        if let Some((c, _, rest)) = fence(line) {
+            // ``` fences can't have backticks in their info string
+            if c == '~' || !rest.contains('`') {
+                if let Some(block_start) = indented.take() {
+                    blocks.push(block_start..start);
+                }
+                fenced = Some((c, fence(line).unwrap().1, start));
+                previous_blank = false;
+                continue;
+            }
+        }
+
+        if is_blank(line) {
+            previous_blank = true;
+            continue;
+        }
+        if indentation(line) >= 4 {
+            if indented.is_none() && previous_blank && !in_list {
+                indented = Some(start);
+            }
+        } else {
+            if let Some(block_start) = indented.take() {
+                blocks.push(block_start..start);
+            }
+            if is_list_item(line) {
+                in_list = true;
+            } else if previous_blank {
+                in_list = false;
+            }
+        }
+        previous_blank = false;
+    }
+    if let Some((_, _, block_start)) = fenced {
+        blocks.push(block_start..source.len());
+    }
+    if let Some(block_start) = indented {
+        blocks.push(block_start..source.len());
+    }
+    blocks
+}
+
+/// Whether the line break at `i` is followed by a blank line
+fn ends_paragraph(source: &str, i: usize) -> bool {
+    source[i + 1..].split('\n').next().is_some_and(is_blank)
+}
+
+/// The end of the directive starting at `start` (the `[[!`), `]]` in quoted values like
+/// `[[!summary "a]]b"]]` don't end it, see `plugins::arguments::Arguments`
+///
+/// Only `"""` values may contain line breaks and no directive spans a blank line, so an unclosed
+/// quote or a missing `]]` doesn't swallow the following paragraphs.
+fn directive_end(source: &str, start: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut i = start + 3;
+    let mut value_start = true;
+    while i < bytes.len() {
+        if value_start && source[i..].starts_with(r#"""""#) {
+            i += 3 + source[i + 3..].find(r#"""""#)? + 3;
+            value_start = false;
+            continue;
+        }
+        match bytes[i] {
+            b'"' if value_start => {
+                i += 1;
+                loop {
+                    match bytes.get(i)? {
+                        b'\\' => i += 2,
+                        b'"' => break,
+                        b'\n' => return None,
+                        _ => i += 1,
+                    }
+                }
+                i += 1;
+                value_start = false;
+            }
+            b']' if bytes.get(i + 1) == Some(&b']') => return Some(i + 2),
+            b'\n' if ends_paragraph(source, i) => return None,
+            c => {
+                value_start = c.is_ascii_whitespace() || c == b'=';
+                i += 1;
+            }
+        }
+    }
+    None
+}
+
+/// The directives of the markdown `source` outside of code blocks and inline code
+pub fn find_directives(source: &str) -> Vec<Directive> {
+    let blocks = code_blocks(source);
+    let bytes = source.as_bytes();
+    let mut directives = Vec::new();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(block) = blocks.iter().find(|block| block.contains(&i)) {
+            i = block.end;
+            continue;
+        }
+        match bytes[i] {
+            b'\\' if source[i + 1..].starts_with("[[!") => match directive_end(source, i + 1) {
+                Some(end) => {
+                    directives.push(Directive {
+                        range: i..end,
+                        escaped: true,
+                    });
+                    i = end;
+                }
+                None => i += 4,
+            },
+            // an escaped backtick doesn't start inline code
+            b'\\' => i += 2,
+            b'`' => {
+                let length = bytes[i..].iter().take_while(|c| **c == b'`').count();
+                i += length;
+                // inline code ends at a run of as many backticks in the same paragraph
+                let paragraph_end = [source[i..].find("\n\n"), source[i..].find("\n\r\n")]
+                    .into_iter()
+                    .flatten()
+                    .min()
+                    .map_or(bytes.len(), |end| i + end);
+                let mut j = i;
+                while j < paragraph_end {
+                    if bytes[j] == b'`' {
+                        let run = bytes[j..].iter().take_while(|c| **c == b'`').count();
+                        if run == length {
+                            i = j + run;
+                            break;
+                        }
+                        j += run;
+                    } else {
+                        j += 1;
+                    }
+                }
+            }
+            b'[' if source[i..].starts_with("[[!") => match directive_end(source, i) {
+                Some(end) => {
+                    directives.push(Directive {
+                        range: i..end,
+                        escaped: false,
+                    });
+                    i = end;
+                }
+                None => i += 3,
+            },
+            _ => i += 1,
+        }
+    }
+    directives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directives(source: &str) -> Vec<(&str, bool)> {
+        find_directives(source)
+            .into_iter()
+            .map(|directive| (&source[directive.range], directive.escaped))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_directives() {
+        assert_eq!(
+            directives("a [[!tag foo]] b [[!title x]]\n"),
+            vec![("[[!tag foo]]", false), ("[[!title x]]", false)]
+        );
+        // escaped
+        assert_eq!(
+            directives(r"show \[[!tag foo]] and run [[!draft]]"),
+            vec![(r"\[[!tag foo]]", true), ("[[!draft]]", false)]
+        );
+        // multi-line, ]] in quoted values
+        assert_eq!(
+            directives(
+                "[[!summary a\nb]] [[!img a.png title=\"x]]\"]] [[!x t=\"\"\"\n]]\n\"\"\"]]"
+            ),
+            vec![
+                ("[[!summary a\nb]]", false),
+                ("[[!img a.png title=\"x]]\"]]", false),
+                ("[[!x t=\"\"\"\n]]\n\"\"\"]]", false)
+            ]
+        );
+        // a quote inside of a word is no quoted value
+        assert_eq!(
+            directives(r#"[[!title 5" screen]] "#),
+            vec![(r#"[[!title 5" screen]]"#, false)]
+        );
+        // unterminated
+        assert_eq!(directives("[[!tag foo\n\nbar"), vec![]);
+        assert_eq!(
+            directives("[[!tag foo\n\nbar [[!tag bar]]"),
+            vec![("[[!tag bar]]", false)]
+        );
+        // unclosed quotes end at the line
+        assert_eq!(
+            directives("[[!tag \"foo]] a\nb [[!tag bar]]"),
+            vec![("[[!tag bar]]", false)]
+        );
+        assert_eq!(
+            directives("[[!tag \"foo]]\n\n[[!tag bar]]"),
+            vec![("[[!tag bar]]", false)]
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        // inline code
+        assert_eq!(
+            directives("`[[!tag a]]` ``x ` [[!tag b]]`` [[!tag c]] \\`[[!tag d]]"),
+            vec![("[[!tag c]]", false), ("[[!tag d]]", false)]
+        );
+        // a lone backtick is no inline code
+        assert_eq!(
+            directives("it`s\n\n[[!tag a]]"),
+            vec![("[[!tag a]]", false)]
+        );
+
+        // fenced code blocks
+        let source =
+            "```\n[[!tag a]]\n```\n[[!tag b]]\n~~~~ rust\n[[!tag c]]\n~~~\n~~~~\n[[!tag d]]";
+        assert_eq!(
+            directives(source),
+            vec![("[[!tag b]]", false), ("[[!tag d]]", false)]
+        );
+        assert_eq!(directives("```\n[[!tag a]]\n"), vec![]);
+
+        // indented code blocks, but not the continuation of list items
+        let source = "text\n\n    [[!tag a]]\n\n    [[!tag b]]\ntext\n    [[!tag c]]\n\
+            * item\n\n    [[!tag d]]\n";
+        assert_eq!(
+            directives(source),
+            vec![("[[!tag c]]", false), ("[[!tag d]]", false)]
+        );
+        assert_eq!(code_blocks(source), vec![6..37]);
+    }
+}
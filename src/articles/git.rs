@@ -0,0 +1,109 @@
+use crate::config;
+use crate::db::git::{get_git_history, set_git_history, GitCommit};
+use diesel::SqliteConnection;
+use std::error::Error;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// ASCII unit/record separators, they don't show up in author names or commit messages
+const FIELD_SEPARATOR: char = '\x1f';
+const RECORD_SEPARATOR: char = '\x1e';
+const LOG_FORMAT: &str = "--format=%H%x1f%an%x1f%aI%x1f%s%x1e";
+
+fn git(input: &Path, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(input)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!("git {} failed", args.join(" ")).into())
+    }
+}
+
+/// Returns the HEAD commit of the repository `input` is part of, `None` if it isn't a git checkout
+fn head(input: &Path) -> Option<String> {
+    git(input, &["rev-parse", "HEAD"])
+        .ok()
+        .map(|head| head.trim().to_string())
+}
+
+fn parse_log(output: &str) -> Vec<GitCommit> {
+    output
+        .split(RECORD_SEPARATOR)
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim().splitn(4, FIELD_SEPARATOR).collect();
+            if fields.len() != 4 {
+                return None;
+            }
+            // the author's wall clock time, like the dates written in [[!meta date=...]]
+            let date = chrono::DateTime::parse_from_rfc3339(fields[2])
+                .ok()?
+                .naive_local();
+            Some(GitCommit {
+                commit_id: fields[0].to_string(),
+                author: fields[1].to_string(),
+                date,
+                message: fields[3].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Returns all commits touching `src_file_name`, newest first, following renames
+fn log(input: &Path, src_file_name: &str) -> Result<Vec<GitCommit>, Box<dyn Error>> {
+    let output = git(input, &["log", "--follow", LOG_FORMAT, "--", src_file_name])?;
+    Ok(parse_log(&output))
+}
+
+/// Returns the git history of an article, newest commit first, or an empty list if the input
+/// directory isn't tracked by git. The lookup is cached in the database until HEAD moves.
+pub fn article_history(conn: &mut SqliteConnection, src_file_name: &str) -> Vec<GitCommit> {
+    let cfg = config::Config::get();
+
+    let Some(head) = head(&cfg.input) else {
+        return Vec::new();
+    };
+
+    match get_git_history(conn, src_file_name, &head) {
+        Ok(Some(history)) => return history,
+        Ok(None) => {}
+        Err(e) => println!("Error loading git history of {}: {}", src_file_name, e),
+    }
+
+    match log(&cfg.input, src_file_name) {
+        Ok(history) => {
+            if let Err(e) = set_git_history(conn, src_file_name, &head, &history) {
+                println!("Error caching git history of {}: {}", src_file_name, e);
+            }
+            history
+        }
+        Err(e) => {
+            println!("Error reading git history of {}: {}", src_file_name, e);
+            Vec::new()
+        }
+    }
+}
+
+#[test]
+fn test_parse_log() {
+    let output = "a1b2\x1fJoachim Schiele\x1f2024-04-12T20:53:00+02:00\x1ffix typo\x1e\n\
+                  c3d4\x1fJoachim Schiele\x1f2021-04-17T08:00:00+00:00\x1finitial import\x1e\n";
+
+    let history = parse_log(output);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].commit_id, "a1b2");
+    assert_eq!(history[0].author, "Joachim Schiele");
+    assert_eq!(history[0].message, "fix typo");
+    assert_eq!(
+        history[0].date,
+        chrono::NaiveDateTime::parse_from_str("2024-04-12 20:53", "%Y-%m-%d %H:%M").unwrap()
+    );
+    assert_eq!(history[1].message, "initial import");
+    assert!(parse_log("").is_empty());
+}
@@ -1,6 +1,10 @@
+use self::diagnostics::Diagnostic;
 use crate::db::article::{
     get_prev_and_next_article, get_prev_and_next_article_for_series, ArticleNeighbours,
 };
+use crate::db::article_revisions::{
+    add_revision, del_old_revisions, del_revisions, NewArticleRevision,
+};
 use crate::db::cache::{compute_hash, get_cache, set_cache};
 use crate::db::DbPool;
 use colored::Colorize;
@@ -12,7 +16,11 @@ use serde_json::json;
 use std::error::Error;
 use std::path::PathBuf;
 
-mod plugins;
+mod diagnostics;
+pub mod directives;
+mod git;
+pub mod plugins;
+pub mod scaffold;
 mod tests;
 pub mod timeline;
 mod utils;
@@ -24,7 +32,8 @@ use crate::renderer::html::{
 };
 use crate::renderer::pandoc::pandoc_mdwn_2_html;
 
-use self::plugins::{draft, img, meta, series, specialpage, summary, tag, title};
+use self::plugins::gallery;
+use self::plugins::registry::PluginRegistry;
 use diesel::prelude::*;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -115,7 +124,8 @@ pub fn file_monitor_articles_change(
                 "📝 created / ✏️ modified called on {}",
                 event.path.display()
             );
-            match parse_article(conn, &event.path) {
+            let mut diagnostics: Vec<Diagnostic> = Vec::new();
+            match parse_article(conn, &event.path, &mut diagnostics) {
                 Ok(article) => {
                     //println!("Parsed article: {:#?}", article);
                     let reply = crate::db::article::set(conn, &article);
@@ -133,6 +143,7 @@ pub fn file_monitor_articles_change(
                                 let output_path: PathBuf = cfg.output.clone();
                                 let mut output_filename = output_path.clone();
                                 output_filename.push(article.dst_file_name.clone());
+                                crate::compression::remove_siblings(&output_filename);
                                 match std::fs::remove_file(output_filename.clone()) {
                                     Ok(_) => {
                                         let target: String =
@@ -147,12 +158,23 @@ pub fn file_monitor_articles_change(
 
                             match crate::db::cache::get_cache(conn, article.src_file_name.clone()) {
                                 Some(cache_entry) => {
+                                    let source = std::fs::read_to_string(
+                                        config::Config::get().input.join(&event.path),
+                                    )
+                                    .unwrap_or_default();
+                                    diagnostics.extend(diagnostics::check_article_links(
+                                        std::path::Path::new(&db_reply.article.dst_file_name),
+                                        &cache_entry.html,
+                                        &source,
+                                    ));
                                     let html: String = create_nav_content_template(
                                         conn,
                                         &db_reply.article,
                                         cache_entry.html,
                                     );
-                                    Ok(json!({ "update": html, }).to_string())
+                                    // always sent, an empty list clears the overlay of the client
+                                    Ok(json!({ "update": html, "diagnostics": diagnostics, })
+                                        .to_string())
                                 }
                                 None => Err("Error loading cache for Article".to_string()),
                             }
@@ -172,6 +194,12 @@ pub fn file_monitor_articles_change(
         }
         EventKind::Remove(_) => {
             println!("🗑️ removed called on {}", event.path.display());
+            let _ = crate::db::git::del_git_history(conn, &event.path.display().to_string());
+            let _ = del_revisions(conn, &event.path.display().to_string());
+            let _ = crate::db::article_dependencies::del_article_dependencies(
+                conn,
+                &event.path.display().to_string(),
+            );
             let res =
                 crate::db::article::del_by_src_file_name(conn, event.path.display().to_string());
             match res {
@@ -184,6 +212,70 @@ pub fn file_monitor_articles_change(
     }
 }
 
+/// The articles (relative to the input directory) which are built from the file `path` besides
+/// their own source, e.g. the articles with a `[[!gallery]]` of its directory or an `[[!include]]`
+/// of it
+pub fn dependent_articles(conn: &mut SqliteConnection, path: &std::path::Path) -> Vec<PathBuf> {
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    if hidden {
+        return Vec::new();
+    }
+    let file: String = path.to_string_lossy().replace('\\', "/");
+    let dir: String = path
+        .parent()
+        .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+
+    let mut dependents: Vec<PathBuf> = Vec::new();
+    for (kind, path) in [
+        (gallery::DEPENDENCY_KIND, &dir),
+        (plugins::include::DEPENDENCY_KIND, &file),
+    ] {
+        match crate::db::article_dependencies::get_dependent_articles(conn, kind, path) {
+            Ok(src_file_names) => dependents.extend(src_file_names.into_iter().map(PathBuf::from)),
+            Err(e) => println!("Error loading the articles depending on {}: {}", path, e),
+        }
+    }
+    dependents.sort();
+    dependents.dedup();
+    dependents
+}
+
+/// The articles with a `[[!pages]]` listing an article, `before` and `after` it changed, or none if
+/// the change doesn't show in the lists, e.g. an edit of the text only
+pub fn listing_articles(
+    conn: &mut SqliteConnection,
+    before: &Option<ArticleWithTags>,
+    after: &Option<ArticleWithTags>,
+) -> Vec<PathBuf> {
+    if before == after {
+        return Vec::new();
+    }
+    let mut listing: Vec<PathBuf> = Vec::new();
+    for article in [before, after].into_iter().flatten() {
+        for path in plugins::pages::dependency_paths(article) {
+            match crate::db::article_dependencies::get_dependent_articles(
+                conn,
+                plugins::pages::DEPENDENCY_KIND,
+                &path,
+            ) {
+                Ok(src_file_names) => listing.extend(
+                    src_file_names
+                        .into_iter()
+                        .filter(|src_file_name| *src_file_name != article.src_file_name)
+                        .map(PathBuf::from),
+                ),
+                Err(e) => println!("Error loading the articles listing {}: {}", path, e),
+            }
+        }
+    }
+    listing.sort();
+    listing.dedup();
+    listing
+}
+
 pub fn collect_garbage(pool: &DbPool) {
     let cfg = config::Config::get();
     let input_path: PathBuf = cfg.input.clone();
@@ -197,15 +289,22 @@ pub fn collect_garbage(pool: &DbPool) {
         Ok(articles) => {
             println!("====== Running GC on 'articles table' ======");
             for article in articles.clone() {
-                let path = input_path.join(article.src_file_name);
+                let path = input_path.join(&article.src_file_name);
                 if !path.exists() {
                     println!("Removing garbage 'article table' entry: {:?}", path);
                     let _ = crate::db::article::del_by_id(&mut conn, article.id.unwrap());
+                    let _ = crate::db::git::del_git_history(&mut conn, &article.src_file_name);
+                    let _ = del_revisions(&mut conn, &article.src_file_name);
+                    let _ = crate::db::article_dependencies::del_article_dependencies(
+                        &mut conn,
+                        &article.src_file_name,
+                    );
                 }
             }
             println!("====== Running GC on 'output' directory ======");
             match output_folder_check(&output_path) {
                 Ok(_) => {
+                    let precompress = cfg.compression.precompress;
                     let lookup_articles_set: std::collections::HashSet<String> = articles
                         .iter()
                         .map(|article| article.dst_file_name.clone())
@@ -217,10 +316,35 @@ pub fn collect_garbage(pool: &DbPool) {
                             .strip_prefix(&output_path)
                             .unwrap()
                             .to_path_buf();
-                        let relative_entry_string: String = relative_entry.display().to_string();
+                        // foo.html.gz and foo.html.br live as long as foo.html does
+                        let relative_entry_string: String =
+                            match crate::compression::sibling_of(&relative_entry) {
+                                Some(original) if precompress => original.display().to_string(),
+                                _ => relative_entry.display().to_string(),
+                            };
                         if relative_entry_string == PANKAT_FILE {
                             continue;
                         }
+                        // resized variants of [[!img]], see images::collect_garbage
+                        if relative_entry_string == crate::images::IMAGES_DIR
+                            && entry.file_type().unwrap().is_dir()
+                        {
+                            continue;
+                        }
+                        // copied by production and export builds, see renderer::assets and export
+                        if crate::export::MAINTAINED_DIRS.contains(&relative_entry_string.as_str())
+                            && entry.file_type().unwrap().is_dir()
+                        {
+                            let maintained = match relative_entry_string.as_str() {
+                                "assets" => cfg.production || cfg.export,
+                                _ => cfg.export && !cfg.static_build_only,
+                            };
+                            if !maintained {
+                                println!("Removing garbage 'output' entry: {:?}", relative_entry);
+                                std::fs::remove_dir_all(entry.path()).unwrap();
+                            }
+                            continue;
+                        }
                         if entry.file_type().unwrap().is_dir() {
                             let w = "WARNING: ignoring path, needs to be implemented!".red();
                             println!("{w}");
@@ -231,6 +355,11 @@ pub fn collect_garbage(pool: &DbPool) {
                             std::fs::remove_file(entry.path()).unwrap();
                         }
                     }
+                    if let Err(e) =
+                        crate::images::collect_garbage(&mut conn, &input_path, &output_path)
+                    {
+                        println!("Error running GC on images: {}", e);
+                    }
                 }
                 Err(e) => {
                     println!(
@@ -288,7 +417,7 @@ pub fn scan_articles(pool: &DbPool) {
                             if ext == "mdwn" {
                                 let article_path: PathBuf =
                                     path.strip_prefix(input_path).unwrap().to_path_buf();
-                                match parse_article(conn, &article_path) {
+                                match parse_article(conn, &article_path, &mut Vec::new()) {
                                     Ok(article) => {
                                         //println!("Parsed article: {:#?}", article);
                                         let _ = crate::db::article::set(conn, &article);
@@ -305,6 +434,23 @@ pub fn scan_articles(pool: &DbPool) {
 
     traverse_and_collect_articles(&mut conn, &input_path, &input_path);
 
+    // a [[!pages]] only lists the articles parsed before it, so these are parsed again
+    match crate::db::article_dependencies::get_articles_by_dependency_kind(
+        &mut conn,
+        plugins::pages::DEPENDENCY_KIND,
+    ) {
+        Ok(src_file_names) => {
+            for src_file_name in src_file_names {
+                if let Ok(article) =
+                    parse_article(&mut conn, &PathBuf::from(src_file_name), &mut Vec::new())
+                {
+                    let _ = crate::db::article::set(&mut conn, &article);
+                }
+            }
+        }
+        Err(e) => println!("Error loading the articles with [[!pages]]: {}", e),
+    }
+
     let duration = start_time.elapsed();
     println!("Time to scan input for articles: {:?}", duration);
 }
@@ -397,11 +543,14 @@ fn create_nav_content_template(
             Err(_) => ArticleNeighbours::new(),
         };
 
+    let history = crate::db::git::get_git_commits(conn, &article.src_file_name).unwrap_or_default();
+
     let content: String = create_html_from_content_template(
         article.clone(),
         html,
         article_neighbours,
         article_series_neighbours,
+        history,
     )
     .unwrap();
     content
@@ -436,12 +585,22 @@ pub fn write_to_disk(content: &String, filepath: &PathBuf) {
             eprintln!("Failed to create directory {:?}: {}", parent, e);
         }
     }
+    let cfg = config::Config::get();
+    let minified: String;
+    let content = if cfg.production && filepath.extension().is_some_and(|ext| ext == "html") {
+        minified = crate::renderer::utils::minify_html(content);
+        &minified
+    } else {
+        content
+    };
     std::fs::write(filepath, content.as_str()).expect("Unable to write HTML file");
+    crate::compression::write_siblings(filepath, content.as_bytes(), cfg.compression.precompress);
 }
 
 fn parse_article(
     conn: &mut SqliteConnection,
     article_path: &PathBuf,
+    diagnostics: &mut Vec<Diagnostic>,
 ) -> Result<ArticleWithTags, Box<dyn Error>> {
     let src_file_name_string = article_path.display().to_string();
     let cfg = config::Config::get();
@@ -470,12 +629,24 @@ fn parse_article(
 
     let file_path: PathBuf = input_path.join(article_path);
     let article_mdwn_raw_string = std::fs::read_to_string(file_path).unwrap();
-    match eval_plugins(&article_mdwn_raw_string, &mut new_article) {
+    // the plugins record the current ones
+    crate::db::article_dependencies::del_article_dependencies(conn, &src_file_name_string)?;
+    match eval_plugins(
+        conn,
+        &article_mdwn_raw_string,
+        &mut new_article,
+        diagnostics,
+    ) {
         Ok(article_mdwn_refined_source) => {
             if new_article.special_page == Some(true) {
                 new_article.tocify = None;
             }
-            let hash: String = compute_hash(article_mdwn_refined_source.clone());
+            let math = plugins::math::math_mode(&article_mdwn_refined_source, &cfg.math);
+            // the same markdown renders differently with another math mode of pankat.toml
+            let hash: String = compute_hash(match &math {
+                Some(mode) => format!("{}\n{}", mode, article_mdwn_refined_source),
+                None => article_mdwn_refined_source.clone(),
+            });
             // println!(
             //     "src_file_name_string.clone(): {}",
             //     src_file_name_string.clone()
@@ -496,10 +667,35 @@ fn parse_article(
                 match pandoc_mdwn_2_html(
                     article_mdwn_refined_source.clone(),
                     new_article.anchorjs.unwrap_or(false),
+                    math.as_deref(),
                 ) {
                     Ok(html) => {
-                        match set_cache(conn, src_file_name_string.clone(), html.clone(), hash) {
-                            Ok(_) => {}
+                        let html = plugins::toc::fill_toc(&html)?;
+                        match set_cache(
+                            conn,
+                            src_file_name_string.clone(),
+                            html.clone(),
+                            hash.clone(),
+                        ) {
+                            Ok(_) => {
+                                let revision = NewArticleRevision {
+                                    src_file_name: &src_file_name_string,
+                                    hash: &hash,
+                                    markdown: &article_mdwn_refined_source,
+                                    source: &article_mdwn_raw_string,
+                                    created_at: chrono::Local::now().naive_local(),
+                                };
+                                if let Err(e) = add_revision(conn, &revision) {
+                                    println!("Error storing revision: {}", e);
+                                }
+                                if let Err(e) = del_old_revisions(
+                                    conn,
+                                    &src_file_name_string,
+                                    cfg.revisions.keep,
+                                ) {
+                                    println!("Error deleting old revisions: {}", e);
+                                }
+                            }
                             Err(e) => {
                                 println!("Error udpating cache: {}", e);
                             }
@@ -516,6 +712,11 @@ fn parse_article(
             } else {
                 println!(" ... skipping call to pandoc, already in cache");
             };
+            let history = git::article_history(conn, &src_file_name_string);
+            if new_article.modification_date.is_none() {
+                // no [[!meta date=...]], fall back to the date of the first commit
+                new_article.modification_date = history.last().map(|commit| commit.date);
+            }
             if new_article.title == None {
                 let title = utils::article_src_file_name_to_title(&article_path);
                 new_article.title = Some(title);
@@ -532,29 +733,85 @@ fn parse_article(
     }
 }
 
+/// Directives like `[[!include]]` evaluate the directives of their output, but not deeper than this
+const MAX_NESTED_PLUGINS: usize = 16;
+
 fn eval_plugins(
+    conn: &mut SqliteConnection,
+    article_mdwn_raw_string: &String,
+    article: &mut ArticleWithTags,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<String, Box<dyn Error>> {
+    eval_plugins_with(
+        PluginRegistry::instance(),
+        conn,
+        article_mdwn_raw_string,
+        article,
+        diagnostics,
+    )
+}
+
+/// `eval_plugins` with the plugins of `registry` instead of the ones of the singleton
+fn eval_plugins_with(
+    registry: &PluginRegistry,
+    conn: &mut SqliteConnection,
     article_mdwn_raw_string: &String,
     article: &mut ArticleWithTags,
+    diagnostics: &mut Vec<Diagnostic>,
 ) -> Result<String, Box<dyn Error>> {
-    let re = Regex::new(r"\[\[\!(.*?)\]\]").unwrap();
+    let mut nested = vec![article.src_file_name.clone()];
+    eval_nested_plugins(
+        registry,
+        conn,
+        article_mdwn_raw_string,
+        article,
+        diagnostics,
+        &mut nested,
+    )
+}
 
+/// `nested` are the keys of the directives whose output is evaluated, see `Plugin::nested`
+fn eval_nested_plugins(
+    registry: &PluginRegistry,
+    conn: &mut SqliteConnection,
+    article_mdwn_raw_string: &String,
+    article: &mut ArticleWithTags,
+    diagnostics: &mut Vec<Diagnostic>,
+    nested: &mut Vec<String>,
+) -> Result<String, Box<dyn Error>> {
     let mut last = 0;
     let mut res: String = String::new();
-    for mat in re.find_iter(&article_mdwn_raw_string) {
-        let start = mat.start();
-        let end = mat.end();
-
-        if start > last {
-            res += &article_mdwn_raw_string[last..start];
-            last = start;
+    for directive in directives::find_directives(article_mdwn_raw_string) {
+        let start = directive.range.start;
+        let end = directive.range.end;
+        res += &article_mdwn_raw_string[last..start];
+        last = end;
+
+        if directive.escaped {
+            // \[[!...]] is shown as [[!...]]
+            res += &article_mdwn_raw_string[start + 1..end];
+            continue;
         }
-
-        match exec_plugin(&article_mdwn_raw_string[start..end], article) {
+        let before = diagnostics.len();
+        match exec_plugin(
+            registry,
+            conn,
+            &article_mdwn_raw_string[start..end],
+            article,
+            diagnostics,
+            nested,
+        ) {
             Ok(result) => {
                 res.push_str(&result);
             }
             Err(e) => {
                 res += &article_mdwn_raw_string[start..end];
+                diagnostics.push(Diagnostic::new(
+                    "plugin",
+                    e.to_string(),
+                    article_mdwn_raw_string,
+                    Some(start),
+                ));
                 match utils::position_to_line_and_col_number(&article_mdwn_raw_string, start) {
                     Ok((line, col)) => {
                         println!(
@@ -571,41 +828,74 @@ fn eval_plugins(
                 }
             }
         }
-        if end <= article_mdwn_raw_string.len() {
-            res += &article_mdwn_raw_string[last..start];
-            last = end;
-        } else {
-            return Err(
-                "Error: The specified length to extract is beyond the string's bounds.".into(),
-            );
+        // the problems of an included file are shown at the directive including it
+        for diagnostic in diagnostics[before..].iter_mut() {
+            if diagnostic.line.is_none() {
+                diagnostic.locate(article_mdwn_raw_string, start);
+            }
         }
     }
-    if last <= article_mdwn_raw_string.len() {
-        let t = &article_mdwn_raw_string[last..];
-        res += t;
-    }
+    res += &article_mdwn_raw_string[last..];
     Ok(res)
 }
 
-pub fn exec_plugin(input: &str, article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    let pattern = r#"\[\[!([\w]+)(?:\s+(.*))?\]\]"#;
+pub fn exec_plugin(
+    registry: &PluginRegistry,
+    conn: &mut SqliteConnection,
+    input: &str,
+    article: &mut ArticleWithTags,
+    diagnostics: &mut Vec<Diagnostic>,
+    nested: &mut Vec<String>,
+) -> Result<String, Box<dyn Error>> {
+    // triple quoted arguments may span lines
+    let pattern = r#"(?s)\[\[!([\w]+)(?:\s+(.*))?\]\]"#;
     let re = Regex::new(pattern).unwrap();
 
     if let Some(captures) = re.captures(input) {
         let name: &str = captures.get(1).unwrap().as_str();
         let argument = captures.get(2).map_or("", |m| m.as_str()).trim();
 
-        match name.to_lowercase().as_str() {
-            "title" => title::title(argument, article),
-            "specialpage" => specialpage::specialpage(argument, article),
-            "draft" => draft::draft(argument, article),
-            "meta" => meta::meta(argument, article),
-            "series" => series::series(argument, article),
-            "tag" => tag::tag(argument, article),
-            "img" => img::img(argument, article),
-            "summary" => summary::summary(argument, article),
-            _ => Err(format!("Plugin '{}' is not supported", name).into()),
+        let plugin = registry
+            .get(name)
+            .ok_or_else(|| format!("Plugin '{}' is not supported", name))?;
+        let arguments = plugin.parse(argument)?;
+        plugin.validate(&arguments)?;
+        let key = plugin.nested(&arguments);
+        if let Some(key) = &key {
+            if nested.contains(key) {
+                return Err(format!("Cycle: {} -> {}", nested.join(" -> "), key).into());
+            }
+            if nested.len() > MAX_NESTED_PLUGINS {
+                return Err(format!("[[!{}]] is nested too deeply", plugin.name()).into());
+            }
         }
+        plugin.apply(&arguments, article)?;
+        let result = plugin.render(conn, &arguments, article)?;
+        let Some(key) = key else {
+            return Ok(result);
+        };
+
+        nested.push(key.clone());
+        let mut nested_diagnostics = Vec::new();
+        let result = eval_nested_plugins(
+            registry,
+            conn,
+            &result,
+            article,
+            &mut nested_diagnostics,
+            nested,
+        );
+        nested.pop();
+        diagnostics.extend(nested_diagnostics.into_iter().map(|diagnostic| Diagnostic {
+            message: match diagnostic.line {
+                Some(line) => format!("{}:{}: {}", key, line, diagnostic.message),
+                None => format!("{}: {}", key, diagnostic.message),
+            },
+            line: None,
+            column: None,
+            ..diagnostic
+        }));
+        result
     } else {
         Err("Plugin couldn't be decoded".into())
     }
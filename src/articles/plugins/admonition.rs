@@ -0,0 +1,85 @@
+use super::{partial, Arguments, Plugin};
+use crate::articles::ArticleWithTags;
+use diesel::SqliteConnection;
+use serde_json::json;
+use std::error::Error;
+
+/// `[[!note text]]`, `[[!warning text]]` and `[[!tip text]]` boxes, the text is markdown and may
+/// span lines with `"""text"""`, see `partials/admonition.html`
+pub struct Admonition {
+    pub kind: &'static str,
+    pub title: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+}
+
+pub const NOTE: Admonition = Admonition {
+    kind: "note",
+    title: "Note",
+    usage: r#"[[!note [title="..."] text]]"#,
+    help: r#"Note box, the markdown text may span paragraphs with """text""""#,
+};
+
+pub const WARNING: Admonition = Admonition {
+    kind: "warning",
+    title: "Warning",
+    usage: r#"[[!warning [title="..."] text]]"#,
+    help: r#"Warning box, the markdown text may span paragraphs with """text""""#,
+};
+
+pub const TIP: Admonition = Admonition {
+    kind: "tip",
+    title: "Tip",
+    usage: r#"[[!tip [title="..."] text]]"#,
+    help: r#"Tip box, the markdown text may span paragraphs with """text""""#,
+};
+
+impl Admonition {
+    /// The data of the partial, `[[!note title="Heads up" "text"]]` replaces the default title
+    pub fn data(&self, arguments: &Arguments) -> Result<serde_json::Value, Box<dyn Error>> {
+        let content = match arguments.get("title") {
+            Some(_) => arguments.positional().join(" "),
+            None => arguments.text(),
+        };
+        if content.trim().is_empty() {
+            return Err(format!("{} needs a text", self.title).into());
+        }
+        Ok(json!({
+            "Kind": self.kind,
+            "Title": arguments.get("title").unwrap_or(self.title),
+            "Content": content,
+        }))
+    }
+}
+
+impl Plugin for Admonition {
+    fn name(&self) -> &str {
+        self.kind
+    }
+
+    fn usage(&self) -> &str {
+        self.usage
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    /// Free text like `[[!note don't "quote" me]]` is taken as it is
+    fn parse(&self, input: &str) -> Result<Arguments, Box<dyn Error>> {
+        Ok(Arguments::parse_lenient(input))
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        self.data(arguments).map(|_| ())
+    }
+
+    fn render(
+        &self,
+        _conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        _article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        partial("admonition", &self.data(arguments)?)
+    }
+}
@@ -0,0 +1,247 @@
+use std::error::Error;
+
+/// A parameter of a directive, `key` is `None` for positional ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parameter {
+    pub key: Option<String>,
+    pub value: String,
+}
+
+/// The text of a directive after its name, e.g. `media/cat.jpg class="noFancy"` of
+/// `[[!img media/cat.jpg class="noFancy"]]`, split into parameters like ikiwiki does:
+///
+/// * `value` and `key=value` without whitespace
+/// * `"value"` and `key="value"`, `\"` and `\\` are the only escapes
+/// * `"""value"""` and `key="""value"""`, which may contain quotes and newlines but no escapes
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Arguments {
+    pub raw: String,
+    pub parameters: Vec<Parameter>,
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+impl Arguments {
+    pub fn parse(input: &str) -> Result<Arguments, Box<dyn Error>> {
+        let mut parameters = Vec::new();
+        let mut rest = input.trim_start();
+        while !rest.is_empty() {
+            let mut key = None;
+            if let Some(position) = rest.find('=') {
+                if position > 0 && rest[..position].chars().all(is_key_char) {
+                    key = Some(rest[..position].to_string());
+                    rest = &rest[position + 1..];
+                }
+            }
+
+            let value: String;
+            if let Some(quoted) = rest.strip_prefix(r#"""""#) {
+                let end = quoted
+                    .find(r#"""""#)
+                    .ok_or("Unterminated \"\"\" in arguments")?;
+                value = quoted[..end].to_string();
+                rest = &quoted[end + 3..];
+            } else if let Some(quoted) = rest.strip_prefix('"') {
+                let mut unescaped = String::new();
+                let mut chars = quoted.char_indices();
+                let end = loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c @ ('"' | '\\'))) => unescaped.push(c),
+                            Some((_, c)) => {
+                                unescaped.push('\\');
+                                unescaped.push(c);
+                            }
+                            None => unescaped.push('\\'),
+                        },
+                        Some((i, '"')) => break i,
+                        Some((_, c)) => unescaped.push(c),
+                        None => return Err("Unterminated \" in arguments".into()),
+                    }
+                };
+                value = unescaped;
+                rest = &quoted[end + 1..];
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                value = rest[..end].to_string();
+                rest = &rest[end..];
+            }
+
+            if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+                return Err(format!("Expected whitespace after \"{}\" in arguments", value).into());
+            }
+            parameters.push(Parameter { key, value });
+            rest = rest.trim_start();
+        }
+        Ok(Arguments {
+            raw: input.to_string(),
+            parameters,
+        })
+    }
+
+    /// Like `parse`, but free text like `[[!title 5" screen]]` is kept as `raw` without parameters
+    /// instead of failing
+    pub fn parse_lenient(input: &str) -> Arguments {
+        Arguments::parse(input).unwrap_or_else(|_| Arguments {
+            raw: input.to_string(),
+            parameters: Vec::new(),
+        })
+    }
+
+    /// The value of the first parameter named `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|parameter| parameter.key.as_deref() == Some(key))
+            .map(|parameter| parameter.value.as_str())
+    }
+
+    pub fn positional(&self) -> Vec<&str> {
+        self.parameters
+            .iter()
+            .filter(|parameter| parameter.key.is_none())
+            .map(|parameter| parameter.value.as_str())
+            .collect()
+    }
+
+    pub fn named(&self) -> Vec<(&str, &str)> {
+        self.parameters
+            .iter()
+            .filter_map(|parameter| {
+                parameter
+                    .key
+                    .as_deref()
+                    .map(|key| (key, parameter.value.as_str()))
+            })
+            .collect()
+    }
+
+    /// Fails on named parameters other than `keys`
+    pub fn allow_keys(&self, directive: &str, keys: &[&str]) -> Result<(), Box<dyn Error>> {
+        match self.named().iter().find(|(key, _)| !keys.contains(key)) {
+            Some((key, _)) => Err(format!("Unknown parameter '{}' of {}", key, directive).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// The text of directives like `[[!title text]]`, which is taken as it is unless it is a single
+    /// quoted value, so `[[!title "Foo"]]` and `[[!title Foo]]` are the same
+    pub fn text(&self) -> String {
+        let raw = self.raw.trim();
+        match self.parameters.as_slice() {
+            [Parameter { key: None, value }] if raw.starts_with('"') => value.clone(),
+            _ => raw.to_string(),
+        }
+    }
+}
+
+/// Writes `value` so that `Arguments::parse` reads it back as one parameter
+pub fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+        return value.to_string();
+    }
+    format!(r#""{}""#, value.replace('\\', r"\\").replace('"', r#"\""#))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameter(key: Option<&str>, value: &str) -> Parameter {
+        Parameter {
+            key: key.map(str::to_string),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let arguments = Arguments::parse(
+            r#"media/cat.jpg class="noFancy" style="float: right" width=200px "two words""#,
+        )
+        .unwrap();
+        assert_eq!(
+            arguments.parameters,
+            vec![
+                parameter(None, "media/cat.jpg"),
+                parameter(Some("class"), "noFancy"),
+                parameter(Some("style"), "float: right"),
+                parameter(Some("width"), "200px"),
+                parameter(None, "two words"),
+            ]
+        );
+        assert_eq!(arguments.positional(), vec!["media/cat.jpg", "two words"]);
+        assert_eq!(arguments.get("style"), Some("float: right"));
+        assert_eq!(arguments.get("alt"), None);
+        assert!(arguments.allow_keys("img", &["class", "style"]).is_err());
+        assert!(arguments
+            .allow_keys("img", &["class", "style", "width"])
+            .is_ok());
+
+        // escapes
+        assert_eq!(
+            Arguments::parse(r#"title="say \"hi\" \\o/" path="C:\temp""#)
+                .unwrap()
+                .parameters,
+            vec![
+                parameter(Some("title"), r#"say "hi" \o/"#),
+                parameter(Some("path"), r"C:\temp"),
+            ]
+        );
+
+        // triple quotes span lines and may contain quotes
+        assert_eq!(
+            Arguments::parse("text=\"\"\"line \"one\"\nline two\"\"\"  x")
+                .unwrap()
+                .parameters,
+            vec![
+                parameter(Some("text"), "line \"one\"\nline two"),
+                parameter(None, "x"),
+            ]
+        );
+
+        // only keys made of word characters name a parameter
+        assert_eq!(
+            Arguments::parse(r#"a=b=c "d=e" f/g=h"#).unwrap().parameters,
+            vec![
+                parameter(Some("a"), "b=c"),
+                parameter(None, "d=e"),
+                parameter(None, "f/g=h"),
+            ]
+        );
+
+        assert_eq!(Arguments::parse("  ").unwrap().parameters, vec![]);
+        assert!(Arguments::parse(r#"title="unterminated"#).is_err());
+        assert!(Arguments::parse(r#"text="""unterminated""#).is_err());
+        assert!(Arguments::parse(r#""a"b"#).is_err());
+    }
+
+    #[test]
+    fn test_text_and_quote() {
+        assert_eq!(
+            Arguments::parse("Test  Title").unwrap().text(),
+            "Test  Title"
+        );
+        assert_eq!(
+            Arguments::parse(r#""Test Title""#).unwrap().text(),
+            "Test Title"
+        );
+        assert_eq!(
+            Arguments::parse(r#""Foo" and "Bar""#).unwrap().text(),
+            r#""Foo" and "Bar""#
+        );
+        assert_eq!(
+            Arguments::parse_lenient(r#"5" screen"#).text(),
+            r#"5" screen"#
+        );
+
+        for value in ["nix", "home manager", r#"say "hi""#, r"C:\temp", "a=b", ""] {
+            let arguments = Arguments::parse(&quote(value)).unwrap();
+            assert_eq!(arguments.parameters, vec![parameter(None, value)]);
+        }
+        assert_eq!(quote("nix"), "nix");
+        assert_eq!(quote("home manager"), r#""home manager""#);
+    }
+}
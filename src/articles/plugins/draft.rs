@@ -1,7 +1,28 @@
+use super::{Arguments, Plugin};
 use crate::articles::ArticleWithTags;
 use std::error::Error;
 
-pub fn draft(_input: &str, article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    article.draft = Some(true);
-    Ok("".to_string())
+pub struct Draft;
+
+impl Plugin for Draft {
+    fn name(&self) -> &'static str {
+        "draft"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[[!draft]]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Marks the article as draft, drafts are only listed in the draft view"
+    }
+
+    fn apply(
+        &self,
+        _arguments: &Arguments,
+        article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        article.draft = Some(true);
+        Ok(())
+    }
 }
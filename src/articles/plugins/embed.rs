@@ -0,0 +1,177 @@
+use super::{partial, Arguments, Plugin};
+use crate::articles::ArticleWithTags;
+use diesel::SqliteConnection;
+use regex::Regex;
+use serde_json::json;
+use std::error::Error;
+
+/// The single positional id or url of an embed, `pattern` captures the id
+fn embed_id(
+    directive: &str,
+    arguments: &Arguments,
+    pattern: &str,
+) -> Result<String, Box<dyn Error>> {
+    let value = match arguments.positional().as_slice() {
+        [value] => *value,
+        _ => return Err(format!("{} needs exactly one id or url", directive).into()),
+    };
+    Regex::new(pattern)
+        .unwrap()
+        .captures(value)
+        .map(|captures| captures[1].to_string())
+        .ok_or_else(|| format!("'{}' is no {} id or url", value, directive).into())
+}
+
+/// `[[!youtube id]]`, rendered as a link which the page replaces with the player from
+/// youtube-nocookie.com on click, so visitors don't talk to Google before they decide to
+pub struct Youtube;
+
+impl Youtube {
+    pub fn data(arguments: &Arguments) -> Result<serde_json::Value, Box<dyn Error>> {
+        arguments.allow_keys("youtube", &["start", "title"])?;
+        let id = embed_id(
+            "youtube",
+            arguments,
+            r"^(?:https?://(?:www\.)?(?:youtube\.com/watch\?(?:\S*&)?v=|youtube\.com/embed/|youtu\.be/))?([\w-]{11})(?:[?&#]\S*)?$",
+        )?;
+        let start = match arguments.get("start") {
+            Some(start) => Some(
+                start
+                    .parse::<u32>()
+                    .map_err(|_| format!("start has to be in seconds, not '{}'", start))?,
+            ),
+            None => None,
+        };
+        Ok(json!({
+            "Id": id,
+            "Start": start,
+            "Title": arguments.get("title"),
+        }))
+    }
+}
+
+impl Plugin for Youtube {
+    fn name(&self) -> &str {
+        "youtube"
+    }
+
+    fn usage(&self) -> &str {
+        r#"[[!youtube id|url [start=seconds] [title="..."]]]"#
+    }
+
+    fn help(&self) -> &str {
+        "YouTube video, loaded from youtube-nocookie.com only after a click"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Youtube::data(arguments).map(|_| ())
+    }
+
+    fn render(
+        &self,
+        _conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        _article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        partial("youtube", &Youtube::data(arguments)?)
+    }
+}
+
+/// `[[!asciinema id]]`, a terminal recording loaded on click like `[[!youtube]]`
+pub struct Asciinema;
+
+impl Asciinema {
+    pub fn data(arguments: &Arguments) -> Result<serde_json::Value, Box<dyn Error>> {
+        arguments.allow_keys("asciinema", &["title"])?;
+        let id = embed_id(
+            "asciinema",
+            arguments,
+            r"^(?:https?://asciinema\.org/a/)?(\w+)(?:\.cast|\.js)?/?$",
+        )?;
+        Ok(json!({
+            "Id": id,
+            "Title": arguments.get("title"),
+        }))
+    }
+}
+
+impl Plugin for Asciinema {
+    fn name(&self) -> &str {
+        "asciinema"
+    }
+
+    fn usage(&self) -> &str {
+        r#"[[!asciinema id|url [title="..."]]]"#
+    }
+
+    fn help(&self) -> &str {
+        "asciinema terminal recording, loaded from asciinema.org only after a click"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Asciinema::data(arguments).map(|_| ())
+    }
+
+    fn render(
+        &self,
+        _conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        _article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        partial("asciinema", &Asciinema::data(arguments)?)
+    }
+}
+
+/// `[[!gist user/id]]`, embedded with the script of github
+pub struct Gist;
+
+impl Gist {
+    pub fn data(arguments: &Arguments) -> Result<serde_json::Value, Box<dyn Error>> {
+        arguments.allow_keys("gist", &["file"])?;
+        let gist = embed_id(
+            "gist",
+            arguments,
+            r"^(?:https?://gist\.github\.com/)?([\w-]+/[0-9a-fA-F]+)/?$",
+        )?;
+        let file = arguments.get("file");
+        if let Some(file) = file {
+            if !file
+                .chars()
+                .all(|c| c.is_alphanumeric() || "._-".contains(c))
+            {
+                return Err(format!("'{}' is no file name of a gist", file).into());
+            }
+        }
+        Ok(json!({
+            "Gist": gist,
+            "File": file,
+        }))
+    }
+}
+
+impl Plugin for Gist {
+    fn name(&self) -> &str {
+        "gist"
+    }
+
+    fn usage(&self) -> &str {
+        "[[!gist user/id|url [file=name]]]"
+    }
+
+    fn help(&self) -> &str {
+        "GitHub gist, file= shows a single file of it"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Gist::data(arguments).map(|_| ())
+    }
+
+    fn render(
+        &self,
+        _conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        _article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        partial("gist", &Gist::data(arguments)?)
+    }
+}
@@ -0,0 +1,191 @@
+use super::{Arguments, Plugin};
+use crate::articles::ArticleWithTags;
+use crate::config::ImagesConfig;
+use crate::db::article_dependencies::{add_article_dependency, ArticleDependency};
+use crate::images::{image_title, picture_html, process_image, ResponsiveImage};
+use crate::renderer::utils::escape_attribute;
+use diesel::SqliteConnection;
+use std::error::Error;
+use std::path::Path;
+
+/// The `kind` of the `article_dependencies` on the directory of a gallery
+pub const DEPENDENCY_KIND: &str = "gallery";
+
+/// Lets the browser pick the smallest variant for the cells of the grid, see `.pankatGallery` in
+/// `style.css`
+const THUMBNAIL_SIZES: &str = "(max-width: 480px) 50vw, 240px";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// By the exif date, images without one follow by name
+    Date,
+    Name,
+}
+
+/// The arguments of `[[!gallery media/trip]]` (all images of the directory) or
+/// `[[!gallery media/trip/*.jpg sort=name]]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gallery {
+    /// Relative to the input directory
+    pub dir: String,
+    /// Matched against the file names in `dir`
+    pub pattern: glob::Pattern,
+    pub sort: Sort,
+}
+
+impl Gallery {
+    pub fn from_arguments(arguments: &Arguments) -> Result<Gallery, Box<dyn Error>> {
+        arguments.allow_keys("gallery", &["sort"])?;
+        let path = match arguments.positional().as_slice() {
+            [path] => path.trim_matches('/'),
+            _ => return Err("Gallery needs exactly one directory or glob".into()),
+        };
+        let sort = match arguments.get("sort") {
+            None | Some("date") => Sort::Date,
+            Some("name") => Sort::Name,
+            Some(other) => return Err(format!("Unknown gallery sort '{}'", other).into()),
+        };
+
+        let is_glob = |s: &str| s.contains(['*', '?', '[']);
+        let (dir, pattern) = match path.rsplit_once('/') {
+            Some((dir, pattern)) if is_glob(pattern) => (dir, pattern),
+            None if is_glob(path) => ("", path),
+            _ => (path, "*"),
+        };
+        if is_glob(dir) {
+            return Err(format!("Only the file names of '{}' may contain wildcards", path).into());
+        }
+        Ok(Gallery {
+            dir: dir.to_string(),
+            pattern: glob::Pattern::new(pattern)?,
+            sort,
+        })
+    }
+}
+
+/// A grid of thumbnails of the images of `gallery`, each linking to the original for the colorbox
+/// lightbox
+pub fn gallery_html(
+    conn: &mut SqliteConnection,
+    settings: &ImagesConfig,
+    input: &Path,
+    output: &Path,
+    subdir: &Path,
+    gallery: &Gallery,
+) -> Result<String, Box<dyn Error>> {
+    let path = crate::safe_path::resolve_path(input, &gallery.dir)?;
+    if !path.is_dir() {
+        return Err(format!("Gallery directory '{}' does not exist", gallery.dir).into());
+    }
+
+    let mut file_names: Vec<String> = Vec::new();
+    for entry in std::fs::read_dir(&path)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_file()
+            && !file_name.starts_with('.')
+            && gallery.pattern.matches(&file_name)
+        {
+            file_names.push(file_name);
+        }
+    }
+    file_names.sort();
+
+    let mut images: Vec<(String, ResponsiveImage)> = Vec::new();
+    for file_name in file_names {
+        let url = match gallery.dir.as_str() {
+            "" => file_name,
+            dir => format!("{}/{}", dir, file_name),
+        };
+        // files which are no raster images are skipped
+        match process_image(conn, settings, input, output, &url) {
+            Ok(Some(image)) => images.push((url, image)),
+            Ok(None) => {}
+            Err(e) => println!("Skipping image {} of gallery: {}", url, e),
+        }
+    }
+    if gallery.sort == Sort::Date {
+        // stable, so images without a date stay ordered by name
+        images.sort_by_key(|(_, image)| (image.date.is_none(), image.date));
+    }
+
+    let settings = ImagesConfig {
+        sizes: THUMBNAIL_SIZES.to_string(),
+        ..settings.clone()
+    };
+    // colorbox groups the links by `rel`
+    let rel: String = format!("gallery-{}", gallery.dir)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let items: String = images
+        .iter()
+        .map(|(url, image)| {
+            // colorbox shows the title of the link below the image
+            let title = image_title(image)
+                .map(|title| format!(r#" title="{}""#, escape_attribute(&title)))
+                .unwrap_or_default();
+            let picture = picture_html(image, url, &[], &settings, subdir);
+            format!(
+                r#"<a href="{}" rel="{}"{}>{}</a>"#,
+                url, rel, title, picture
+            )
+        })
+        .collect();
+    Ok(format!(r#"<div class="pankatGallery">{}</div>"#, items))
+}
+
+pub struct GalleryPlugin;
+
+impl Plugin for GalleryPlugin {
+    fn name(&self) -> &'static str {
+        "gallery"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[[!gallery dir|glob [sort=date|name]]]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Thumbnail grid of the images in a directory with a lightbox, sorted by exif date by default"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Gallery::from_arguments(arguments).map(|_| ())
+    }
+
+    fn render(
+        &self,
+        conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        let gallery = Gallery::from_arguments(arguments)?;
+        // the file monitor re-renders the article when images are added to or removed from the
+        // directory
+        add_article_dependency(
+            conn,
+            &ArticleDependency {
+                src_file_name: article.src_file_name.clone(),
+                kind: DEPENDENCY_KIND.to_string(),
+                path: gallery.dir.clone(),
+            },
+        )?;
+
+        let cfg = crate::config::Config::try_get().ok_or("Gallery needs a configuration")?;
+        gallery_html(
+            conn,
+            &cfg.images,
+            &cfg.input,
+            &cfg.output,
+            &cfg.subdir,
+            &gallery,
+        )
+    }
+}
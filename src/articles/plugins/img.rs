@@ -1,14 +1,49 @@
+use super::{Arguments, Plugin};
 use crate::articles::ArticleWithTags;
+use crate::renderer::utils::escape_attribute;
+use diesel::SqliteConnection;
 use std::error::Error;
 
-pub fn img(input: &str, _article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    let mut parts = input.split_whitespace();
-    let img_url = parts.next().unwrap_or("").to_string();
-    let attributes = parts.collect::<Vec<&str>>().join(" ");
+pub struct Img;
 
-    let out = format!(
-        r#"<a href="{}"><img src="{}" {}></a>"#,
-        img_url, img_url, attributes
-    );
-    Ok(out.to_string())
+impl Plugin for Img {
+    fn name(&self) -> &'static str {
+        "img"
+    }
+
+    fn usage(&self) -> &'static str {
+        r#"[[!img path [attributes like class="noFancy"]]]"#
+    }
+
+    fn help(&self) -> &'static str {
+        "Image linking to the original, with responsive variants and alt/title from its metadata"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        match arguments.parameters.first() {
+            Some(parameter) if parameter.key.is_none() => Ok(()),
+            _ => Err("Image needs a path as first parameter".into()),
+        }
+    }
+
+    fn render(
+        &self,
+        conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        _article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        let img_url = &arguments.parameters[0].value;
+        // the other parameters are written as html attributes
+        let attributes = &arguments.parameters[1..];
+
+        let img = crate::images::img_html(conn, img_url, attributes).unwrap_or_else(|| {
+            format!(
+                r#"<img src="{}" {}>"#,
+                escape_attribute(img_url),
+                crate::images::attributes_html(attributes)
+            )
+        });
+        let out = format!(r#"<a href="{}">{}</a>"#, escape_attribute(img_url), img);
+        Ok(out.to_string())
+    }
 }
@@ -0,0 +1,102 @@
+use super::{Arguments, Plugin};
+use crate::articles::ArticleWithTags;
+use crate::db::article_dependencies::{add_article_dependency, ArticleDependency};
+use diesel::SqliteConnection;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// The `kind` of the `article_dependencies` on included files
+pub const DEPENDENCY_KIND: &str = "include";
+
+/// `[[!include snippets/disclaimer.md]]` inlines a file of the input directory before pandoc runs,
+/// its directives are evaluated like the ones of the including article
+#[derive(Default)]
+pub struct Include {
+    /// Defaults to the input directory of the configuration
+    pub input: Option<PathBuf>,
+}
+
+impl Include {
+    /// The path relative to the input directory, with `.` and `..` resolved, so each file is
+    /// recorded and detected in cycles by a single path
+    fn path(arguments: &Arguments) -> Result<String, Box<dyn Error>> {
+        arguments.allow_keys("include", &[])?;
+        let positional = arguments.positional();
+        let [path] = positional.as_slice() else {
+            return Err("Include needs exactly one path".into());
+        };
+        let mut segments: Vec<&str> = Vec::new();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop().ok_or_else(|| {
+                        format!(
+                            "Can't include '{}' from outside of the input directory",
+                            path
+                        )
+                    })?;
+                }
+                segment => segments.push(segment),
+            }
+        }
+        if segments.is_empty() {
+            return Err("Include needs the path of a file".into());
+        }
+        Ok(segments.join("/"))
+    }
+}
+
+impl Plugin for Include {
+    fn name(&self) -> &str {
+        "include"
+    }
+
+    fn usage(&self) -> &str {
+        "[[!include path]]"
+    }
+
+    fn help(&self) -> &str {
+        "Inlines a file relative to the input directory, articles are rendered again when it changes"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Include::path(arguments).map(|_| ())
+    }
+
+    /// Includes of includes are evaluated as well, a file including itself is a cycle
+    fn nested(&self, arguments: &Arguments) -> Option<String> {
+        Include::path(arguments).ok()
+    }
+
+    fn render(
+        &self,
+        conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        let path = Include::path(arguments)?;
+        // recorded before reading, so the article is rendered again once a missing file appears
+        add_article_dependency(
+            conn,
+            &ArticleDependency {
+                src_file_name: article.src_file_name.clone(),
+                kind: DEPENDENCY_KIND.to_string(),
+                path: path.clone(),
+            },
+        )?;
+
+        let input = match &self.input {
+            Some(input) => input.clone(),
+            None => {
+                let cfg =
+                    crate::config::Config::try_get().ok_or("Include needs a configuration")?;
+                cfg.input.clone()
+            }
+        };
+        let file = crate::safe_path::resolve_path(&input, &path)?;
+        let content = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Can't include '{}': {}", path, e))?;
+        Ok(content.trim_end_matches('\n').to_string())
+    }
+}
@@ -0,0 +1,122 @@
+use super::{Arguments, Plugin};
+use crate::articles::ArticleWithTags;
+use crate::config::MathConfig;
+use diesel::SqliteConnection;
+use regex::Regex;
+use std::error::Error;
+
+/// Left in the markdown by `[[!math]]`, pandoc passes it through as it is
+const PLACEHOLDER: &str = "<!-- pankat-math mode=";
+
+/// `--mathml` renders the formulas on the server, `--katex` leaves them to KaTeX in the browser
+pub const MODES: [&str; 2] = ["mathml", "katex"];
+
+/// The math mode pandoc renders `markdown` with: the one of its first `[[!math]]` (`None` for
+/// `[[!math off]]`), else the one of `[math]` in pankat.toml if enabled for all articles
+pub fn math_mode(markdown: &str, settings: &MathConfig) -> Option<String> {
+    let placeholder = Regex::new(r"<!-- pankat-math mode=(\w+) -->").unwrap();
+    let mode = match placeholder.captures(markdown) {
+        Some(captures) => captures[1].to_string(),
+        None if settings.enabled => "default".to_string(),
+        None => return None,
+    };
+    match mode.as_str() {
+        "off" => None,
+        "default" if MODES.contains(&settings.mode.as_str()) => Some(settings.mode.clone()),
+        "default" => Some(MODES[0].to_string()),
+        mode => Some(mode.to_string()),
+    }
+}
+
+/// `[[!math]]` renders the LaTeX formulas (`$x^2$` and `$$x^2$$`) of the article, with the mode of
+/// pankat.toml or the given one
+pub struct Math;
+
+impl Math {
+    fn mode(arguments: &Arguments) -> Result<&str, Box<dyn Error>> {
+        arguments.allow_keys("math", &[])?;
+        match arguments.positional().as_slice() {
+            [] => Ok("default"),
+            [mode] if *mode == "off" || MODES.contains(mode) => Ok(mode),
+            _ => Err("math takes mathml, katex, off or nothing".into()),
+        }
+    }
+}
+
+impl Plugin for Math {
+    fn name(&self) -> &str {
+        "math"
+    }
+
+    fn usage(&self) -> &str {
+        "[[!math [mathml|katex|off]]]"
+    }
+
+    fn help(&self) -> &str {
+        "Renders the LaTeX formulas of the article as MathML or with KaTeX, off if [math] enables it for all articles"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Math::mode(arguments).map(|_| ())
+    }
+
+    fn render(
+        &self,
+        _conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        _article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        Ok(format!(
+            "\n\n{}{} -->\n\n",
+            PLACEHOLDER,
+            Math::mode(arguments)?
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_math_mode() {
+        let off = MathConfig::default();
+        let all = MathConfig {
+            enabled: true,
+            mode: "katex".to_string(),
+            ..MathConfig::default()
+        };
+        let marker = |mode: &str| format!("a\n\n{}{} -->\n\nb", PLACEHOLDER, mode);
+
+        assert_eq!(math_mode("$x$", &off), None);
+        assert_eq!(math_mode("$x$", &all), Some("katex".to_string()));
+        assert_eq!(
+            math_mode(&marker("default"), &off),
+            Some("mathml".to_string())
+        );
+        assert_eq!(
+            math_mode(&marker("default"), &all),
+            Some("katex".to_string())
+        );
+        assert_eq!(
+            math_mode(&marker("mathml"), &all),
+            Some("mathml".to_string())
+        );
+        assert_eq!(math_mode(&marker("off"), &all), None);
+        let unknown = MathConfig {
+            mode: "mathjax".to_string(),
+            ..all
+        };
+        assert_eq!(math_mode("$x$", &unknown), Some("mathml".to_string()));
+
+        for (input, valid) in [
+            ("", true),
+            ("katex", true),
+            ("off", true),
+            ("mathjax", false),
+        ] {
+            let arguments = Math.parse(input).unwrap();
+            assert_eq!(Math.validate(&arguments).is_ok(), valid, "{}", input);
+        }
+    }
+}
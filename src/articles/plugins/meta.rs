@@ -1,18 +1,53 @@
+use super::{Arguments, Plugin};
 use crate::articles::ArticleWithTags;
 use chrono::NaiveDateTime;
 use regex::Regex;
 use std::error::Error;
 
-pub fn meta(input: &str, article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    let re = Regex::new(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}").unwrap();
-    if let Some(mat) = re.find(&input) {
-        if let Ok(parsed_time) = NaiveDateTime::parse_from_str(mat.as_str(), "%Y-%m-%d %H:%M") {
-            article.modification_date = Some(parsed_time);
-            return Ok("".to_string());
-        } else {
-            Err("Argument contains invalid characters (newlines or tabs)".into())
+pub struct Meta;
+
+impl Meta {
+    fn date(arguments: &Arguments) -> Result<NaiveDateTime, Box<dyn Error>> {
+        // posts before the parameter parser only had to contain the date somewhere
+        let date = arguments.get("date").unwrap_or(arguments.raw.as_str());
+        let re = Regex::new(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}").unwrap();
+        if let Some(mat) = re.find(date) {
+            if let Ok(parsed_time) = NaiveDateTime::parse_from_str(mat.as_str(), "%Y-%m-%d %H:%M") {
+                return Ok(parsed_time);
+            }
         }
-    } else {
-        Err("Argument contains invalid characters (newlines or tabs)".into())
+        Err(format!("Expected a date like \"2024-05-01 19:30\", got '{}'", date).into())
+    }
+}
+
+impl Plugin for Meta {
+    fn name(&self) -> &'static str {
+        "meta"
+    }
+
+    fn usage(&self) -> &'static str {
+        r#"[[!meta date="YYYY-MM-DD HH:MM"]]"#
+    }
+
+    fn help(&self) -> &'static str {
+        "Date of the article, defaults to the date of its first git commit"
+    }
+
+    fn parse(&self, input: &str) -> Result<Arguments, Box<dyn Error>> {
+        Ok(Arguments::parse_lenient(input))
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        arguments.allow_keys("meta", &["date"])?;
+        Meta::date(arguments).map(|_| ())
+    }
+
+    fn apply(
+        &self,
+        arguments: &Arguments,
+        article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        article.modification_date = Some(Meta::date(arguments)?);
+        Ok(())
     }
 }
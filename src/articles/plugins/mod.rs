@@ -1,8 +1,88 @@
+pub mod admonition;
+pub mod arguments;
 pub mod draft;
+pub mod embed;
+pub mod gallery;
 pub mod img;
+pub mod include;
+pub mod math;
 pub mod meta;
+pub mod pages;
+pub mod registry;
+pub mod script;
 pub mod series;
 pub mod specialpage;
 pub mod summary;
 pub mod tag;
 pub mod title;
+pub mod toc;
+
+pub use self::arguments::Arguments;
+use crate::articles::ArticleWithTags;
+use diesel::SqliteConnection;
+use std::error::Error;
+
+/// A `[[!name arguments]]` directive, `exec_plugin` calls `parse`, `validate`, `apply` and `render`
+/// in this order and replaces the directive with the result of `render`
+///
+/// Plugins are looked up by name in the `registry::PluginRegistry`.
+pub trait Plugin: Send + Sync {
+    /// Lowercase, e.g. `img` for `[[!img ...]]`
+    fn name(&self) -> &str;
+
+    /// The syntax, e.g. `[[!img path [attributes]]]`, listed by `pankat plugins`
+    fn usage(&self) -> &str;
+
+    /// What the directive does, listed by `pankat plugins`
+    fn help(&self) -> &str;
+
+    fn parse(&self, input: &str) -> Result<Arguments, Box<dyn Error>> {
+        Arguments::parse(input)
+    }
+
+    fn validate(&self, _arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// `Some` if `render` returns markdown with directives of its own, which are evaluated like the
+    /// ones of the article, e.g. the file of `[[!include]]`, the key (its path) detects cycles
+    fn nested(&self, _arguments: &Arguments) -> Option<String> {
+        None
+    }
+
+    /// Changes the metadata of the article, e.g. its title
+    fn apply(
+        &self,
+        _arguments: &Arguments,
+        _article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// The markdown or html the directive is replaced with before pandoc runs
+    fn render(
+        &self,
+        _conn: &mut SqliteConnection,
+        _arguments: &Arguments,
+        _article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        Ok("".to_string())
+    }
+}
+
+/// Rejects arguments which have to fit on a line, like titles and tags
+fn single_line(arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+    if arguments.raw.contains('\n') || arguments.raw.contains('\t') {
+        Err("Argument contains invalid characters (newlines or tabs)".into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Renders the partial `templates/partials/<name>.html` of the configured assets as a block of its
+/// own in the markdown
+fn partial(name: &str, data: &serde_json::Value) -> Result<String, Box<dyn Error>> {
+    let cfg = crate::config::Config::try_get().ok_or("Partials need a configuration")?;
+    let html = crate::renderer::html::render_partial(&cfg.assets, name, data)?;
+    Ok(format!("\n\n{}\n\n", html))
+}
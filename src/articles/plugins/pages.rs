@@ -0,0 +1,204 @@
+use super::{partial, Arguments, Plugin};
+use crate::articles::ArticleWithTags;
+use crate::db::article_dependencies::{add_article_dependency, ArticleDependency};
+use diesel::SqliteConnection;
+use serde_json::json;
+use std::error::Error;
+
+/// The `kind` of the `article_dependencies` on listed articles, the `path` is one of
+/// `dependency_paths`
+pub const DEPENDENCY_KIND: &str = "pages";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// Newest first
+    Date,
+    Title,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub tag: Option<String>,
+    pub series: Option<String>,
+    pub limit: Option<usize>,
+    pub sort: Sort,
+    pub reverse: bool,
+}
+
+/// Whether a `[[!pages]]` lists the article at all
+fn listed(article: &ArticleWithTags) -> bool {
+    article.draft != Some(true) && article.special_page != Some(true)
+}
+
+/// The `article_dependencies` paths by which a change of `article` reaches the `[[!pages]]` listing
+/// it: `*` for the ones without a filter, `tag:<tag>` and `series:<series>`
+pub fn dependency_paths(article: &ArticleWithTags) -> Vec<String> {
+    if !listed(article) {
+        return Vec::new();
+    }
+    let mut paths = vec!["*".to_string()];
+    for tag in article.tags.iter().flatten() {
+        paths.push(format!("tag:{}", tag));
+    }
+    if let Some(series) = &article.series {
+        paths.push(format!("series:{}", series));
+    }
+    paths
+}
+
+/// `[[!pages tag=nix series="libnix" limit=10 sort=date]]` lists the visible articles with the tag
+/// and of the series, like `inline` of ikiwiki, see `partials/pages.html`
+///
+/// The listing articles are rendered again when a listed article changes, see
+/// `articles::listing_articles`.
+pub struct Pages;
+
+impl Pages {
+    pub fn query(arguments: &Arguments) -> Result<Query, Box<dyn Error>> {
+        arguments.allow_keys("pages", &["tag", "series", "limit", "sort", "reverse"])?;
+        if !arguments.positional().is_empty() {
+            return Err("pages only takes tag=, series=, limit=, sort= and reverse=".into());
+        }
+        let limit = match arguments.get("limit") {
+            Some(limit) => match limit.parse::<usize>() {
+                Ok(limit) if limit > 0 => Some(limit),
+                _ => {
+                    return Err(
+                        format!("limit has to be a positive number, not '{}'", limit).into(),
+                    )
+                }
+            },
+            None => None,
+        };
+        let sort = match arguments.get("sort") {
+            None | Some("date") => Sort::Date,
+            Some("title") => Sort::Title,
+            Some(sort) => {
+                return Err(format!("sort has to be date or title, not '{}'", sort).into())
+            }
+        };
+        let reverse = match arguments.get("reverse") {
+            None | Some("no") => false,
+            Some("yes") => true,
+            Some(reverse) => {
+                return Err(format!("reverse has to be yes or no, not '{}'", reverse).into())
+            }
+        };
+        Ok(Query {
+            tag: arguments.get("tag").map(str::to_string),
+            series: arguments.get("series").map(str::to_string),
+            limit,
+            sort,
+            reverse,
+        })
+    }
+
+    /// The articles `query` lists on the page of `article`, which never lists itself
+    pub fn pages(
+        conn: &mut SqliteConnection,
+        query: &Query,
+        article: &ArticleWithTags,
+    ) -> Result<Vec<ArticleWithTags>, Box<dyn Error>> {
+        let articles = match (&query.tag, &query.series) {
+            (_, Some(series)) => crate::db::article::get_visible_articles_by_series(conn, series)?,
+            (Some(tag), None) => {
+                crate::db::article::get_visible_articles_by_tag(conn, tag.clone())?
+            }
+            (None, None) => crate::db::article::get_visible_articles(conn)?,
+        };
+        let mut pages: Vec<ArticleWithTags> = articles
+            .into_iter()
+            .filter(|page| listed(page) && page.src_file_name != article.src_file_name)
+            .filter(|page| match &query.tag {
+                Some(tag) => page.tags.iter().flatten().any(|t| t == tag),
+                None => true,
+            })
+            .collect();
+        match query.sort {
+            Sort::Date => pages.sort_by(|a, b| {
+                b.modification_date
+                    .cmp(&a.modification_date)
+                    .then_with(|| a.title.cmp(&b.title))
+            }),
+            Sort::Title => pages.sort_by(|a, b| {
+                let title = |page: &ArticleWithTags| page.title.clone().unwrap_or_default();
+                title(a).to_lowercase().cmp(&title(b).to_lowercase())
+            }),
+        }
+        if query.reverse {
+            pages.reverse();
+        }
+        if let Some(limit) = query.limit {
+            pages.truncate(limit);
+        }
+        Ok(pages)
+    }
+
+    /// The data of the partial
+    pub fn data(pages: &[ArticleWithTags]) -> serde_json::Value {
+        let subdir = crate::config::Config::try_get()
+            .map(|cfg| cfg.subdir.display().to_string())
+            .unwrap_or_default();
+        let pages: Vec<serde_json::Value> = pages
+            .iter()
+            .map(|page| {
+                json!({
+                    "Title": page.title,
+                    "Href": format!("{}/{}", subdir, page.dst_file_name),
+                    "Date": crate::renderer::utils::date_and_time(&page.modification_date),
+                    "Summary": page.summary,
+                })
+            })
+            .collect();
+        json!({ "Pages": pages })
+    }
+}
+
+impl Plugin for Pages {
+    fn name(&self) -> &str {
+        "pages"
+    }
+
+    fn usage(&self) -> &str {
+        r#"[[!pages [tag=name] [series="name"] [limit=10] [sort=date|title] [reverse=yes]]]"#
+    }
+
+    fn help(&self) -> &str {
+        "Lists the articles with the tag and of the series, rendered again when one of them changes"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Pages::query(arguments).map(|_| ())
+    }
+
+    fn render(
+        &self,
+        conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        let query = Pages::query(arguments)?;
+        let mut paths: Vec<String> = Vec::new();
+        if let Some(tag) = &query.tag {
+            paths.push(format!("tag:{}", tag));
+        }
+        if let Some(series) = &query.series {
+            paths.push(format!("series:{}", series));
+        }
+        if paths.is_empty() {
+            paths.push("*".to_string());
+        }
+        for path in paths {
+            add_article_dependency(
+                conn,
+                &ArticleDependency {
+                    src_file_name: article.src_file_name.clone(),
+                    kind: DEPENDENCY_KIND.to_string(),
+                    path,
+                },
+            )?;
+        }
+        let pages = Pages::pages(conn, &query, article)?;
+        partial("pages", &Pages::data(&pages))
+    }
+}
@@ -0,0 +1,110 @@
+use super::script::ScriptPlugin;
+use super::Plugin;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Maps the names of directives to their plugins, the built-in ones are registered on creation
+pub struct PluginRegistry {
+    plugins: RwLock<BTreeMap<String, Arc<dyn Plugin>>>,
+}
+
+impl PluginRegistry {
+    /// A registry with the built-in plugins, independent of the singleton
+    pub fn new() -> PluginRegistry {
+        let registry = PluginRegistry {
+            plugins: RwLock::new(BTreeMap::new()),
+        };
+        registry.register_builtin();
+        registry
+    }
+
+    /// Get the singleton instance of `PluginRegistry`
+    pub fn instance() -> &'static PluginRegistry {
+        static INSTANCE: OnceLock<PluginRegistry> = OnceLock::new();
+        INSTANCE.get_or_init(PluginRegistry::new)
+    }
+
+    fn register_builtin(&self) {
+        self.register(Arc::new(super::admonition::NOTE));
+        self.register(Arc::new(super::admonition::TIP));
+        self.register(Arc::new(super::admonition::WARNING));
+        self.register(Arc::new(super::draft::Draft));
+        self.register(Arc::new(super::embed::Asciinema));
+        self.register(Arc::new(super::embed::Gist));
+        self.register(Arc::new(super::embed::Youtube));
+        self.register(Arc::new(super::gallery::GalleryPlugin));
+        self.register(Arc::new(super::img::Img));
+        self.register(Arc::new(super::include::Include::default()));
+        self.register(Arc::new(super::math::Math));
+        self.register(Arc::new(super::meta::Meta));
+        self.register(Arc::new(super::pages::Pages));
+        self.register(Arc::new(super::series::Series));
+        self.register(Arc::new(super::specialpage::SpecialPage));
+        self.register(Arc::new(super::summary::Summary));
+        self.register(Arc::new(super::tag::Tag));
+        self.register(Arc::new(super::title::Title));
+        self.register(Arc::new(super::toc::Toc));
+    }
+
+    /// Adds `plugin`, replacing a plugin of the same name
+    pub fn register(&self, plugin: Arc<dyn Plugin>) {
+        self.plugins
+            .write()
+            .unwrap()
+            .insert(plugin.name().to_lowercase(), plugin);
+    }
+
+    /// Registers the `*.rhai` scripts in `dir` (usually `assets/plugins`), scripts which fail to
+    /// compile or would replace an already registered plugin are skipped
+    pub fn register_scripts(&self, dir: &Path) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            match ScriptPlugin::load(&path) {
+                Ok(plugin) if self.get(plugin.name()).is_some() => println!(
+                    "Skipping plugin script {}: [[!{}]] is already registered",
+                    path.display(),
+                    plugin.name()
+                ),
+                Ok(plugin) => self.register(Arc::new(plugin)),
+                Err(e) => println!("Error loading plugin script {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Directive names are case insensitive
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Plugin>> {
+        self.plugins
+            .read()
+            .unwrap()
+            .get(&name.to_lowercase())
+            .cloned()
+    }
+
+    /// All plugins ordered by name
+    pub fn plugins(&self) -> Vec<Arc<dyn Plugin>> {
+        self.plugins.read().unwrap().values().cloned().collect()
+    }
+
+    /// The usage and help text of every plugin, printed by `pankat plugins`
+    pub fn help(&self) -> String {
+        self.plugins()
+            .iter()
+            .map(|plugin| format!("{}\n    {}\n", plugin.usage(), plugin.help()))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl Default for PluginRegistry {
+    fn default() -> Self {
+        PluginRegistry::new()
+    }
+}
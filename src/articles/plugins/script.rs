@@ -0,0 +1,260 @@
+use super::{Arguments, Plugin};
+use crate::articles::ArticleWithTags;
+use diesel::SqliteConnection;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use std::error::Error;
+use std::path::Path;
+
+/// Scripts are stopped after this many operations, so a loop can't hang a build
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// An engine without access to files, modules or `eval`, with limits on the size of values
+fn sandboxed_engine(name: &str) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1024 * 1024);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+
+    let prefix = format!("Plugin script {}:", name);
+    engine.on_print(move |text| println!("{} {}", prefix, text));
+    let prefix = format!("Plugin script {}:", name);
+    engine.on_debug(move |text, _, position| println!("{} {} ({})", prefix, text, position));
+    engine
+}
+
+/// A directive implemented by a Rhai script `assets/plugins/<name>.rhai`, e.g.
+///
+/// ```rhai
+/// fn usage() { "[[!kbd keys]]" }
+/// fn help() { "Keyboard shortcuts like Ctrl+C" }
+/// fn render(arguments, article) { `<kbd>${arguments.raw}</kbd>` }
+/// ```
+///
+/// All functions are optional:
+///
+/// * `render(arguments, article)` returns the markdown or html the directive is replaced with
+/// * `apply(arguments, article)` returns a map of metadata changes: `title`, `summary`, `series`,
+///   `draft` and `tags` (which are added to the tags of the article)
+///
+/// `arguments` is a map of `raw`, `positional` and `named` (see `Arguments`), `article` a copy of
+/// the metadata of the article: `src_file_name`, `title`, `date`, `summary`, `series`, `tags` and
+/// `draft`.
+pub struct ScriptPlugin {
+    name: String,
+    usage: String,
+    help: String,
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptPlugin {
+    pub fn new(name: &str, source: &str) -> Result<ScriptPlugin, Box<dyn Error>> {
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("'{}' is no valid directive name", name).into());
+        }
+        let name = name.to_lowercase();
+        let engine = sandboxed_engine(&name);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| format!("Plugin script {}: {}", name, e))?;
+
+        let mut plugin = ScriptPlugin {
+            usage: format!("[[!{} ...]]", name),
+            help: format!("Script assets/plugins/{}.rhai", name),
+            name,
+            engine,
+            ast,
+        };
+        if let Some(usage) = plugin.call_text("usage")? {
+            plugin.usage = usage;
+        }
+        if let Some(help) = plugin.call_text("help")? {
+            plugin.help = help;
+        }
+        Ok(plugin)
+    }
+
+    pub fn load(path: &Path) -> Result<ScriptPlugin, Box<dyn Error>> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        ScriptPlugin::new(&name, &std::fs::read_to_string(path)?)
+    }
+
+    fn has_fn(&self, name: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|f| f.name == name && f.params.len() == arity)
+    }
+
+    fn call(&self, function: &str, args: Vec<Dynamic>) -> Result<Dynamic, Box<dyn Error>> {
+        self.engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, function, args)
+            .map_err(|e| format!("Plugin script {}: {}", self.name, e).into())
+    }
+
+    /// The result of `usage()` or `help()`, if the script defines it
+    fn call_text(&self, function: &str) -> Result<Option<String>, Box<dyn Error>> {
+        if !self.has_fn(function, 0) {
+            return Ok(None);
+        }
+        let result = self.call(function, Vec::new())?;
+        result.into_string().map(Some).map_err(|t| {
+            format!(
+                "{}() of {} returned {}, not a string",
+                function, self.name, t
+            )
+            .into()
+        })
+    }
+}
+
+fn arguments_map(arguments: &Arguments) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("raw".into(), arguments.raw.trim().into());
+    let positional: Array = arguments
+        .positional()
+        .into_iter()
+        .map(|value| value.to_string().into())
+        .collect();
+    map.insert("positional".into(), positional.into());
+    let named: Map = arguments
+        .named()
+        .into_iter()
+        .map(|(key, value)| (key.into(), value.to_string().into()))
+        .collect();
+    map.insert("named".into(), named.into());
+    map.into()
+}
+
+fn article_map(article: &ArticleWithTags) -> Dynamic {
+    let optional = |value: &Option<String>| value.clone().map_or(Dynamic::UNIT, Dynamic::from);
+    let mut map = Map::new();
+    map.insert("src_file_name".into(), article.src_file_name.clone().into());
+    map.insert("title".into(), optional(&article.title));
+    map.insert(
+        "date".into(),
+        optional(
+            &article
+                .modification_date
+                .map(|date| date.format("%Y-%m-%d %H:%M").to_string()),
+        ),
+    );
+    map.insert("summary".into(), optional(&article.summary));
+    map.insert("series".into(), optional(&article.series));
+    let tags: Array = article
+        .tags
+        .iter()
+        .flatten()
+        .cloned()
+        .map(Dynamic::from)
+        .collect();
+    map.insert("tags".into(), tags.into());
+    map.insert("draft".into(), article.draft.unwrap_or(false).into());
+    map.into()
+}
+
+fn text(key: &str, value: Dynamic) -> Result<String, Box<dyn Error>> {
+    value
+        .into_string()
+        .map_err(|t| format!("'{}' has to be a string, not {}", key, t).into())
+}
+
+impl Plugin for ScriptPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn usage(&self) -> &str {
+        &self.usage
+    }
+
+    fn help(&self) -> &str {
+        &self.help
+    }
+
+    /// Scripts get free text like `[[!kbd "]]` as `raw` without parameters
+    fn parse(&self, input: &str) -> Result<Arguments, Box<dyn Error>> {
+        Ok(Arguments::parse_lenient(input))
+    }
+
+    fn apply(
+        &self,
+        arguments: &Arguments,
+        article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.has_fn("apply", 2) {
+            return Ok(());
+        }
+        let result = self.call(
+            "apply",
+            vec![arguments_map(arguments), article_map(article)],
+        )?;
+        if result.is_unit() {
+            return Ok(());
+        }
+        let changes = result
+            .try_cast::<Map>()
+            .ok_or_else(|| format!("apply() of {} has to return a map", self.name))?;
+        for (key, value) in changes {
+            match key.as_str() {
+                "title" => article.title = Some(text(&key, value)?),
+                "summary" => article.summary = Some(text(&key, value)?),
+                "series" => article.series = Some(text(&key, value)?),
+                "draft" => {
+                    article.draft = Some(
+                        value
+                            .as_bool()
+                            .map_err(|t| format!("'draft' has to be a bool, not {}", t))?,
+                    )
+                }
+                "tags" => {
+                    let tags = value
+                        .try_cast::<Array>()
+                        .ok_or("'tags' has to be an array of strings")?;
+                    let article_tags = article.tags.get_or_insert_with(Vec::new);
+                    for tag in tags {
+                        let tag = text("tags", tag)?;
+                        if !article_tags.contains(&tag) {
+                            article_tags.push(tag);
+                        }
+                    }
+                }
+                other => {
+                    return Err(format!(
+                        "apply() of {} returned unknown metadata '{}'",
+                        self.name, other
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn render(
+        &self,
+        _conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        if !self.has_fn("render", 2) {
+            return Ok("".to_string());
+        }
+        let result = self.call(
+            "render",
+            vec![arguments_map(arguments), article_map(article)],
+        )?;
+        if result.is_unit() {
+            return Ok("".to_string());
+        }
+        text("render()", result)
+    }
+}
@@ -1,12 +1,36 @@
+use super::{single_line, Arguments, Plugin};
 use crate::articles::ArticleWithTags;
 use std::error::Error;
 
-pub fn series(input: &str, article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    if input.contains('\n') || input.contains('\t') {
-        Err("Argument contains invalid characters (newlines or tabs)".into())
-    } else {
-        let series = input.to_string();
-        article.series = Some(series);
-        Ok("".to_string())
+pub struct Series;
+
+impl Plugin for Series {
+    fn name(&self) -> &'static str {
+        "series"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[[!series name]]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Adds the article to a series, articles of a series link to their neighbours"
+    }
+
+    fn parse(&self, input: &str) -> Result<Arguments, Box<dyn Error>> {
+        Ok(Arguments::parse_lenient(input))
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        single_line(arguments)
+    }
+
+    fn apply(
+        &self,
+        arguments: &Arguments,
+        article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        article.series = Some(arguments.text());
+        Ok(())
     }
 }
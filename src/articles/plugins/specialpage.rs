@@ -1,7 +1,28 @@
+use super::{Arguments, Plugin};
 use crate::articles::ArticleWithTags;
 use std::error::Error;
 
-pub fn specialpage(_input: &str, article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    article.special_page = Some(true);
-    Ok("".to_string())
+pub struct SpecialPage;
+
+impl Plugin for SpecialPage {
+    fn name(&self) -> &'static str {
+        "specialpage"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[[!specialpage]]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Page like 'about' which is not part of the timeline and has no navigation"
+    }
+
+    fn apply(
+        &self,
+        _arguments: &Arguments,
+        article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        article.special_page = Some(true);
+        Ok(())
+    }
 }
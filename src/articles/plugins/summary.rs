@@ -1,12 +1,39 @@
+use super::{Arguments, Plugin};
 use crate::articles::ArticleWithTags;
 use std::error::Error;
 
-pub fn summary(input: &str, article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    if input.contains('\n') || input.contains('\t') {
-        Err("Argument contains invalid characters (newlines or tabs)".into())
-    } else {
-        let summary = input.trim().to_string();
-        article.summary = Some(summary);
-        Ok("".to_string())
+pub struct Summary;
+
+impl Plugin for Summary {
+    fn name(&self) -> &'static str {
+        "summary"
+    }
+
+    fn usage(&self) -> &'static str {
+        "[[!summary text]]"
+    }
+
+    fn help(&self) -> &'static str {
+        "Summary of the article shown on the timeline"
+    }
+
+    fn parse(&self, input: &str) -> Result<Arguments, Box<dyn Error>> {
+        Ok(Arguments::parse_lenient(input))
+    }
+
+    fn apply(
+        &self,
+        arguments: &Arguments,
+        article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        // summaries may span lines, the timeline shows them as one paragraph
+        let text = arguments.text();
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        article.summary = Some(lines.join(" "));
+        Ok(())
     }
 }
@@ -1,12 +1,43 @@
+use super::{single_line, Arguments, Plugin};
 use crate::articles::ArticleWithTags;
 use std::error::Error;
 
-pub fn tag(input: &str, article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    if input.contains('\n') || input.contains('\t') {
-        Err("Argument contains invalid characters (newlines or tabs)".into())
-    } else {
-        let tag = input.to_string();
-        article.tags = Some(tag.split_whitespace().map(|s| s.to_string()).collect());
-        Ok("".to_string())
+pub struct Tag;
+
+impl Plugin for Tag {
+    fn name(&self) -> &'static str {
+        "tag"
+    }
+
+    fn usage(&self) -> &'static str {
+        r#"[[!tag tag1 "tag 2" ...]]"#
+    }
+
+    fn help(&self) -> &'static str {
+        "Tags of the article, listed on the timeline, quote tags with whitespace"
+    }
+
+    fn parse(&self, input: &str) -> Result<Arguments, Box<dyn Error>> {
+        Ok(Arguments::parse_lenient(input))
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        single_line(arguments)?;
+        arguments.allow_keys("tag", &[])
+    }
+
+    fn apply(
+        &self,
+        arguments: &Arguments,
+        article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        // posts before the parameter parser may have tags like `5"` which don't parse
+        let tags = if arguments.parameters.is_empty() {
+            arguments.raw.split_whitespace().collect()
+        } else {
+            arguments.positional()
+        };
+        article.tags = Some(tags.into_iter().map(|s| s.to_string()).collect());
+        Ok(())
     }
 }
@@ -1,12 +1,36 @@
+use super::{single_line, Arguments, Plugin};
 use crate::articles::ArticleWithTags;
 use std::error::Error;
 
-pub fn title(input: &str, article: &mut ArticleWithTags) -> Result<String, Box<dyn Error>> {
-    if input.contains('\n') || input.contains('\t') {
-        Err("Argument contains invalid characters (newlines or tabs)".into())
-    } else {
-        let title = input.trim().to_string();
-        article.title = Some(title);
-        Ok("".to_string())
+pub struct Title;
+
+impl Plugin for Title {
+    fn name(&self) -> &'static str {
+        "title"
+    }
+
+    fn usage(&self) -> &'static str {
+        r#"[[!title text]] or [[!title "text"]]"#
+    }
+
+    fn help(&self) -> &'static str {
+        "Title of the article, defaults to the file name"
+    }
+
+    fn parse(&self, input: &str) -> Result<Arguments, Box<dyn Error>> {
+        Ok(Arguments::parse_lenient(input))
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        single_line(arguments)
+    }
+
+    fn apply(
+        &self,
+        arguments: &Arguments,
+        article: &mut ArticleWithTags,
+    ) -> Result<(), Box<dyn Error>> {
+        article.title = Some(arguments.text());
+        Ok(())
     }
 }
@@ -0,0 +1,171 @@
+use super::{Arguments, Plugin};
+use crate::articles::ArticleWithTags;
+use diesel::SqliteConnection;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
+use std::error::Error;
+
+/// Headings are only known after pandoc ran, so `[[!toc]]` leaves this comment which `fill_toc`
+/// replaces
+const PLACEHOLDER: &str = "<!-- pankat-toc levels=";
+
+const DEFAULT_LEVELS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TocEntry {
+    /// 1 for the topmost headings of the article
+    #[serde(rename = "Level")]
+    pub level: usize,
+    #[serde(rename = "Id")]
+    pub id: String,
+    /// Html without tags
+    #[serde(rename = "Text")]
+    pub text: String,
+}
+
+/// The headings of the html of pandoc with an id, up to `levels` levels below the topmost ones
+///
+/// The id is either the one of the heading or the one of the anchor `create-anchors.lua` adds.
+pub fn toc_entries(html: &str, levels: usize) -> Vec<TocEntry> {
+    let heading = Regex::new(r"(?s)<h([1-6])([^>]*)>(.*?)</h[1-6]>").unwrap();
+    let id = Regex::new(r#"\bid="([^"]+)""#).unwrap();
+    let anchor = Regex::new(r##"href="#([^"]+)""##).unwrap();
+    let tag = Regex::new(r"<[^>]*>").unwrap();
+
+    let entries: Vec<TocEntry> = heading
+        .captures_iter(html)
+        .filter_map(|captures| {
+            let id = id
+                .captures(&captures[2])
+                .or_else(|| anchor.captures(&captures[3]))?[1]
+                .to_string();
+            let text = tag.replace_all(&captures[3], "");
+            Some(TocEntry {
+                level: captures[1].parse().unwrap(),
+                id,
+                text: text.split_whitespace().collect::<Vec<&str>>().join(" "),
+            })
+        })
+        .collect();
+
+    let top = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+    entries
+        .into_iter()
+        .filter(|entry| entry.level < top + levels)
+        .map(|entry| TocEntry {
+            level: entry.level - top + 1,
+            ..entry
+        })
+        .collect()
+}
+
+/// Replaces the placeholders of `[[!toc]]` in the html of pandoc with `partials/toc.html`
+pub fn fill_toc(html: &str) -> Result<String, Box<dyn Error>> {
+    if !html.contains(PLACEHOLDER) {
+        return Ok(html.to_string());
+    }
+    let cfg = crate::config::Config::get();
+    let placeholder = Regex::new(r"<!-- pankat-toc levels=(\d+) -->").unwrap();
+    let mut result = String::new();
+    let mut last = 0;
+    for captures in placeholder.captures_iter(html) {
+        let range = captures.get(0).unwrap().range();
+        let levels: usize = captures[1].parse()?;
+        result += &html[last..range.start];
+        result += &crate::renderer::html::render_partial(
+            &cfg.assets,
+            "toc",
+            &json!({ "Entries": toc_entries(html, levels) }),
+        )?;
+        last = range.end;
+    }
+    result += &html[last..];
+    Ok(result)
+}
+
+pub struct Toc;
+
+impl Toc {
+    fn levels(arguments: &Arguments) -> Result<usize, Box<dyn Error>> {
+        arguments.allow_keys("toc", &["levels"])?;
+        if !arguments.positional().is_empty() {
+            return Err("toc only takes levels=".into());
+        }
+        match arguments.get("levels") {
+            Some(levels) => match levels.parse::<usize>() {
+                Ok(levels @ 1..=6) => Ok(levels),
+                _ => Err(format!("levels has to be 1 to 6, not '{}'", levels).into()),
+            },
+            None => Ok(DEFAULT_LEVELS),
+        }
+    }
+}
+
+impl Plugin for Toc {
+    fn name(&self) -> &str {
+        "toc"
+    }
+
+    fn usage(&self) -> &str {
+        "[[!toc [levels=3]]]"
+    }
+
+    fn help(&self) -> &str {
+        "Static table of contents of the headings of the article"
+    }
+
+    fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+        Toc::levels(arguments).map(|_| ())
+    }
+
+    fn render(
+        &self,
+        _conn: &mut SqliteConnection,
+        arguments: &Arguments,
+        _article: &ArticleWithTags,
+    ) -> Result<String, Box<dyn Error>> {
+        Ok(format!(
+            "\n\n{}{} -->\n\n",
+            PLACEHOLDER,
+            Toc::levels(arguments)?
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toc_entries() {
+        let html = r##"<section id="intro" class="level2" data-number="1">
+<h2 data-number="1" id="intro"><span class="header-section-number">1</span> Intro &amp; <em>setup</em><a class="glyphicon glyphicon-link" aria-label="Anchor" href="#intro"></a></h2>
+<h3 data-number="1.1"><span class="header-section-number">1.1</span> Nix<a class="glyphicon glyphicon-link" aria-label="Anchor" href="#nix"></a></h3>
+<h4 id="deep">Deep</h4>
+<h3>No id</h3>
+</section>"##;
+        let entry = |level, id: &str, text: &str| TocEntry {
+            level,
+            id: id.to_string(),
+            text: text.to_string(),
+        };
+        assert_eq!(
+            toc_entries(html, 3),
+            vec![
+                entry(1, "intro", "1 Intro &amp; setup"),
+                entry(2, "nix", "1.1 Nix"),
+                entry(3, "deep", "Deep"),
+            ]
+        );
+        assert_eq!(
+            toc_entries(html, 2),
+            vec![
+                entry(1, "intro", "1 Intro &amp; setup"),
+                entry(2, "nix", "1.1 Nix")
+            ]
+        );
+        assert_eq!(toc_entries("<p>no headings</p>", 3), vec![]);
+        assert_eq!(fill_toc("<p>no toc</p>").unwrap(), "<p>no toc</p>");
+    }
+}
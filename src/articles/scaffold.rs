@@ -0,0 +1,131 @@
+use super::plugins::arguments::quote;
+use super::utils::{article_src_file_name_to_title, create_src_file_name};
+use crate::config;
+use chrono::NaiveDateTime;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// A new article as requested by `pankat new` or `POST /api/articles`
+#[derive(Debug, Clone, Default)]
+pub struct ArticleScaffold {
+    pub title: String,
+    pub tags: Vec<String>,
+    pub series: Option<String>,
+    pub draft: bool,
+}
+
+fn check_argument(name: &str, value: &str) -> Result<(), Error> {
+    if value.contains('\n') || value.contains('\t') || value.contains("]]") {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{} contains invalid characters (newlines, tabs or ']]')",
+                name
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// `[[!title ...]]` and `[[!series ...]]` take their text as it is unless it starts with a quote
+fn text_argument(value: &str) -> String {
+    if value.starts_with('"') {
+        quote(value)
+    } else {
+        value.to_string()
+    }
+}
+
+impl ArticleScaffold {
+    fn validate(&self) -> Result<(), Error> {
+        if self.title.trim().is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Title is empty"));
+        }
+        check_argument("Title", &self.title)?;
+        // e.g. "../" is reduced to nothing by `create_src_file_name`
+        if self.src_file_name().file_name() == Some(".mdwn".as_ref()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Title '{}' doesn't result in a valid file name", self.title),
+            ));
+        }
+        for tag in &self.tags {
+            check_argument("Tag", tag)?;
+        }
+        if let Some(series) = &self.series {
+            check_argument("Series", series)?;
+        }
+        Ok(())
+    }
+
+    /// The `src_file_name` of the new article, e.g. `posts/My_new_article.mdwn`
+    pub fn src_file_name(&self) -> PathBuf {
+        create_src_file_name(&self.title)
+    }
+
+    /// Returns the markdown of the new article, `date` is written in the format the `meta` plugin parses
+    pub fn source(&self, date: NaiveDateTime) -> String {
+        let mut source = format!("[[!meta date=\"{}\"]]\n", date.format("%Y-%m-%d %H:%M"));
+
+        // the title is derived from the file name, only spell it out if the slug lost something
+        let title = self.title.trim();
+        if article_src_file_name_to_title(&self.src_file_name()) != title {
+            source += &format!("[[!title {}]]\n", text_argument(title));
+        }
+        let tags: Vec<&str> = self
+            .tags
+            .iter()
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if !tags.is_empty() {
+            // multi-word tags are quoted
+            let tags: Vec<String> = tags.into_iter().map(quote).collect();
+            source += &format!("[[!tag {}]]\n", tags.join(" "));
+        }
+        if let Some(series) = self.series.as_deref().map(str::trim) {
+            if !series.is_empty() {
+                source += &format!("[[!series {}]]\n", text_argument(series));
+            }
+        }
+        if self.draft {
+            source += "[[!draft]]\n";
+        }
+        source += &format!("\n# {}\n\n", title);
+        source
+    }
+
+    /// Writes the new article below `cfg.input` and returns its `src_file_name`,
+    /// an existing article is never overwritten (`ErrorKind::AlreadyExists`)
+    pub fn create(&self) -> Result<String, Error> {
+        let cfg = config::Config::get();
+        self.validate()?;
+
+        let src_file_name = self.src_file_name();
+        let path = cfg.input.join(&src_file_name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // hard_link fails if the target exists, so the check and the write happen atomically and
+        // the file monitor only ever sees the complete file
+        let mut tmp_path = path.clone();
+        tmp_path.set_extension("mdwn.pankat-tmp");
+        std::fs::write(
+            &tmp_path,
+            self.source(chrono::Local::now().naive_local()).as_bytes(),
+        )?;
+        let result = std::fs::hard_link(&tmp_path, &path);
+        std::fs::remove_file(&tmp_path)?;
+        result.map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => Error::new(
+                ErrorKind::AlreadyExists,
+                format!("Article '{}' already exists", src_file_name.display()),
+            ),
+            _ => e,
+        })?;
+
+        println!("Created new article '{}'", src_file_name.display());
+        Ok(src_file_name.display().to_string())
+    }
+}
@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::articles::eval_plugins;
     use crate::articles::ArticleWithTags;
+    use crate::articles::Diagnostic;
+    use crate::articles::{eval_plugins, eval_plugins_with};
+    use crate::db::tests::establish_connection_and_initialize_schema;
 
     #[test]
     fn test_title() {
@@ -39,7 +41,8 @@ mod tests {
             tags: None,
         };
 
-        let result = eval_plugins(&input, &mut article);
+        let mut conn = establish_connection_and_initialize_schema();
+        let result = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new());
 
         assert!(result.is_ok());
 
@@ -91,7 +94,8 @@ mod tests {
             tags: None,
         };
 
-        let result = eval_plugins(&input, &mut article);
+        let mut conn = establish_connection_and_initialize_schema();
+        let result = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new());
 
         assert!(result.is_ok());
 
@@ -143,7 +147,8 @@ mod tests {
             tags: None,
         };
 
-        let result = eval_plugins(&input, &mut article);
+        let mut conn = establish_connection_and_initialize_schema();
+        let result = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new());
 
         assert!(result.is_ok());
 
@@ -201,7 +206,8 @@ mod tests {
             tags: None,
         };
 
-        let result = eval_plugins(&input, &mut article);
+        let mut conn = establish_connection_and_initialize_schema();
+        let result = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new());
 
         assert!(result.is_ok());
 
@@ -254,7 +260,8 @@ mod tests {
             tags: None,
         };
 
-        let result = eval_plugins(&input, &mut article);
+        let mut conn = establish_connection_and_initialize_schema();
+        let result = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new());
 
         assert!(result.is_ok());
 
@@ -307,7 +314,8 @@ mod tests {
             tags: vec!["foo".to_string(), "bar".to_string(), "asdf".to_string()].into(),
         };
 
-        let result = eval_plugins(&input, &mut article);
+        let mut conn = establish_connection_and_initialize_schema();
+        let result = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new());
 
         assert!(result.is_ok());
 
@@ -324,6 +332,44 @@ mod tests {
         assert_eq!(article, article_expected);
     }
 
+    #[test]
+    fn test_tag_quoted() {
+        let article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+
+        for (input, tags) in [
+            (
+                r#"[[!tag foo "home manager" asdf]]"#,
+                vec!["foo", "home manager", "asdf"],
+            ),
+            // tags which don't parse are split at whitespace like before quoting
+            (r#"[[!tag 5" screen]]"#, vec!["5\"", "screen"]),
+        ] {
+            let mut article = article.clone();
+            let document =
+                eval_plugins(&mut conn, &input.to_string(), &mut article, &mut Vec::new()).unwrap();
+            assert_eq!(document, "");
+            assert_eq!(
+                article.tags,
+                Some(tags.into_iter().map(str::to_string).collect())
+            );
+        }
+    }
+
     #[test]
     fn test_summary() {
         let input = "hi!\n[[!summary   foo bar asdf  ]]\n".to_string();
@@ -360,7 +406,8 @@ mod tests {
             tags: None,
         };
 
-        let result = eval_plugins(&input, &mut article);
+        let mut conn = establish_connection_and_initialize_schema();
+        let result = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new());
 
         assert!(result.is_ok());
 
@@ -380,18 +427,20 @@ mod tests {
     #[test]
     fn test_img() {
         let input = r#"
-        hi!
-        [[!img media/nlnet-logo.gif class="noFancy" style="float: right"]]
-        abab
-        [[!img posts/libnix/Nix_snowflake_windows.svg class="noFancy" style="float: right" width="200px"]]
-        "#.to_string();
+hi!
+[[!img media/nlnet-logo.gif class="noFancy" style="float: right"]]
+abab
+[[!img posts/libnix/Nix_snowflake_windows.svg class="noFancy" style="float: right" width="200px"]]
+"#
+        .to_string();
 
         let expected_output = r#"
-        hi!
-        <a href="media/nlnet-logo.gif"><img src="media/nlnet-logo.gif" class="noFancy" style="float: right"></a>
-        abab
-        <a href="posts/libnix/Nix_snowflake_windows.svg"><img src="posts/libnix/Nix_snowflake_windows.svg" class="noFancy" style="float: right" width="200px"></a>
-        "#.to_string();
+hi!
+<a href="media/nlnet-logo.gif"><img src="media/nlnet-logo.gif" class="noFancy" style="float: right"></a>
+abab
+<a href="posts/libnix/Nix_snowflake_windows.svg"><img src="posts/libnix/Nix_snowflake_windows.svg" class="noFancy" style="float: right" width="200px"></a>
+"#
+        .to_string();
 
         //o := `<a href="` + f[1] + `"><img src=` + b + `></a>`
         let mut article = ArticleWithTags {
@@ -426,7 +475,8 @@ mod tests {
             tags: None,
         };
 
-        let result = eval_plugins(&input, &mut article);
+        let mut conn = establish_connection_and_initialize_schema();
+        let result = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new());
 
         assert!(result.is_ok());
 
@@ -442,4 +492,782 @@ mod tests {
         println!("article_expected: {:#?}", article_expected);
         assert_eq!(article, article_expected);
     }
+
+    #[test]
+    fn test_gallery() {
+        use crate::articles::plugins::gallery::{gallery_html, Gallery, Sort};
+        use crate::articles::plugins::Arguments;
+        use crate::config::ImagesConfig;
+        use crate::db::images::{set_image_metadata, ImageMetadata};
+        use std::error::Error;
+
+        let parse = |input: &str| -> Result<Gallery, Box<dyn Error>> {
+            Gallery::from_arguments(&Arguments::parse(input)?)
+        };
+        assert_eq!(
+            parse(r#"media/trip/*.jpg sort="name""#).unwrap(),
+            Gallery {
+                dir: "media/trip".to_string(),
+                pattern: glob::Pattern::new("*.jpg").unwrap(),
+                sort: Sort::Name,
+            }
+        );
+        assert_eq!(parse("/media/trip/").unwrap().dir, "media/trip");
+        assert!(parse("media/*/a.jpg").is_err());
+        assert!(parse("media/trip sort=size").is_err());
+        assert!(parse("").is_err());
+        assert!(parse("media/trip media/zoo").is_err());
+        assert!(parse("media/trip order=name").is_err());
+
+        let dir = crate::test_utils::create_temp_dir("gallery");
+        let (input, output) = (dir.join("input"), dir.join("output"));
+        std::fs::create_dir_all(input.join("media/trip")).unwrap();
+        std::fs::create_dir_all(&output).unwrap();
+        for (name, red) in [("a.png", 0), ("b.png", 100), ("c.png", 200)] {
+            image::RgbImage::from_pixel(4, 2, image::Rgb([red, 0, 0]))
+                .save(input.join("media/trip").join(name))
+                .unwrap();
+        }
+        std::fs::write(input.join("media/trip/notes.txt"), "no image").unwrap();
+
+        let mut conn = establish_connection_and_initialize_schema();
+        // c.png was taken before b.png, a.png has no date
+        for (name, date) in [("b.png", 2), ("c.png", 1)] {
+            let content = std::fs::read(input.join("media/trip").join(name)).unwrap();
+            set_image_metadata(
+                &mut conn,
+                &ImageMetadata {
+                    hash: crate::db::cache::compute_hash_bytes(&content),
+                    width: 4,
+                    height: 2,
+                    orientation: 1,
+                    caption: None,
+                    date: chrono::NaiveDate::from_ymd_opt(2024, 5, date)
+                        .unwrap()
+                        .and_hms_opt(12, 0, 0),
+                },
+            )
+            .unwrap();
+        }
+
+        let settings = ImagesConfig {
+            responsive: false,
+            lazy: false,
+            ..ImagesConfig::default()
+        };
+        let item = |name: &str, title: &str| {
+            format!(
+                r#"<a href="media/trip/{name}" rel="gallery-media_trip"{title}><img src="media/trip/{name}" width="4" height="2"{title}></a>"#
+            )
+        };
+        let html = gallery_html(
+            &mut conn,
+            &settings,
+            &input,
+            &output,
+            std::path::Path::new(""),
+            &parse("media/trip").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            format!(
+                r#"<div class="pankatGallery">{}{}{}</div>"#,
+                item("c.png", r#" title="2024-05-01""#),
+                item("b.png", r#" title="2024-05-02""#),
+                item("a.png", "")
+            )
+        );
+
+        let html = gallery_html(
+            &mut conn,
+            &settings,
+            &input,
+            &output,
+            std::path::Path::new(""),
+            &parse("media/trip/[ab].png sort=name").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            format!(
+                r#"<div class="pankatGallery">{}{}</div>"#,
+                item("a.png", ""),
+                item("b.png", r#" title="2024-05-02""#)
+            )
+        );
+
+        assert!(gallery_html(
+            &mut conn,
+            &settings,
+            &input,
+            &output,
+            std::path::Path::new(""),
+            &parse("media/missing").unwrap(),
+        )
+        .is_err());
+
+        crate::db::article_dependencies::add_article_dependency(
+            &mut conn,
+            &crate::db::article_dependencies::ArticleDependency {
+                src_file_name: "posts/trip.mdwn".to_string(),
+                kind: "gallery".to_string(),
+                path: "media/trip".to_string(),
+            },
+        )
+        .unwrap();
+        let mut dependents =
+            |path: &str| crate::articles::dependent_articles(&mut conn, std::path::Path::new(path));
+        assert_eq!(
+            dependents("media/trip/d.png"),
+            vec![std::path::PathBuf::from("posts/trip.mdwn")]
+        );
+        assert!(dependents("media/trip/.d.png.swp").is_empty());
+        assert!(dependents("media/d.png").is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_registry() {
+        use crate::articles::plugins::registry::PluginRegistry;
+        use crate::articles::plugins::{Arguments, Plugin};
+        use diesel::SqliteConnection;
+        use std::error::Error;
+        use std::sync::Arc;
+
+        struct Shout;
+        impl Plugin for Shout {
+            fn name(&self) -> &'static str {
+                "shout"
+            }
+            fn usage(&self) -> &'static str {
+                "[[!shout text]]"
+            }
+            fn help(&self) -> &'static str {
+                "Upper case text"
+            }
+            fn validate(&self, arguments: &Arguments) -> Result<(), Box<dyn Error>> {
+                match arguments.raw.is_empty() {
+                    true => Err("Nothing to shout".into()),
+                    false => Ok(()),
+                }
+            }
+            fn apply(
+                &self,
+                _arguments: &Arguments,
+                article: &mut ArticleWithTags,
+            ) -> Result<(), Box<dyn Error>> {
+                article.summary = Some("loud".to_string());
+                Ok(())
+            }
+            fn render(
+                &self,
+                _conn: &mut SqliteConnection,
+                arguments: &Arguments,
+                _article: &ArticleWithTags,
+            ) -> Result<String, Box<dyn Error>> {
+                Ok(arguments.raw.to_uppercase())
+            }
+        }
+
+        let registry = PluginRegistry::new();
+        registry.register(Arc::new(Shout));
+        assert_eq!(registry.get("SHOUT").unwrap().name(), "shout");
+        assert!(registry.get("title").is_some());
+        let help = registry.help();
+        assert!(help.contains("[[!shout text]]\n    Upper case text\n"));
+        assert!(help.contains("[[!img "));
+
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let result = eval_plugins_with(
+            &registry,
+            &mut conn,
+            &"a [[!shout hey]] b [[!shout]]".to_string(),
+            &mut article,
+            &mut diagnostics,
+        )
+        .unwrap();
+        assert_eq!(result, "a HEY b [[!shout]]");
+        assert_eq!(article.summary, Some("loud".to_string()));
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_scaffold() {
+        use crate::articles::scaffold::ArticleScaffold;
+
+        let scaffold = ArticleScaffold {
+            title: "nix: status update".to_string(),
+            tags: vec![
+                "nix".to_string(),
+                " libnix ".to_string(),
+                "home manager".to_string(),
+            ],
+            series: Some(r#""libnix" reloaded"#.to_string()),
+            draft: true,
+        };
+        let date =
+            chrono::NaiveDateTime::parse_from_str("2025-03-01 12:30", "%Y-%m-%d %H:%M").unwrap();
+        let source = scaffold.source(date);
+        assert_eq!(
+            scaffold.src_file_name(),
+            std::path::PathBuf::from("posts/nix_status_update.mdwn")
+        );
+
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "posts/nix_status_update.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let document = eval_plugins(&mut conn, &source, &mut article, &mut Vec::new()).unwrap();
+
+        assert_eq!(document, "\n\n\n\n\n\n# nix: status update\n\n");
+        assert_eq!(article.title, Some("nix: status update".to_string()));
+        assert_eq!(article.modification_date, Some(date));
+        assert!(source.contains(r#"[[!tag nix libnix "home manager"]]"#));
+        assert_eq!(
+            article.tags,
+            Some(vec![
+                "nix".to_string(),
+                "libnix".to_string(),
+                "home manager".to_string()
+            ])
+        );
+        assert_eq!(article.series, Some(r#""libnix" reloaded"#.to_string()));
+        assert_eq!(article.draft, Some(true));
+    }
+
+    #[test]
+    fn test_plugin_diagnostics() {
+        let input = "# hello\n\n[[!tilte Hello]]\n[[!title Hello]]\n".to_string();
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut diagnostics = Vec::new();
+        let mut conn = establish_connection_and_initialize_schema();
+        let document = eval_plugins(&mut conn, &input, &mut article, &mut diagnostics).unwrap();
+
+        assert_eq!(document, "# hello\n\n[[!tilte Hello]]\n\n");
+        assert_eq!(article.title, Some("Hello".to_string()));
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                kind: "plugin",
+                message: "Plugin 'tilte' is not supported".to_string(),
+                line: Some(3),
+                column: Some(1),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_plugin_scripts() {
+        use crate::articles::plugins::registry::PluginRegistry;
+        use crate::articles::plugins::script::ScriptPlugin;
+        use crate::articles::plugins::Plugin;
+        use std::sync::Arc;
+
+        let source = r#"
+            fn help() { "Marks articles as reviewed" }
+            fn apply(arguments, article) {
+                #{ tags: ["reviewed"], summary: `reviewed by ${arguments.named.by}` }
+            }
+            fn render(arguments, article) {
+                `<p class="reviewed">${article.title}: ${arguments.positional.len()}</p>`
+            }
+        "#;
+        let plugin = ScriptPlugin::new("Reviewed", source).unwrap();
+        assert_eq!(plugin.name(), "reviewed");
+        assert_eq!(plugin.usage(), "[[!reviewed ...]]");
+        assert_eq!(plugin.help(), "Marks articles as reviewed");
+
+        let registry = PluginRegistry::new();
+        registry.register(Arc::new(plugin));
+        registry.register_scripts(std::path::Path::new("documents/assets/plugins"));
+        assert_eq!(registry.get("kbd").unwrap().usage(), "[[!kbd keys]]");
+
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: Some("Hello".to_string()),
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: Some(vec!["nix".to_string()]),
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let document = eval_plugins_with(
+            &registry,
+            &mut conn,
+            &"[[!reviewed a b by=joachim]] [[!kbd Ctrl + <]]".to_string(),
+            &mut article,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            document,
+            r#"<p class="reviewed">Hello: 2</p> <kbd>Ctrl</kbd>+<kbd>&lt;</kbd>"#
+        );
+        assert_eq!(
+            article.tags,
+            Some(vec!["nix".to_string(), "reviewed".to_string()])
+        );
+        assert_eq!(article.summary, Some("reviewed by joachim".to_string()));
+
+        // sandbox
+        assert!(ScriptPlugin::new("evil", r#"fn render(a, b) { eval("1") }"#).is_err());
+        assert!(ScriptPlugin::new("bad name", "").is_err());
+        for source in [
+            "fn render(a, b) { loop {} }",
+            r#"fn render(a, b) { import "x" as x; "" }"#,
+            r#"fn render(a, b) { let s = "x"; loop { s += s; } }"#,
+        ] {
+            let plugin = ScriptPlugin::new("sandboxed", source).unwrap();
+            let arguments = plugin.parse("").unwrap();
+            assert!(plugin.render(&mut conn, &arguments, &article).is_err());
+        }
+        let plugin = ScriptPlugin::new("meta", "fn apply(a, b) { #{ date: 1 } }").unwrap();
+        assert!(plugin
+            .apply(&plugin.parse("").unwrap(), &mut article)
+            .is_err());
+    }
+
+    #[test]
+    fn test_shortcodes() {
+        use crate::articles::plugins::admonition::{NOTE, WARNING};
+        use crate::articles::plugins::embed::{Asciinema, Gist, Youtube};
+        use crate::articles::plugins::registry::PluginRegistry;
+        use crate::articles::plugins::Plugin;
+        use crate::renderer::html::render_partial;
+
+        let assets = std::path::Path::new("documents/assets");
+        let arguments = |input: &str| NOTE.parse(input).unwrap();
+
+        let data = NOTE.data(&arguments("Don't *panic*")).unwrap();
+        let html = render_partial(assets, "admonition", &data).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"pankatAdmonition pankatAdmonition-note\">\n\
+            <p class=\"pankatAdmonitionTitle\">Note</p>\n\nDon't *panic*\n\n</div>"
+        );
+        let data = WARNING
+            .data(&arguments(
+                "title=\"Careful <now>\" \"\"\"first\n\n    code\"\"\"",
+            ))
+            .unwrap();
+        let html = render_partial(assets, "admonition", &data).unwrap();
+        // the markdown keeps its indentation, the title is escaped
+        assert!(html.contains("Careful &lt;now&gt;</p>\n\nfirst\n\n    code\n\n</div>"));
+        assert!(NOTE.data(&arguments("")).is_err());
+
+        for input in [
+            "dQw4w9WgXcQ",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://www.youtube.com/watch?feature=share&v=dQw4w9WgXcQ&t=3",
+            "https://youtu.be/dQw4w9WgXcQ",
+        ] {
+            let data = Youtube::data(&arguments(input)).unwrap();
+            assert_eq!(data["Id"], "dQw4w9WgXcQ");
+        }
+        let data = Youtube::data(&arguments(r#"dQw4w9WgXcQ start=42 title="Never""#)).unwrap();
+        let html = render_partial(assets, "youtube", &data).unwrap();
+        assert!(html.starts_with(
+            r#"<div class="pankatEmbed pankatYoutube" data-src="https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?autoplay=1&amp;start=42">"#
+        ));
+        // nothing is loaded from youtube before the click
+        assert!(!html.contains("<iframe"));
+        assert!(html.contains(">Never</span>"));
+        assert!(Youtube::data(&arguments("not-an-id")).is_err());
+        assert!(Youtube::data(&arguments("dQw4w9WgXcQ start=soon")).is_err());
+
+        let data = Asciinema::data(&arguments("https://asciinema.org/a/335480")).unwrap();
+        assert_eq!(data["Id"], "335480");
+        let html = render_partial(assets, "asciinema", &data).unwrap();
+        assert!(html.contains(r#"data-src="https://asciinema.org/a/335480/iframe?autoplay=1""#));
+
+        let data = Gist::data(&arguments("qknight/0123abcd file=flake.nix")).unwrap();
+        let html = render_partial(assets, "gist", &data).unwrap();
+        assert!(html.contains(
+            r#"<script src="https://gist.github.com/qknight/0123abcd.js?file=flake.nix"></script>"#
+        ));
+        assert!(Gist::data(&arguments(r#"qknight/0123abcd file="a b""#)).is_err());
+
+        let registry = PluginRegistry::instance();
+        for name in [
+            "note",
+            "warning",
+            "tip",
+            "youtube",
+            "gist",
+            "asciinema",
+            "toc",
+        ] {
+            assert!(registry.get(name).is_some(), "{} is not registered", name);
+        }
+
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let mut diagnostics = Vec::new();
+        let document = eval_plugins(
+            &mut conn,
+            &"[[!toc levels=2]]\n[[!youtube nope]]".to_string(),
+            &mut article,
+            &mut diagnostics,
+        )
+        .unwrap();
+        assert_eq!(
+            document,
+            "\n\n<!-- pankat-toc levels=2 -->\n\n\n[[!youtube nope]]"
+        );
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_include() {
+        use crate::articles::plugins::include::Include;
+        use crate::articles::plugins::registry::PluginRegistry;
+        use std::sync::Arc;
+
+        let dir = crate::test_utils::create_temp_dir("include");
+        std::fs::create_dir_all(dir.join("snippets")).unwrap();
+        for (name, content) in [
+            ("a.md", "A [[!include snippets/b.md]] [[!tag x]]\n"),
+            ("b.md", "B"),
+            ("c.md", "[[!include snippets/d.md]]"),
+            ("d.md", "[[!include /snippets/c.md]]"),
+            ("e.md", "\n[[!tilte E]]"),
+            ("f.md", "[[!include ./snippets/x/../h.md]]"),
+            ("h.md", "H"),
+        ] {
+            std::fs::write(dir.join("snippets").join(name), content).unwrap();
+        }
+        let registry = PluginRegistry::new();
+        registry.register(Arc::new(Include {
+            input: Some(dir.clone()),
+        }));
+
+        let input = "[[!include snippets/a.md]]\n[[!include snippets/c.md]]\n\
+            [[!include snippets/e.md]]\n[[!include snippets/missing.md]]\n\
+            [[!include snippets/f.md]]\n[[!include snippets/../../secret.md]]"
+            .to_string();
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let mut diagnostics = Vec::new();
+        let document =
+            eval_plugins_with(&registry, &mut conn, &input, &mut article, &mut diagnostics)
+                .unwrap();
+
+        assert_eq!(
+            document,
+            "A B \n[[!include /snippets/c.md]]\n\n[[!tilte E]]\n[[!include snippets/missing.md]]\n\
+            H\n[[!include snippets/../../secret.md]]"
+        );
+        // directives of included files change the metadata of the article
+        assert_eq!(article.tags, Some(vec!["x".to_string()]));
+        let messages: Vec<(Option<usize>, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    Some(2),
+                    "snippets/c.md:1: snippets/d.md:1: \
+                    Cycle: example.mdwn -> snippets/c.md -> snippets/d.md -> snippets/c.md"
+                ),
+                (Some(3), "snippets/e.md:2: Plugin 'tilte' is not supported"),
+                (
+                    Some(4),
+                    "Can't include 'snippets/missing.md': No such file or directory (os error 2)"
+                ),
+                (
+                    Some(6),
+                    "Can't include 'snippets/../../secret.md' from outside of the input directory"
+                ),
+            ]
+        );
+
+        // the monitor renders these articles again when the files change
+        let mut dependents =
+            |path: &str| crate::articles::dependent_articles(&mut conn, std::path::Path::new(path));
+        for file in [
+            "snippets/a.md",
+            "snippets/b.md",
+            "snippets/missing.md",
+            "snippets/h.md",
+        ] {
+            assert_eq!(
+                dependents(file),
+                vec![std::path::PathBuf::from("example.mdwn")]
+            );
+        }
+        assert!(dependents("snippets/g.md").is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_img_in_indented_code() {
+        let input =
+            "hi!\n\n    [[!img media/nlnet-logo.gif class=\"noFancy\"]]\n\nabab\n".to_string();
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let document = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new()).unwrap();
+
+        // an indented block is code, its directives are shown as they are
+        assert_eq!(document, input);
+    }
+
+    #[test]
+    fn test_img_url_is_escaped() {
+        let input = "[[!img \"\"\"media/\"onerror=\"alert(1).gif\"\"\"]]\n".to_string();
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let document = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new()).unwrap();
+
+        assert_eq!(
+            document,
+            concat!(
+                r#"<a href="media/&quot;onerror=&quot;alert(1).gif">"#,
+                r#"<img src="media/&quot;onerror=&quot;alert(1).gif" ></a>"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_directives_in_code() {
+        let input = "[[!summary a summary\n  spanning lines]]\n\
+            write `[[!tag foo]]` or \\[[!tag bar]]\n\n\
+            ```\n[[!draft]]\n```\n\n    [[!title Code]]\n\n[[!tag baz]]\n"
+            .to_string();
+        let mut article = ArticleWithTags {
+            id: None,
+            src_file_name: "example.mdwn".to_string(),
+            dst_file_name: String::new(),
+            title: None,
+            modification_date: None,
+            summary: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+            tags: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let document = eval_plugins(&mut conn, &input, &mut article, &mut Vec::new()).unwrap();
+
+        assert_eq!(
+            document,
+            "\nwrite `[[!tag foo]]` or [[!tag bar]]\n\n\
+            ```\n[[!draft]]\n```\n\n    [[!title Code]]\n\n\n"
+        );
+        assert_eq!(
+            article.summary,
+            Some("a summary spanning lines".to_string())
+        );
+        assert_eq!(article.tags, Some(vec!["baz".to_string()]));
+        assert_eq!(article.draft, None);
+        assert_eq!(article.title, None);
+    }
+
+    #[test]
+    fn test_pages() {
+        use crate::articles::plugins::pages::{Pages, Sort};
+        use crate::articles::plugins::Plugin;
+        use crate::renderer::html::render_partial;
+        use std::path::PathBuf;
+
+        let article = |name: &str, day: u32, tags: &[&str], series: Option<&str>| ArticleWithTags {
+            id: None,
+            src_file_name: format!("posts/{}.mdwn", name),
+            dst_file_name: format!("posts/{}.html", name),
+            title: Some(name.to_string()),
+            modification_date: chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+                .and_then(|date| date.and_hms_opt(0, 0, 0)),
+            summary: None,
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            series: series.map(str::to_string),
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+        };
+        let mut conn = establish_connection_and_initialize_schema();
+        let mut draft = article("draft", 5, &["nix"], None);
+        draft.draft = Some(true);
+        for article in [
+            article("a", 1, &["nix"], Some("libnix")),
+            article("b", 2, &["nix", "rust"], None),
+            article("c", 3, &["nix"], Some("libnix")),
+            article("d", 4, &["rust"], Some("libnix")),
+            draft,
+        ] {
+            crate::db::article::set(&mut conn, &article).unwrap();
+        }
+        let mut listing = article("listing", 6, &["nix"], None);
+        let names = |pages: Vec<ArticleWithTags>| -> Vec<String> {
+            pages.into_iter().filter_map(|page| page.title).collect()
+        };
+        let mut pages = |input: &str| {
+            let query = Pages.parse(input).map(|arguments| Pages::query(&arguments));
+            names(Pages::pages(&mut conn, &query.unwrap().unwrap(), &listing).unwrap())
+        };
+
+        assert_eq!(pages("tag=nix"), vec!["c", "b", "a"]);
+        assert_eq!(pages("tag=nix limit=2"), vec!["c", "b"]);
+        assert_eq!(pages(r#"tag=nix series="libnix""#), vec!["c", "a"]);
+        assert_eq!(
+            pages("series=libnix sort=title reverse=yes"),
+            vec!["d", "c", "a"]
+        );
+        assert_eq!(pages(""), vec!["d", "c", "b", "a"]);
+        for input in ["limit=0", "sort=age", "reverse=maybe", "tags=nix", "nix"] {
+            let arguments = Pages.parse(input).unwrap();
+            assert!(Pages.validate(&arguments).is_err(), "{}", input);
+        }
+        assert_eq!(
+            Pages::query(&Pages.parse("").unwrap()).unwrap().sort,
+            Sort::Date
+        );
+
+        let html = render_partial(
+            std::path::Path::new("documents/assets"),
+            "pages",
+            &Pages::data(&[article("a", 1, &[], None)]),
+        )
+        .unwrap();
+        assert!(html.contains(
+            r#"<a href="/posts/a.html">a</a> <span class="pankatPagesDate">01 jan 2024</span>"#
+        ));
+        let html = render_partial(
+            std::path::Path::new("documents/assets"),
+            "pages",
+            &Pages::data(&[]),
+        )
+        .unwrap();
+        assert!(html.contains("No articles yet"));
+
+        // the monitor renders the listing again when the listed articles change what it shows
+        let input = r#"[[!pages tag=nix series="libnix"]]"#.to_string();
+        let _ = eval_plugins(&mut conn, &input, &mut listing, &mut Vec::new());
+        let before = Some(article("b", 2, &["rust"], None));
+        let listed = Some(article("b", 2, &["nix"], None));
+        assert_eq!(
+            crate::articles::listing_articles(&mut conn, &before, &listed),
+            vec![PathBuf::from("posts/listing.mdwn")]
+        );
+        assert_eq!(
+            crate::articles::listing_articles(&mut conn, &listed, &None),
+            vec![PathBuf::from("posts/listing.mdwn")]
+        );
+        assert!(crate::articles::listing_articles(&mut conn, &listed, &listed).is_empty());
+        assert!(crate::articles::listing_articles(&mut conn, &None, &before).is_empty());
+    }
 }
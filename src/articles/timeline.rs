@@ -1,5 +1,6 @@
 use crate::articles::ArticleWithTags;
 use crate::config;
+use crate::renderer::utils::escape_attribute;
 use serde_json::json;
 
 use std::collections::HashMap;
@@ -97,10 +98,13 @@ pub fn tag_links_to_timeline(tags: Option<Vec<String>>) -> String {
         Some(tags) => {
             let mut result = String::new();
             for tag in tags {
+                // tags may contain whitespace and quotes since [[!tag]] takes quoted values
+                let query =
+                    percent_encoding::utf8_percent_encode(&tag, percent_encoding::NON_ALPHANUMERIC);
                 result.push_str(
                     &format!(
                     r#"<a href="timeline.html?filter=tag::{}" class="tagbtn btn btn-primary">{}</a>"#,
-                    tag, tag
+                    query, escape_attribute(&tag)
                 ));
             }
             result
@@ -113,9 +117,11 @@ fn tag_to_link_list(article: &ArticleWithTags) -> String {
     let mut output = String::new();
     if let Some(tags) = &article.tags {
         for tag in tags {
+            let js_string = tag.replace('\\', "\\\\").replace('\'', "\\'");
             output.push_str(&format!(
                 "<a class=\"tagbtn btn btn-primary\" onClick=\"setFilter('tag::{}', 1)\">{}</a>",
-                tag, tag
+                escape_attribute(&js_string),
+                escape_attribute(tag)
             ));
         }
     }
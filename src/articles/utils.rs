@@ -63,6 +63,40 @@ pub fn create_dst_file_name(article_path: &PathBuf, flat: bool) -> String {
     }
 }
 
+/// Creates the `src_file_name` for a new article, the inverse of `article_src_file_name_to_title`
+pub fn create_src_file_name(title: &str) -> PathBuf {
+    let slug: String = title
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join("_")
+        .chars()
+        // `#`, `?` and `%` would end or escape the path of the url of the article
+        .filter(|c| !matches!(c, '/' | '\\' | ':' | '#' | '?' | '%') && !c.is_control())
+        .collect();
+    PathBuf::from("posts").join(format!("{}.mdwn", slug.trim_start_matches('.')))
+}
+
+#[test]
+fn test_create_src_file_name() {
+    assert_eq!(
+        create_src_file_name("libnix status  update"),
+        PathBuf::from("posts/libnix_status_update.mdwn")
+    );
+    assert_eq!(
+        create_src_file_name("../etc/passwd"),
+        PathBuf::from("posts/etcpasswd.mdwn")
+    );
+    assert_eq!(
+        create_src_file_name("C# 100% done?"),
+        PathBuf::from("posts/C_100_done.mdwn")
+    );
+    let title = "nix on windows";
+    assert_eq!(
+        article_src_file_name_to_title(&create_src_file_name(title)),
+        title
+    );
+}
+
 #[test]
 fn test_create_dst_file_name_flat() {
     let article_path = PathBuf::from("src/articles/test.mdwn");
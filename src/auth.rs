@@ -1,22 +1,91 @@
 use crate::config;
+use crate::db::tokens::{is_jti_revoked, revoke_refresh_tokens_for_user};
+use crate::db::users::{get_user_by_username, set_admin};
+use crate::db::DbPool;
+use crate::error::AppError;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::{HeaderMap, AUTHORIZATION, COOKIE};
+use axum::http::request::Parts;
+use axum::http::Method;
+use bcrypt::{hash, verify, DEFAULT_COST};
+use diesel::SqliteConnection;
+use jsonwebtoken::errors::ErrorKind;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Lifetime of an access token (JWT) in seconds
+pub const ACCESS_TOKEN_LIFETIME: u64 = 15 * 60; // 15 minutes
+
+/// Lifetime of a refresh token in seconds
+pub const REFRESH_TOKEN_LIFETIME: u64 = 30 * 86400; // 30 days
+
+/// HttpOnly cookie holding the access token of a browser session
+pub const SESSION_COOKIE: &str = "pankat_session";
+
+/// HttpOnly cookie holding the refresh token of a browser session
+pub const REFRESH_COOKIE: &str = "pankat_refresh";
+
+/// Cookie readable by javascript, its value has to be echoed in the `CSRF_HEADER`
+pub const CSRF_COOKIE: &str = "pankat_csrf";
+
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// The account `admin_password` of the configuration is set for
+pub const ADMIN_USERNAME: &str = "admin";
+
 /// User access levels in the system
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum UserLevel {
     Admin,
     User,
     Guest,
 }
 
+impl UserLevel {
+    /// Parses the level as stored in the `users` table, unknown values map to `Guest`
+    pub fn from_db(level: &str) -> UserLevel {
+        match level {
+            "Admin" => UserLevel::Admin,
+            "User" => UserLevel::User,
+            _ => serde_json::from_str(level).unwrap_or(UserLevel::Guest),
+        }
+    }
+}
+
 /// JWT claims structure
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub user_id: String,
     pub level: UserLevel,
     pub exp: u64,
+    pub jti: String,
+    /// Only set for cookie sessions, state-changing requests must send it in the `CSRF_HEADER`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csrf: Option<String>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Converts a unix timestamp (as used in `exp`) into a database timestamp
+pub fn timestamp_to_naive(timestamp: u64) -> chrono::NaiveDateTime {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+pub fn random_hex(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut buffer);
+    buffer.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
 /// Create a new JWT token for a user
@@ -24,25 +93,23 @@ pub struct Claims {
 /// # Arguments
 /// * `user_id` - The user's ID
 /// * `level` - The user's access level
+/// * `csrf` - The CSRF token bound to a cookie session, `None` for bearer tokens
 ///
 /// # Returns
 /// * `Result<String, jsonwebtoken::errors::Error>` - The JWT token if successful
 pub fn create_token(
     user_id: String,
     level: UserLevel,
+    csrf: Option<String>,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     let cfg = config::Config::get();
 
-    let expiration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        + 86400; // 24 hours
-
     let claims = Claims {
         user_id,
         level,
-        exp: expiration,
+        exp: now() + ACCESS_TOKEN_LIFETIME,
+        jti: random_hex(16),
+        csrf,
     };
 
     encode(
@@ -52,19 +119,178 @@ pub fn create_token(
     )
 }
 
+/// Create a new opaque refresh token
+///
+/// # Returns
+/// * `(String, String, chrono::NaiveDateTime)` - The token handed to the client, its hash for the
+///   database and the expiration date
+pub fn create_refresh_token() -> (String, String, chrono::NaiveDateTime) {
+    let token = random_hex(32);
+    let token_hash = hash_refresh_token(&token);
+    (
+        token,
+        token_hash,
+        timestamp_to_naive(now() + REFRESH_TOKEN_LIFETIME),
+    )
+}
+
+/// Refresh tokens are only stored hashed, a leaked database can't be used to log in
+pub fn hash_refresh_token(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 /// Validate and decode a JWT token
 ///
 /// # Arguments
+/// * `conn` - Database connection used to check the revocation list
 /// * `token` - The JWT token to validate
 ///
 /// # Returns
 /// * `Result<TokenData<Claims>, jsonwebtoken::errors::Error>` - The decoded claims if valid
-pub fn validate_token(token: &str) -> Result<TokenData<Claims>, jsonwebtoken::errors::Error> {
+pub fn validate_token(
+    conn: &mut SqliteConnection,
+    token: &str,
+) -> Result<TokenData<Claims>, jsonwebtoken::errors::Error> {
     let cfg = config::Config::get();
 
-    decode::<Claims>(
+    let token_data = decode::<Claims>(
         token,
         &DecodingKey::from_secret(cfg.jwt_token.as_bytes()),
         &Validation::default(),
-    )
+    )?;
+
+    match is_jti_revoked(conn, &token_data.claims.jti) {
+        Ok(false) => Ok(token_data),
+        Ok(true) => Err(ErrorKind::InvalidToken.into()),
+        Err(e) => {
+            println!("Error checking token revocation list: {}", e);
+            Err(ErrorKind::InvalidToken.into())
+        }
+    }
+}
+
+/// Returns the value of the cookie `name` from the `Cookie` request header(s)
+pub fn get_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Creates a `Set-Cookie` header value scoped to the blog's subdir, a `max_age` of 0 deletes the cookie
+pub fn build_cookie(name: &str, value: &str, max_age: u64, http_only: bool) -> String {
+    let cfg = config::Config::get();
+
+    let subdir = cfg.subdir.display().to_string();
+    let path = if subdir.is_empty() { "/" } else { &subdir };
+
+    let mut cookie = format!(
+        "{}={}; Path={}; Max-Age={}; SameSite=Strict",
+        name, value, path, max_age
+    );
+    if http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if cfg.secure_cookies {
+        cookie.push_str("; Secure");
+    }
+    cookie
+}
+
+/// Creates the `ADMIN_USERNAME` account with `admin_password` or updates it when the password
+/// changed, its sessions end then. `false` if nothing changed, the default `"not set"` disables the
+/// account setup.
+pub fn ensure_admin(
+    conn: &mut SqliteConnection,
+    admin_password: &str,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    if admin_password.is_empty() || admin_password == "not set" {
+        return Ok(false);
+    }
+    if let Some(user) = get_user_by_username(conn, ADMIN_USERNAME)? {
+        let is_admin = UserLevel::from_db(&user.level) == UserLevel::Admin;
+        if is_admin && verify(admin_password.as_bytes(), &user.password).unwrap_or(false) {
+            return Ok(false);
+        }
+    }
+    let hashed = hash(admin_password.as_bytes(), DEFAULT_COST)?;
+    let user_id = set_admin(conn, ADMIN_USERNAME, &hashed)?;
+    revoke_refresh_tokens_for_user(conn, user_id)?;
+    Ok(true)
+}
+
+/// Extractor for routes which require a valid, non-revoked access token
+///
+/// The token is taken from the `Authorization: Bearer` header or, for browsers, from the
+/// `SESSION_COOKIE`. Cookie sessions must pass the CSRF check on state-changing requests.
+pub struct AuthenticatedUser {
+    pub claims: Claims,
+    pub via_cookie: bool,
+}
+
+#[async_trait]
+impl FromRequestParts<DbPool> for AuthenticatedUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, pool: &DbPool) -> Result<Self, Self::Rejection> {
+        let bearer = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(|token| token.to_string());
+
+        let (token, via_cookie) = match bearer {
+            Some(token) => (token, false),
+            None => (
+                get_cookie(&parts.headers, SESSION_COOKIE).ok_or(AppError::AuthError)?,
+                true,
+            ),
+        };
+
+        let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+        let claims = match validate_token(&mut conn, &token) {
+            Ok(token_data) => token_data.claims,
+            Err(_) => return Err(AppError::AuthError),
+        };
+
+        if via_cookie && !is_safe_method(&parts.method) {
+            let csrf_header = parts
+                .headers
+                .get(CSRF_HEADER)
+                .and_then(|value| value.to_str().ok());
+            match (claims.csrf.as_deref(), csrf_header) {
+                (Some(expected), Some(given)) if expected == given => {}
+                _ => {
+                    println!("CSRF check failed for user '{}'", claims.user_id);
+                    return Err(AppError::Forbidden);
+                }
+            }
+        }
+
+        Ok(AuthenticatedUser { claims, via_cookie })
+    }
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+#[test]
+fn test_get_cookie() {
+    let mut headers = HeaderMap::new();
+    headers.append(COOKIE, "foo=bar; pankat_session=abc.def".parse().unwrap());
+    headers.append(COOKIE, "pankat_csrf=123".parse().unwrap());
+
+    assert_eq!(
+        get_cookie(&headers, SESSION_COOKIE),
+        Some("abc.def".to_string())
+    );
+    assert_eq!(get_cookie(&headers, CSRF_COOKIE), Some("123".to_string()));
+    assert_eq!(get_cookie(&headers, REFRESH_COOKIE), None);
 }
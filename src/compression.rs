@@ -0,0 +1,205 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// Preferred first, brotli compresses text better than gzip
+    pub const ALL: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+    /// The `Content-Encoding` / `Accept-Encoding` token
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// Extension of the precompressed sibling, `foo.html` -> `foo.html.br`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+
+    pub fn sibling(&self, path: &Path) -> PathBuf {
+        let mut sibling = path.as_os_str().to_owned();
+        sibling.push(".");
+        sibling.push(self.extension());
+        PathBuf::from(sibling)
+    }
+
+    /// `best` is used for precompressed siblings, requests are compressed at a faster level
+    pub fn compress(&self, content: &[u8], best: bool) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut compressed = Vec::new();
+                {
+                    let mut writer = brotli::CompressorWriter::new(
+                        &mut compressed,
+                        4096,
+                        if best { 11 } else { 5 },
+                        22,
+                    );
+                    writer.write_all(content)?;
+                }
+                Ok(compressed)
+            }
+            Encoding::Gzip => {
+                let level = if best {
+                    flate2::Compression::best()
+                } else {
+                    flate2::Compression::default()
+                };
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// HTML, CSS, JS and wasm, everything else pankat serves is either tiny or already compressed
+pub fn is_compressible(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            matches!(
+                ext.to_ascii_lowercase().as_str(),
+                "html" | "htm" | "css" | "js" | "mjs" | "wasm" | "svg" | "json"
+            )
+        })
+}
+
+/// Returns the file a precompressed sibling belongs to, `foo.html.gz` -> `foo.html`
+pub fn sibling_of(path: &Path) -> Option<PathBuf> {
+    let extension = path.extension()?;
+    Encoding::ALL
+        .iter()
+        .any(|encoding| extension == encoding.extension())
+        .then(|| path.with_extension(""))
+        .filter(|original| is_compressible(original))
+}
+
+/// Picks the encoding from an `Accept-Encoding` header, `None` means identity
+///
+/// Encodings with `q=0` are refused, `*` stands for every encoding not listed explicitly and on
+/// equal weights brotli wins.
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut weights: Vec<(&str, f32)> = Vec::new();
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        if name.is_empty() {
+            continue;
+        }
+        let q = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        weights.push((name, q));
+    }
+
+    let weight = |encoding: &Encoding| {
+        let explicit = weights
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(encoding.name()));
+        let any = weights.iter().find(|(name, _)| *name == "*");
+        explicit.or(any).map(|(_, q)| *q).unwrap_or(0.0)
+    };
+
+    Encoding::ALL
+        .iter()
+        .filter(|encoding| weight(encoding) > 0.0)
+        .fold(None, |best: Option<Encoding>, encoding| match best {
+            Some(best) if weight(&best) >= weight(encoding) => Some(best),
+            _ => Some(*encoding),
+        })
+}
+
+/// Writes (or, with `enabled` being false, removes stale) `.br` and `.gz` siblings of `path`
+pub fn write_siblings(path: &Path, content: &[u8], enabled: bool) {
+    if !is_compressible(path) {
+        return;
+    }
+    for encoding in Encoding::ALL {
+        let sibling = encoding.sibling(path);
+        if !enabled {
+            let _ = std::fs::remove_file(&sibling);
+            continue;
+        }
+        let result = encoding
+            .compress(content, true)
+            .and_then(|compressed| std::fs::write(&sibling, compressed));
+        if let Err(e) = result {
+            println!("Failed to write {}: {}", sibling.display(), e);
+            // an outdated sibling must not be served instead of the new content
+            let _ = std::fs::remove_file(&sibling);
+        }
+    }
+}
+
+pub fn remove_siblings(path: &Path) {
+    for encoding in Encoding::ALL {
+        let _ = std::fs::remove_file(encoding.sibling(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("gzip, deflate"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("br;q=0.5, gzip;q=0.8"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("br;q=0, gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("br;q=0, *;q=0.1"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate("gzip;q=0"), None);
+        assert_eq!(negotiate(""), None);
+    }
+
+    #[test]
+    fn test_siblings() {
+        let html = Path::new("out/foo.html");
+        assert_eq!(Encoding::Brotli.sibling(html), Path::new("out/foo.html.br"));
+        assert_eq!(Encoding::Gzip.sibling(html), Path::new("out/foo.html.gz"));
+        assert_eq!(sibling_of(Path::new("out/foo.html.gz")), Some(html.into()));
+        assert_eq!(sibling_of(Path::new("out/foo.html.br")), Some(html.into()));
+        assert_eq!(sibling_of(Path::new("out/foo.html")), None);
+        assert_eq!(sibling_of(Path::new("out/backup.tar.gz")), None);
+        assert!(is_compressible(Path::new("wasm/pankat_wasm_bg.wasm")));
+        assert!(!is_compressible(Path::new("media/foo.jpg")));
+    }
+
+    #[test]
+    fn test_compress() {
+        let content = "<p>pankat</p>".repeat(100);
+
+        let gzip = Encoding::Gzip.compress(content.as_bytes(), true).unwrap();
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(gzip.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+
+        let brotli = Encoding::Brotli
+            .compress(content.as_bytes(), false)
+            .unwrap();
+        let mut decoded = String::new();
+        brotli::Decompressor::new(brotli.as_slice(), 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+        assert!(brotli.len() < content.len());
+    }
+}
@@ -46,6 +46,10 @@ pub struct CliConfig {
     pub static_build_only: Option<bool>,
     #[serde(skip_serializing_if = "::std::option::Option::is_none")]
     pub flat: Option<bool>,
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    pub production: Option<bool>,
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    pub export: Option<bool>,
 }
 
 #[derive(Parser, Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +66,186 @@ pub struct Config {
     pub port: u16,
     pub static_build_only: bool,
     pub flat: bool,
+    #[serde(default)]
+    pub production: bool,
+    #[serde(default)]
+    pub export: bool,
+    #[serde(default = "default_true")]
+    pub secure_cookies: bool,
+    #[serde(default)]
+    #[arg(skip)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    #[arg(skip)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    #[arg(skip)]
+    pub compression: CompressionConfig,
+    #[serde(default)]
+    #[arg(skip)]
+    pub link_check: LinkCheckConfig,
+    #[serde(default)]
+    #[arg(skip)]
+    pub images: ImagesConfig,
+    #[serde(default)]
+    #[arg(skip)]
+    pub math: MathConfig,
+    #[serde(default)]
+    #[arg(skip)]
+    pub revisions: RevisionsConfig,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Throttling of `/api/auth/login` and `/api/auth/register`, see `[rate_limit]` in pankat.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: u32,
+    pub backoff_seconds: u64,
+    pub backoff_max_seconds: u64,
+    pub lockout_threshold: u32,
+    pub lockout_seconds: u64,
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            requests_per_minute: 10,
+            backoff_seconds: 1,
+            backoff_max_seconds: 60,
+            lockout_threshold: 5,
+            lockout_seconds: 900,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+/// `Cache-Control: max-age` in seconds for the file serving routes, see `[cache]` in pankat.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub output_max_age: u64,
+    pub input_max_age: u64,
+    pub assets_max_age: u64,
+    pub wasm_max_age: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            output_max_age: 60,
+            input_max_age: 3600,
+            assets_max_age: 7 * 86400,
+            wasm_max_age: 7 * 86400,
+        }
+    }
+}
+
+/// `.br`/`.gz` siblings in the output and `Content-Encoding` negotiation, see `[compression]` in pankat.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub precompress: bool,
+    pub dynamic: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            precompress: false,
+            dynamic: true,
+        }
+    }
+}
+
+/// Checking the links of the generated pages after a build, see `[link_check]` in pankat.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkCheckConfig {
+    pub enabled: bool,
+    pub external: bool,
+    pub external_timeout_seconds: u64,
+    pub external_cache_hours: i64,
+    pub fail_static_build: bool,
+    pub ignore: Vec<String>,
+}
+
+impl Default for LinkCheckConfig {
+    fn default() -> Self {
+        LinkCheckConfig {
+            enabled: true,
+            external: false,
+            external_timeout_seconds: 10,
+            external_cache_hours: 24,
+            fail_static_build: false,
+            ignore: vec!["draft".to_string(), "api/".to_string()],
+        }
+    }
+}
+
+/// Resized variants of the images used by `[[!img]]`, see `[images]` in pankat.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImagesConfig {
+    pub responsive: bool,
+    pub widths: Vec<u32>,
+    pub formats: Vec<String>,
+    pub quality: u8,
+    pub avif_speed: u8,
+    pub sizes: String,
+    pub lazy: bool,
+    pub strip_exif: bool,
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        ImagesConfig {
+            responsive: true,
+            widths: vec![480, 960, 1920],
+            formats: vec!["avif".to_string(), "webp".to_string()],
+            quality: 80,
+            avif_speed: 8,
+            sizes: "(max-width: 960px) 100vw, 960px".to_string(),
+            lazy: true,
+            strip_exif: false,
+        }
+    }
+}
+
+/// The `article_revisions` kept per article, see `[revisions]` in pankat.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RevisionsConfig {
+    pub keep: u32,
+}
+
+impl Default for RevisionsConfig {
+    fn default() -> Self {
+        RevisionsConfig { keep: 50 }
+    }
+}
+
+/// LaTeX formulas in articles, rendered by pandoc, see `[math]` in pankat.toml and `[[!math]]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MathConfig {
+    pub enabled: bool,
+    pub mode: String,
+    pub katex_url: String,
+}
+
+impl Default for MathConfig {
+    fn default() -> Self {
+        MathConfig {
+            enabled: false,
+            mode: "mathml".to_string(),
+            katex_url: "https://cdn.jsdelivr.net/npm/katex@0.16.11/dist".to_string(),
+        }
+    }
 }
 
 enum OnlyDefaultValues {
@@ -182,6 +366,28 @@ fn create_config(
                 None
             }
         }),
+        production: config_values.get("production").and_then(|cv| {
+            if let ConfigValueType::Bool(p) = &cv.value {
+                match creation_mode {
+                    OnlyDefaultValues::OnlyDefaultValues if cv.is_default => *p,
+                    OnlyDefaultValues::OnlySetValues if !cv.is_default => *p,
+                    _ => None,
+                }
+            } else {
+                None
+            }
+        }),
+        export: config_values.get("export").and_then(|cv| {
+            if let ConfigValueType::Bool(p) = &cv.value {
+                match creation_mode {
+                    OnlyDefaultValues::OnlyDefaultValues if cv.is_default => *p,
+                    OnlyDefaultValues::OnlySetValues if !cv.is_default => *p,
+                    _ => None,
+                }
+            } else {
+                None
+            }
+        }),
     }
 }
 
@@ -211,6 +417,11 @@ impl Config {
         SINGLETON.get().expect("Config not initialized")
     }
 
+    /// Like `get`, but `None` before `initialize`, e.g. in unit tests of the plugins
+    pub fn try_get() -> Option<&'static Arc<Config>> {
+        SINGLETON.get()
+    }
+
     pub fn initialize(config: Config) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         #[cfg(all(not(test)))]
         {
@@ -121,7 +121,6 @@ pub fn get_article_with_tags_by_id(
     }
 }
 
-#[allow(dead_code)]
 pub fn get_article_with_tags_by_src_file_name(
     conn: &mut SqliteConnection,
     src_file_name: String,
@@ -245,7 +244,6 @@ pub fn get_visible_articles(
     }
 }
 
-#[allow(dead_code)]
 pub fn get_visible_articles_by_series(
     conn: &mut SqliteConnection,
     series: &str,
@@ -286,7 +284,6 @@ pub fn get_visible_articles_by_series(
     }
 }
 
-#[allow(dead_code)]
 pub fn get_visible_articles_by_tag(
     conn: &mut SqliteConnection,
     tag: String,
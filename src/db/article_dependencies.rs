@@ -0,0 +1,62 @@
+use crate::db::schema::article_dependencies;
+use diesel::prelude::*;
+
+/// `src_file_name` is built from `path` (both relative to the input directory), `kind` is the
+/// directive which caused the dependency, e.g. `gallery` for the directory of a `[[!gallery]]`
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = article_dependencies)]
+pub struct ArticleDependency {
+    pub src_file_name: String,
+    pub kind: String,
+    pub path: String,
+}
+
+pub fn add_article_dependency(
+    conn: &mut SqliteConnection,
+    dependency: &ArticleDependency,
+) -> QueryResult<()> {
+    diesel::replace_into(article_dependencies::table)
+        .values(dependency)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Removes all dependencies of an article, they are recorded again while its plugins are evaluated
+pub fn del_article_dependencies(
+    conn: &mut SqliteConnection,
+    src_file_name: &str,
+) -> QueryResult<()> {
+    diesel::delete(
+        article_dependencies::table.filter(article_dependencies::src_file_name.eq(src_file_name)),
+    )
+    .execute(conn)?;
+    Ok(())
+}
+
+/// The `src_file_name`s of the articles depending on `path` by `kind`
+pub fn get_dependent_articles(
+    conn: &mut SqliteConnection,
+    kind: &str,
+    path: &str,
+) -> QueryResult<Vec<String>> {
+    article_dependencies::table
+        .filter(article_dependencies::kind.eq(kind))
+        .filter(article_dependencies::path.eq(path))
+        .order(article_dependencies::src_file_name)
+        .select(article_dependencies::src_file_name)
+        .load(conn)
+}
+
+/// The `src_file_name`s of the articles with a dependency of `kind`, e.g. the ones with a
+/// `[[!pages]]`
+pub fn get_articles_by_dependency_kind(
+    conn: &mut SqliteConnection,
+    kind: &str,
+) -> QueryResult<Vec<String>> {
+    article_dependencies::table
+        .filter(article_dependencies::kind.eq(kind))
+        .order(article_dependencies::src_file_name)
+        .select(article_dependencies::src_file_name)
+        .distinct()
+        .load(conn)
+}
@@ -0,0 +1,108 @@
+use crate::db::schema::article_revisions;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = article_revisions)]
+pub struct ArticleRevision {
+    pub id: i32,
+    pub src_file_name: String,
+    pub hash: String,
+    /// The markdown after evaluating the plugins, as passed to pandoc
+    pub markdown: String,
+    /// The `.mdwn` file as written by the author, used to restore the revision
+    pub source: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = article_revisions)]
+pub struct NewArticleRevision<'a> {
+    pub src_file_name: &'a str,
+    pub hash: &'a str,
+    pub markdown: &'a str,
+    pub source: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+pub fn add_revision(
+    conn: &mut SqliteConnection,
+    revision: &NewArticleRevision,
+) -> QueryResult<i32> {
+    diesel::insert_into(article_revisions::table)
+        .values(revision)
+        .returning(article_revisions::id)
+        .get_result(conn)
+}
+
+/// Deletes all but the `keep` newest revisions of an article
+pub fn del_old_revisions(
+    conn: &mut SqliteConnection,
+    src_file_name_query: &str,
+    keep: u32,
+) -> QueryResult<usize> {
+    let oldest_kept: Option<i32> = article_revisions::table
+        .filter(article_revisions::src_file_name.eq(src_file_name_query))
+        .order(article_revisions::id.desc())
+        .offset(i64::from(keep.max(1)) - 1)
+        .select(article_revisions::id)
+        .first(conn)
+        .optional()?;
+    match oldest_kept {
+        Some(oldest_kept) => diesel::delete(
+            article_revisions::table
+                .filter(article_revisions::src_file_name.eq(src_file_name_query))
+                .filter(article_revisions::id.lt(oldest_kept)),
+        )
+        .execute(conn),
+        None => Ok(0),
+    }
+}
+
+/// Deletes the revisions of a removed article
+pub fn del_revisions(conn: &mut SqliteConnection, src_file_name_query: &str) -> QueryResult<usize> {
+    diesel::delete(
+        article_revisions::table.filter(article_revisions::src_file_name.eq(src_file_name_query)),
+    )
+    .execute(conn)
+}
+
+/// Returns all revisions of an article, newest first
+pub fn get_revisions(
+    conn: &mut SqliteConnection,
+    src_file_name_query: &str,
+) -> QueryResult<Vec<ArticleRevision>> {
+    article_revisions::table
+        .filter(article_revisions::src_file_name.eq(src_file_name_query))
+        .order(article_revisions::id.desc())
+        .select(ArticleRevision::as_select())
+        .load(conn)
+}
+
+pub fn get_revision(
+    conn: &mut SqliteConnection,
+    src_file_name_query: &str,
+    revision_id: i32,
+) -> QueryResult<Option<ArticleRevision>> {
+    article_revisions::table
+        .filter(article_revisions::src_file_name.eq(src_file_name_query))
+        .filter(article_revisions::id.eq(revision_id))
+        .select(ArticleRevision::as_select())
+        .first(conn)
+        .optional()
+}
+
+/// Returns the revision which was replaced by `revision_id`, `None` for the first one
+pub fn get_previous_revision(
+    conn: &mut SqliteConnection,
+    src_file_name_query: &str,
+    revision_id: i32,
+) -> QueryResult<Option<ArticleRevision>> {
+    article_revisions::table
+        .filter(article_revisions::src_file_name.eq(src_file_name_query))
+        .filter(article_revisions::id.lt(revision_id))
+        .order(article_revisions::id.desc())
+        .select(ArticleRevision::as_select())
+        .first(conn)
+        .optional()
+}
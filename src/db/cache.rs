@@ -61,11 +61,15 @@ pub fn get_cache(conn: &mut SqliteConnection, src_file_name: String) -> Option<C
 }
 
 pub fn compute_hash(html: String) -> String {
+    compute_hash_bytes(html.as_bytes())
+}
+
+pub fn compute_hash_bytes(content: &[u8]) -> String {
     use std::hash::Hasher;
     use twox_hash::XxHash64;
 
     let mut hasher = XxHash64::default();
-    hasher.write(html.as_bytes());
+    hasher.write(content);
     let xxhash64_hash = hasher.finish();
     format!("{:x}", xxhash64_hash)
 }
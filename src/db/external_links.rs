@@ -0,0 +1,35 @@
+use crate::db::schema::external_links;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// The last check of an external url by the link checker, `status` is missing if the request failed
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = external_links)]
+pub struct ExternalLink {
+    pub url: String,
+    pub status: Option<i32>,
+    pub error: Option<String>,
+    pub checked_at: NaiveDateTime,
+}
+
+/// Returns the result of the last check of `url`, if it was done after `not_before`
+pub fn get_external_link(
+    conn: &mut SqliteConnection,
+    url_query: &str,
+    not_before: NaiveDateTime,
+) -> QueryResult<Option<ExternalLink>> {
+    external_links::table
+        .filter(external_links::url.eq(url_query))
+        .filter(external_links::checked_at.ge(not_before))
+        .select(ExternalLink::as_select())
+        .first(conn)
+        .optional()
+}
+
+/// Stores the result of a check, replacing the previous one of the same url
+pub fn set_external_link(conn: &mut SqliteConnection, link: &ExternalLink) -> QueryResult<()> {
+    diesel::replace_into(external_links::table)
+        .values(link)
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use crate::db::schema::git_commits;
+use crate::db::schema::git_lookups;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// One commit touching an article, as shown in the history of `content-template.html`
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = git_commits)]
+pub struct GitCommit {
+    pub commit_id: String,
+    pub author: String,
+    pub date: NaiveDateTime,
+    pub message: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = git_commits)]
+struct NewGitCommit<'a> {
+    src_file_name: &'a str,
+    commit_id: &'a str,
+    author: &'a str,
+    date: NaiveDateTime,
+    message: &'a str,
+}
+
+/// Returns the cached history of an article, newest commit first, if it was looked up at `head`
+pub fn get_git_history(
+    conn: &mut SqliteConnection,
+    src_file_name_query: &str,
+    head_query: &str,
+) -> QueryResult<Option<Vec<GitCommit>>> {
+    let cached: Option<String> = git_lookups::table
+        .filter(git_lookups::src_file_name.eq(src_file_name_query))
+        .filter(git_lookups::head.eq(head_query))
+        .select(git_lookups::src_file_name)
+        .first(conn)
+        .optional()?;
+    if cached.is_none() {
+        return Ok(None);
+    }
+
+    get_git_commits(conn, src_file_name_query).map(Some)
+}
+
+/// Returns the history of an article from the last lookup, no matter which `head` it was done at
+pub fn get_git_commits(
+    conn: &mut SqliteConnection,
+    src_file_name_query: &str,
+) -> QueryResult<Vec<GitCommit>> {
+    git_commits::table
+        .filter(git_commits::src_file_name.eq(src_file_name_query))
+        .order(git_commits::id.asc())
+        .select(GitCommit::as_select())
+        .load(conn)
+}
+
+/// Replaces the cached history of an article, `commits` are expected newest first
+pub fn set_git_history(
+    conn: &mut SqliteConnection,
+    src_file_name_value: &str,
+    head_value: &str,
+    commits: &[GitCommit],
+) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        del_git_history(conn, src_file_name_value)?;
+
+        diesel::insert_into(git_lookups::table)
+            .values((
+                git_lookups::src_file_name.eq(src_file_name_value),
+                git_lookups::head.eq(head_value),
+            ))
+            .execute(conn)?;
+
+        let new_commits: Vec<NewGitCommit> = commits
+            .iter()
+            .map(|commit| NewGitCommit {
+                src_file_name: src_file_name_value,
+                commit_id: &commit.commit_id,
+                author: &commit.author,
+                date: commit.date,
+                message: &commit.message,
+            })
+            .collect();
+        diesel::insert_into(git_commits::table)
+            .values(&new_commits)
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+pub fn del_git_history(conn: &mut SqliteConnection, src_file_name_value: &str) -> QueryResult<()> {
+    diesel::delete(git_commits::table.filter(git_commits::src_file_name.eq(src_file_name_value)))
+        .execute(conn)?;
+    diesel::delete(git_lookups::table.filter(git_lookups::src_file_name.eq(src_file_name_value)))
+        .execute(conn)?;
+    Ok(())
+}
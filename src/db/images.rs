@@ -0,0 +1,130 @@
+use crate::db::schema::{image_metadata, image_variants, images};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A source image used by `[[!img]]`, `src_file_name` is relative to the input directory
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = images)]
+pub struct Image {
+    pub src_file_name: String,
+    pub hash: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// A resized and re-encoded copy of the image with `hash`, `file_name` is relative to the output
+/// directory
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = image_variants)]
+pub struct ImageVariant {
+    pub file_name: String,
+    pub hash: String,
+    pub format: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Extracted from the image and its exif data, see `images::metadata::extract_metadata`
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, PartialEq, Eq)]
+#[diesel(table_name = image_metadata)]
+pub struct ImageMetadata {
+    pub hash: String,
+    pub width: i32,
+    pub height: i32,
+    pub orientation: i32,
+    pub caption: Option<String>,
+    pub date: Option<NaiveDateTime>,
+}
+
+pub fn get_image(conn: &mut SqliteConnection, src_file_name: &str) -> QueryResult<Option<Image>> {
+    images::table
+        .filter(images::src_file_name.eq(src_file_name))
+        .select(Image::as_select())
+        .first(conn)
+        .optional()
+}
+
+pub fn get_all_images(conn: &mut SqliteConnection) -> QueryResult<Vec<Image>> {
+    images::table.select(Image::as_select()).load(conn)
+}
+
+/// Stores `image`, replacing the entry of the same source file
+pub fn set_image(conn: &mut SqliteConnection, image: &Image) -> QueryResult<()> {
+    diesel::replace_into(images::table)
+        .values(image)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn del_image(conn: &mut SqliteConnection, src_file_name: &str) -> QueryResult<()> {
+    diesel::delete(images::table.filter(images::src_file_name.eq(src_file_name))).execute(conn)?;
+    Ok(())
+}
+
+/// Whether a source file with the content `hash` is still in use
+pub fn is_image_hash_used(conn: &mut SqliteConnection, hash: &str) -> QueryResult<bool> {
+    let count: i64 = images::table
+        .filter(images::hash.eq(hash))
+        .count()
+        .get_result(conn)?;
+    Ok(count > 0)
+}
+
+pub fn get_image_variants(
+    conn: &mut SqliteConnection,
+    hash: &str,
+) -> QueryResult<Vec<ImageVariant>> {
+    image_variants::table
+        .filter(image_variants::hash.eq(hash))
+        .order((image_variants::format, image_variants::width))
+        .select(ImageVariant::as_select())
+        .load(conn)
+}
+
+pub fn get_all_image_variants(conn: &mut SqliteConnection) -> QueryResult<Vec<ImageVariant>> {
+    image_variants::table
+        .select(ImageVariant::as_select())
+        .load(conn)
+}
+
+pub fn add_image_variant(conn: &mut SqliteConnection, variant: &ImageVariant) -> QueryResult<()> {
+    diesel::replace_into(image_variants::table)
+        .values(variant)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn del_image_variant(conn: &mut SqliteConnection, file_name: &str) -> QueryResult<()> {
+    diesel::delete(image_variants::table.filter(image_variants::file_name.eq(file_name)))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn get_image_metadata(
+    conn: &mut SqliteConnection,
+    hash: &str,
+) -> QueryResult<Option<ImageMetadata>> {
+    image_metadata::table
+        .filter(image_metadata::hash.eq(hash))
+        .select(ImageMetadata::as_select())
+        .first(conn)
+        .optional()
+}
+
+pub fn set_image_metadata(
+    conn: &mut SqliteConnection,
+    metadata: &ImageMetadata,
+) -> QueryResult<()> {
+    diesel::replace_into(image_metadata::table)
+        .values(metadata)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Removes the metadata of contents no source image has anymore, returns the number of removed rows
+pub fn del_unused_image_metadata(conn: &mut SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(image_metadata::table.filter(diesel::dsl::not(
+        image_metadata::hash.eq_any(images::table.select(images::hash)),
+    )))
+    .execute(conn)
+}
@@ -8,9 +8,15 @@ use diesel::r2d2::{self, ConnectionManager};
 pub type DbPool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
 
 pub mod article;
+pub mod article_dependencies;
+pub mod article_revisions;
 pub mod cache;
+pub mod external_links;
+pub mod git;
+pub mod images;
 pub mod schema;
-mod tests;
+pub(crate) mod tests;
+pub mod tokens;
 pub mod users;
 
 pub fn establish_connection_pool() -> DbPool {
@@ -1,5 +1,24 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    article_dependencies (src_file_name, kind, path) {
+        src_file_name -> Text,
+        kind -> Text,
+        path -> Text,
+    }
+}
+
+diesel::table! {
+    article_revisions (id) {
+        id -> Integer,
+        src_file_name -> Text,
+        hash -> Text,
+        markdown -> Text,
+        source -> Text,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     article_tags (article_id, tag_id) {
         article_id -> Integer,
@@ -33,6 +52,80 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    external_links (url) {
+        url -> Text,
+        status -> Nullable<Integer>,
+        error -> Nullable<Text>,
+        checked_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    git_commits (id) {
+        id -> Integer,
+        src_file_name -> Text,
+        commit_id -> Text,
+        author -> Text,
+        date -> Timestamp,
+        message -> Text,
+    }
+}
+
+diesel::table! {
+    git_lookups (src_file_name) {
+        src_file_name -> Text,
+        head -> Text,
+    }
+}
+
+diesel::table! {
+    image_metadata (hash) {
+        hash -> Text,
+        width -> Integer,
+        height -> Integer,
+        orientation -> Integer,
+        caption -> Nullable<Text>,
+        date -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    image_variants (file_name) {
+        file_name -> Text,
+        hash -> Text,
+        format -> Text,
+        width -> Integer,
+        height -> Integer,
+    }
+}
+
+diesel::table! {
+    images (src_file_name) {
+        src_file_name -> Text,
+        hash -> Text,
+        width -> Integer,
+        height -> Integer,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Integer,
+        user_id -> Integer,
+        token_hash -> Text,
+        expires_at -> Timestamp,
+        revoked -> Bool,
+    }
+}
+
+diesel::table! {
+    revoked_tokens (jti) {
+        jti -> Text,
+        expires_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     tags (id) {
         id -> Integer,
@@ -51,5 +144,23 @@ diesel::table! {
 
 diesel::joinable!(article_tags -> articles (article_id));
 diesel::joinable!(article_tags -> tags (tag_id));
+diesel::joinable!(git_commits -> git_lookups (src_file_name));
+diesel::joinable!(refresh_tokens -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(article_tags, articles, cache, tags, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    article_dependencies,
+    article_revisions,
+    article_tags,
+    articles,
+    cache,
+    external_links,
+    git_commits,
+    git_lookups,
+    image_metadata,
+    image_variants,
+    images,
+    refresh_tokens,
+    revoked_tokens,
+    tags,
+    users,
+);
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use crate::db::article_dependencies::{
+        add_article_dependency, del_article_dependencies, get_articles_by_dependency_kind,
+        get_dependent_articles, ArticleDependency,
+    };
+    use crate::db::tests::establish_connection_and_initialize_schema;
+    use diesel::sqlite::SqliteConnection;
+
+    fn dependency(src_file_name: &str, kind: &str, path: &str) -> ArticleDependency {
+        ArticleDependency {
+            src_file_name: src_file_name.to_string(),
+            kind: kind.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_db_article_dependencies() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+
+        add_article_dependency(
+            &mut conn,
+            &dependency("posts/a.mdwn", "gallery", "media/trip"),
+        )
+        .unwrap();
+        // recording a dependency twice is fine
+        add_article_dependency(
+            &mut conn,
+            &dependency("posts/a.mdwn", "gallery", "media/trip"),
+        )
+        .unwrap();
+        add_article_dependency(
+            &mut conn,
+            &dependency("posts/a.mdwn", "gallery", "media/zoo"),
+        )
+        .unwrap();
+        add_article_dependency(
+            &mut conn,
+            &dependency("posts/b.mdwn", "gallery", "media/trip"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_dependent_articles(&mut conn, "gallery", "media/trip").unwrap(),
+            vec!["posts/a.mdwn", "posts/b.mdwn"]
+        );
+        assert_eq!(
+            get_dependent_articles(&mut conn, "include", "media/trip").unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            get_dependent_articles(&mut conn, "gallery", "media/zoo").unwrap(),
+            vec!["posts/a.mdwn"]
+        );
+        assert_eq!(
+            get_articles_by_dependency_kind(&mut conn, "gallery").unwrap(),
+            vec!["posts/a.mdwn", "posts/b.mdwn"]
+        );
+
+        del_article_dependencies(&mut conn, "posts/a.mdwn").unwrap();
+        assert_eq!(
+            get_dependent_articles(&mut conn, "gallery", "media/trip").unwrap(),
+            vec!["posts/b.mdwn"]
+        );
+        assert!(get_dependent_articles(&mut conn, "gallery", "media/zoo")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use crate::db::article_revisions::{
+        add_revision, del_old_revisions, del_revisions, get_previous_revision, get_revision,
+        get_revisions, NewArticleRevision,
+    };
+    use crate::db::tests::establish_connection_and_initialize_schema;
+    use chrono::NaiveDateTime;
+    use diesel::sqlite::SqliteConnection;
+
+    fn revision<'a>(src_file_name: &'a str, markdown: &'a str) -> NewArticleRevision<'a> {
+        NewArticleRevision {
+            src_file_name,
+            hash: "hash",
+            markdown,
+            source: markdown,
+            created_at: NaiveDateTime::parse_from_str("2025-03-15 12:00", "%Y-%m-%d %H:%M")
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_db_article_revisions() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+
+        let first = add_revision(&mut conn, &revision("posts/foo.mdwn", "foo 1")).unwrap();
+        let other = add_revision(&mut conn, &revision("posts/bar.mdwn", "bar 1")).unwrap();
+        let second = add_revision(&mut conn, &revision("posts/foo.mdwn", "foo 2")).unwrap();
+
+        let revisions = get_revisions(&mut conn, "posts/foo.mdwn").unwrap();
+        let ids: Vec<i32> = revisions.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![second, first]);
+        assert_eq!(revisions[0].markdown, "foo 2");
+
+        let previous = get_previous_revision(&mut conn, "posts/foo.mdwn", second).unwrap();
+        assert_eq!(previous.map(|r| r.id), Some(first));
+        assert!(get_previous_revision(&mut conn, "posts/foo.mdwn", first)
+            .unwrap()
+            .is_none());
+
+        // revisions are only found through their own article
+        assert!(get_revision(&mut conn, "posts/foo.mdwn", other)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            get_revision(&mut conn, "posts/bar.mdwn", other)
+                .unwrap()
+                .map(|r| r.markdown),
+            Some("bar 1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_db_del_article_revisions() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+
+        let ids: Vec<i32> = ["foo 1", "foo 2", "foo 3", "foo 4"]
+            .into_iter()
+            .map(|markdown| add_revision(&mut conn, &revision("posts/foo.mdwn", markdown)).unwrap())
+            .collect();
+        add_revision(&mut conn, &revision("posts/bar.mdwn", "bar 1")).unwrap();
+
+        let kept = |conn: &mut SqliteConnection, src_file_name: &str| -> Vec<i32> {
+            get_revisions(conn, src_file_name)
+                .unwrap()
+                .iter()
+                .map(|r| r.id)
+                .collect()
+        };
+        assert_eq!(
+            del_old_revisions(&mut conn, "posts/foo.mdwn", 5).unwrap(),
+            0
+        );
+        assert_eq!(
+            del_old_revisions(&mut conn, "posts/foo.mdwn", 2).unwrap(),
+            2
+        );
+        assert_eq!(kept(&mut conn, "posts/foo.mdwn"), vec![ids[3], ids[2]]);
+        assert_eq!(kept(&mut conn, "posts/bar.mdwn").len(), 1);
+
+        assert_eq!(del_revisions(&mut conn, "posts/foo.mdwn").unwrap(), 2);
+        assert!(kept(&mut conn, "posts/foo.mdwn").is_empty());
+        assert_eq!(kept(&mut conn, "posts/bar.mdwn").len(), 1);
+    }
+}
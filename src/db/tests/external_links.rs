@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod tests {
+    use crate::db::external_links::{get_external_link, set_external_link, ExternalLink};
+    use crate::db::tests::establish_connection_and_initialize_schema;
+    use chrono::NaiveDateTime;
+    use diesel::sqlite::SqliteConnection;
+
+    fn date(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_db_external_links() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+        let url = "https://example.com/";
+
+        assert_eq!(
+            get_external_link(&mut conn, url, date("2025-01-01 00:00")).unwrap(),
+            None
+        );
+
+        let failed = ExternalLink {
+            url: url.to_string(),
+            status: None,
+            error: Some("connection refused".to_string()),
+            checked_at: date("2025-03-01 12:00"),
+        };
+        set_external_link(&mut conn, &failed).unwrap();
+        assert_eq!(
+            get_external_link(&mut conn, url, date("2025-03-01 00:00")).unwrap(),
+            Some(failed)
+        );
+
+        let ok = ExternalLink {
+            url: url.to_string(),
+            status: Some(200),
+            error: None,
+            checked_at: date("2025-03-02 12:00"),
+        };
+        set_external_link(&mut conn, &ok).unwrap();
+        assert_eq!(
+            get_external_link(&mut conn, url, date("2025-03-02 00:00")).unwrap(),
+            Some(ok)
+        );
+        // outdated results are checked again
+        assert_eq!(
+            get_external_link(&mut conn, url, date("2025-03-03 00:00")).unwrap(),
+            None
+        );
+    }
+}
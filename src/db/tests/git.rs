@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use crate::db::git::{
+        del_git_history, get_git_commits, get_git_history, set_git_history, GitCommit,
+    };
+    use crate::db::tests::establish_connection_and_initialize_schema;
+    use chrono::NaiveDateTime;
+    use diesel::sqlite::SqliteConnection;
+
+    fn commit(commit_id: &str, date: &str) -> GitCommit {
+        GitCommit {
+            commit_id: commit_id.to_string(),
+            author: "Joachim Schiele".to_string(),
+            date: NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            message: format!("commit {}", commit_id),
+        }
+    }
+
+    #[test]
+    fn test_db_git_history() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+        let src_file_name = "posts/foo.mdwn";
+
+        assert_eq!(
+            get_git_history(&mut conn, src_file_name, "head1").unwrap(),
+            None
+        );
+
+        let history = vec![
+            commit("c2", "2024-04-12 20:53"),
+            commit("c1", "2021-04-17 20:53"),
+        ];
+        set_git_history(&mut conn, src_file_name, "head1", &history).unwrap();
+        assert_eq!(
+            get_git_history(&mut conn, src_file_name, "head1").unwrap(),
+            Some(history.clone())
+        );
+
+        // HEAD moved, the cached lookup is outdated
+        assert_eq!(
+            get_git_history(&mut conn, src_file_name, "head2").unwrap(),
+            None
+        );
+        assert_eq!(get_git_commits(&mut conn, src_file_name).unwrap(), history);
+
+        // untracked articles are cached as well
+        set_git_history(&mut conn, src_file_name, "head2", &[]).unwrap();
+        assert_eq!(
+            get_git_history(&mut conn, src_file_name, "head2").unwrap(),
+            Some(vec![])
+        );
+
+        set_git_history(&mut conn, src_file_name, "head3", &history[..1]).unwrap();
+        del_git_history(&mut conn, src_file_name).unwrap();
+        assert_eq!(
+            get_git_history(&mut conn, src_file_name, "head3").unwrap(),
+            None
+        );
+        assert!(get_git_commits(&mut conn, src_file_name)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use crate::db::images::{
+        add_image_variant, del_image, del_image_variant, del_unused_image_metadata,
+        get_all_image_variants, get_all_images, get_image, get_image_metadata, get_image_variants,
+        is_image_hash_used, set_image, set_image_metadata, Image, ImageMetadata, ImageVariant,
+    };
+    use crate::db::tests::establish_connection_and_initialize_schema;
+    use diesel::sqlite::SqliteConnection;
+
+    fn variant(file_name: &str, hash: &str, format: &str, width: i32) -> ImageVariant {
+        ImageVariant {
+            file_name: file_name.to_string(),
+            hash: hash.to_string(),
+            format: format.to_string(),
+            width,
+            height: width / 2,
+        }
+    }
+
+    #[test]
+    fn test_db_images() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+        assert_eq!(get_image(&mut conn, "media/cat.jpg").unwrap(), None);
+
+        let cat = Image {
+            src_file_name: "media/cat.jpg".to_string(),
+            hash: "abc".to_string(),
+            width: 2000,
+            height: 1000,
+        };
+        set_image(&mut conn, &cat).unwrap();
+        assert_eq!(
+            get_image(&mut conn, "media/cat.jpg").unwrap(),
+            Some(cat.clone())
+        );
+
+        // the same content under another name shares the variants
+        let copy = Image {
+            src_file_name: "media/copy.jpg".to_string(),
+            ..cat.clone()
+        };
+        set_image(&mut conn, &copy).unwrap();
+        let changed = Image {
+            hash: "def".to_string(),
+            ..cat.clone()
+        };
+        set_image(&mut conn, &changed).unwrap();
+        assert_eq!(get_all_images(&mut conn).unwrap().len(), 2);
+        assert!(is_image_hash_used(&mut conn, "abc").unwrap());
+        del_image(&mut conn, "media/copy.jpg").unwrap();
+        assert!(!is_image_hash_used(&mut conn, "abc").unwrap());
+        assert!(is_image_hash_used(&mut conn, "def").unwrap());
+    }
+
+    #[test]
+    fn test_db_image_variants() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+
+        add_image_variant(
+            &mut conn,
+            &variant("images/cat-abc-960.webp", "abc", "webp", 960),
+        )
+        .unwrap();
+        add_image_variant(
+            &mut conn,
+            &variant("images/cat-abc-480.webp", "abc", "webp", 480),
+        )
+        .unwrap();
+        add_image_variant(
+            &mut conn,
+            &variant("images/cat-abc-480.avif", "abc", "avif", 480),
+        )
+        .unwrap();
+        add_image_variant(
+            &mut conn,
+            &variant("images/dog-def-480.avif", "def", "avif", 480),
+        )
+        .unwrap();
+
+        let variants: Vec<String> = get_image_variants(&mut conn, "abc")
+            .unwrap()
+            .into_iter()
+            .map(|variant| variant.file_name)
+            .collect();
+        assert_eq!(
+            variants,
+            vec![
+                "images/cat-abc-480.avif",
+                "images/cat-abc-480.webp",
+                "images/cat-abc-960.webp"
+            ]
+        );
+
+        del_image_variant(&mut conn, "images/cat-abc-480.avif").unwrap();
+        assert_eq!(get_image_variants(&mut conn, "abc").unwrap().len(), 2);
+        assert_eq!(get_all_image_variants(&mut conn).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_db_image_metadata() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+        assert_eq!(get_image_metadata(&mut conn, "abc").unwrap(), None);
+
+        let metadata = ImageMetadata {
+            hash: "abc".to_string(),
+            width: 1000,
+            height: 2000,
+            orientation: 6,
+            caption: Some("Sunset".to_string()),
+            date: chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(19, 30, 0),
+        };
+        set_image_metadata(&mut conn, &metadata).unwrap();
+        assert_eq!(
+            get_image_metadata(&mut conn, "abc").unwrap(),
+            Some(metadata.clone())
+        );
+
+        set_image(
+            &mut conn,
+            &Image {
+                src_file_name: "media/sunset.jpg".to_string(),
+                hash: "abc".to_string(),
+                width: 1000,
+                height: 2000,
+            },
+        )
+        .unwrap();
+        set_image_metadata(
+            &mut conn,
+            &ImageMetadata {
+                hash: "def".to_string(),
+                caption: None,
+                date: None,
+                ..metadata.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(del_unused_image_metadata(&mut conn).unwrap(), 1);
+        assert_eq!(get_image_metadata(&mut conn, "def").unwrap(), None);
+        assert_eq!(
+            get_image_metadata(&mut conn, "abc").unwrap(),
+            Some(metadata)
+        );
+    }
+}
@@ -1,5 +1,8 @@
+mod article_dependencies;
+mod article_revisions;
 mod del_by_id;
 mod del_by_src_file_name;
+mod external_links;
 mod get_all_articles;
 mod get_all_series_from_visible_articles;
 mod get_all_tags;
@@ -11,7 +14,10 @@ mod get_special_pages;
 mod get_visible_articles;
 mod get_visible_articles_by_series;
 mod get_visible_articles_by_tag;
+mod git;
+mod images;
 mod set;
+mod tokens;
 
 use crate::db::initialize_schema;
 use diesel::prelude::*;
@@ -25,6 +31,19 @@ pub fn establish_connection_and_initialize_schema() -> SqliteConnection {
     conn
 }
 
+/// A pool of a single in-memory database, e.g. for the handlers
+#[allow(dead_code)]
+pub fn create_pool_and_initialize_schema() -> crate::db::DbPool {
+    use diesel::r2d2::{ConnectionManager, Pool};
+
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+        .expect("Failed to create pool");
+    initialize_schema(&mut pool.get().unwrap());
+    pool
+}
+
 #[test]
 fn test_diesel_in_memory_sqlite() {
     let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use crate::auth::UserLevel;
+    use crate::db::tests::establish_connection_and_initialize_schema;
+    use crate::db::tokens::{
+        create_refresh_token, del_expired_tokens, is_jti_revoked, revoke_jti, revoke_refresh_token,
+        revoke_refresh_tokens_for_user, use_refresh_token,
+    };
+    use crate::db::users::create_user;
+    use chrono::{Duration, NaiveDateTime};
+    use diesel::sqlite::SqliteConnection;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2025-03-01 12:00", "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_db_refresh_tokens() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+        let user_id = create_user(&mut conn, "joachim", "hash", UserLevel::User).unwrap();
+
+        create_refresh_token(&mut conn, user_id, "hash1", now() + Duration::days(30)).unwrap();
+        create_refresh_token(&mut conn, user_id, "hash2", now() + Duration::days(30)).unwrap();
+        create_refresh_token(&mut conn, user_id, "hash3", now() - Duration::days(1)).unwrap();
+
+        assert_eq!(revoke_refresh_token(&mut conn, "hash1").unwrap(), 1);
+        assert_eq!(use_refresh_token(&mut conn, "hash1", now()).unwrap(), None);
+
+        assert_eq!(
+            revoke_refresh_tokens_for_user(&mut conn, user_id).unwrap(),
+            3
+        );
+        assert_eq!(use_refresh_token(&mut conn, "hash2", now()).unwrap(), None);
+
+        assert_eq!(del_expired_tokens(&mut conn, now()).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_db_use_refresh_token() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+        let user_id = create_user(&mut conn, "joachim", "hash", UserLevel::User).unwrap();
+
+        create_refresh_token(&mut conn, user_id, "hash1", now() + Duration::days(30)).unwrap();
+        create_refresh_token(&mut conn, user_id, "hash2", now() - Duration::days(1)).unwrap();
+
+        assert_eq!(
+            use_refresh_token(&mut conn, "hash1", now()).unwrap(),
+            Some(user_id)
+        );
+        // a refresh token can only be used once
+        assert_eq!(use_refresh_token(&mut conn, "hash1", now()).unwrap(), None);
+        assert_eq!(use_refresh_token(&mut conn, "hash2", now()).unwrap(), None);
+        assert_eq!(use_refresh_token(&mut conn, "hash3", now()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_db_revoked_jti() {
+        let mut conn: SqliteConnection = establish_connection_and_initialize_schema();
+
+        assert!(!is_jti_revoked(&mut conn, "abc").unwrap());
+        revoke_jti(&mut conn, "abc", now() + Duration::minutes(15)).unwrap();
+        // revoking twice is fine
+        revoke_jti(&mut conn, "abc", now() + Duration::minutes(15)).unwrap();
+        assert!(is_jti_revoked(&mut conn, "abc").unwrap());
+        assert!(!is_jti_revoked(&mut conn, "def").unwrap());
+
+        assert_eq!(del_expired_tokens(&mut conn, now()).unwrap(), 0);
+        assert_eq!(
+            del_expired_tokens(&mut conn, now() + Duration::hours(1)).unwrap(),
+            1
+        );
+        assert!(!is_jti_revoked(&mut conn, "abc").unwrap());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::db::schema::refresh_tokens;
+use crate::db::schema::revoked_tokens;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Insertable)]
+#[diesel(table_name = refresh_tokens)]
+pub struct NewRefreshToken<'a> {
+    pub user_id: i32,
+    pub token_hash: &'a str,
+    pub expires_at: NaiveDateTime,
+}
+
+pub fn create_refresh_token(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    token_hash: &str,
+    expires_at: NaiveDateTime,
+) -> QueryResult<()> {
+    let new_token = NewRefreshToken {
+        user_id,
+        token_hash,
+        expires_at,
+    };
+
+    diesel::insert_into(refresh_tokens::table)
+        .values(&new_token)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn revoke_refresh_token(
+    conn: &mut SqliteConnection,
+    token_hash_query: &str,
+) -> QueryResult<usize> {
+    use crate::db::schema::refresh_tokens::dsl::*;
+
+    diesel::update(refresh_tokens.filter(token_hash.eq(token_hash_query)))
+        .set(revoked.eq(true))
+        .execute(conn)
+}
+
+/// Revokes a valid refresh token and returns its user, `None` if it was revoked or expired already
+///
+/// The check and the revocation are one `UPDATE`, so concurrent refreshes can't both use the token.
+pub fn use_refresh_token(
+    conn: &mut SqliteConnection,
+    token_hash_query: &str,
+    now: NaiveDateTime,
+) -> QueryResult<Option<i32>> {
+    use crate::db::schema::refresh_tokens::dsl::*;
+
+    conn.transaction(|conn| {
+        let used = diesel::update(
+            refresh_tokens
+                .filter(token_hash.eq(token_hash_query))
+                .filter(revoked.eq(false))
+                .filter(expires_at.gt(now)),
+        )
+        .set(revoked.eq(true))
+        .execute(conn)?;
+        if used == 0 {
+            return Ok(None);
+        }
+        refresh_tokens
+            .filter(token_hash.eq(token_hash_query))
+            .select(user_id)
+            .first(conn)
+            .optional()
+    })
+}
+
+pub fn revoke_refresh_tokens_for_user(
+    conn: &mut SqliteConnection,
+    user_id_query: i32,
+) -> QueryResult<usize> {
+    use crate::db::schema::refresh_tokens::dsl::*;
+
+    diesel::update(refresh_tokens.filter(user_id.eq(user_id_query)))
+        .set(revoked.eq(true))
+        .execute(conn)
+}
+
+/// Adds the `jti` of an access token to the revocation list, the entry is kept until the token would expire anyway
+pub fn revoke_jti(
+    conn: &mut SqliteConnection,
+    jti_value: &str,
+    expires_at_value: NaiveDateTime,
+) -> QueryResult<()> {
+    use crate::db::schema::revoked_tokens::dsl::*;
+
+    diesel::insert_into(revoked_tokens)
+        .values((jti.eq(jti_value), expires_at.eq(expires_at_value)))
+        .on_conflict(jti)
+        .do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn is_jti_revoked(conn: &mut SqliteConnection, jti_value: &str) -> QueryResult<bool> {
+    use crate::db::schema::revoked_tokens::dsl::*;
+
+    let count: i64 = revoked_tokens
+        .filter(jti.eq(jti_value))
+        .count()
+        .get_result(conn)?;
+    Ok(count > 0)
+}
+
+/// Removes expired refresh tokens and revocation list entries which can't be used anymore
+pub fn del_expired_tokens(conn: &mut SqliteConnection, now: NaiveDateTime) -> QueryResult<usize> {
+    let refresh = diesel::delete(refresh_tokens::table.filter(refresh_tokens::expires_at.le(now)))
+        .execute(conn)?;
+    let revoked = diesel::delete(revoked_tokens::table.filter(revoked_tokens::expires_at.le(now)))
+        .execute(conn)?;
+    Ok(refresh + revoked)
+}
@@ -45,6 +45,29 @@ pub fn create_user(
         .first(conn)
 }
 
+/// Creates the user as `Admin`, an existing user with that name becomes an `Admin` with the new
+/// password
+pub fn set_admin(
+    conn: &mut SqliteConnection,
+    username_query: &str,
+    password_hash: &str,
+) -> QueryResult<i32> {
+    use crate::db::schema::users::dsl::*;
+
+    conn.transaction(|conn| match get_user_by_username(conn, username_query)? {
+        Some(user) => {
+            diesel::update(users.filter(id.eq(user.id)))
+                .set((
+                    password.eq(password_hash),
+                    level.eq(format!("{:?}", UserLevel::Admin)),
+                ))
+                .execute(conn)?;
+            Ok(user.id)
+        }
+        None => create_user(conn, username_query, password_hash, UserLevel::Admin),
+    })
+}
+
 pub fn get_user_by_username(
     conn: &mut SqliteConnection,
     username_query: &str,
@@ -56,3 +79,9 @@ pub fn get_user_by_username(
         .first(conn)
         .optional()
 }
+
+pub fn get_user_by_id(conn: &mut SqliteConnection, user_id: i32) -> QueryResult<Option<User>> {
+    use crate::db::schema::users::dsl::*;
+
+    users.filter(id.eq(user_id)).first(conn).optional()
+}
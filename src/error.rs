@@ -1,4 +1,8 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use diesel::result::Error as DieselError;
 use serde_json::json;
 use thiserror::Error;
@@ -7,16 +11,38 @@ use thiserror::Error;
 pub enum AppError {
     #[error("Authentication failed")]
     AuthError,
+    #[error("Permission denied")]
+    Forbidden,
+    #[error("Not found")]
+    NotFound,
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Payload too large")]
+    PayloadTooLarge,
     #[error("Database error: {0}")]
     DatabaseError(#[from] DieselError),
     #[error("Internal server error")]
     InternalError,
+    #[error("Too many requests, retry after {0} seconds")]
+    RateLimited(u64),
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
+        let retry_after = match self {
+            AppError::RateLimited(seconds) => Some(seconds),
+            _ => None,
+        };
+
         let (status, error_message) = match self {
             AppError::AuthError => (StatusCode::UNAUTHORIZED, self.to_string()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::NotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::Conflict(_) => (StatusCode::CONFLICT, self.to_string()),
+            AppError::PayloadTooLarge => (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()),
             AppError::DatabaseError(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Database error".to_string(),
@@ -25,12 +51,18 @@ impl IntoResponse for AppError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal server error".to_string(),
             ),
+            AppError::RateLimited(_) => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
         };
 
         let body = Json(json!({
             "error": error_message
         }));
 
-        (status, body).into_response()
+        match retry_after {
+            Some(seconds) => {
+                (status, [(header::RETRY_AFTER, seconds.to_string())], body).into_response()
+            }
+            None => (status, body).into_response(),
+        }
     }
 }
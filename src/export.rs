@@ -0,0 +1,129 @@
+use crate::config;
+use crate::links::{extract_links, resolve_site_link};
+use crate::safe_path::resolve_uri_path;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Directories of the output which are maintained by `--production` and `--export` builds
+pub const MAINTAINED_DIRS: [&str; 2] = ["assets", "wasm"];
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<usize> {
+    let mut copied = 0;
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            copied += copy_dir(&path, &target)?;
+        } else {
+            let content = std::fs::read(&path)?;
+            std::fs::write(&target, &content)?;
+            crate::compression::write_siblings(
+                &target,
+                &content,
+                config::Config::get().compression.precompress,
+            );
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// A copy made by an earlier export is kept unless the source changed since
+fn is_up_to_date(source: &Path, target: &Path) -> bool {
+    match (source.metadata(), target.metadata()) {
+        (Ok(source), Ok(target)) => {
+            source.len() == target.len()
+                && matches!(
+                    (source.modified(), target.modified()),
+                    (Ok(source), Ok(target)) if target >= source
+                )
+        }
+        _ => false,
+    }
+}
+
+/// Generated pages, relative to `output`
+pub fn collect_pages(output: &Path, dir: &Path, pages: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let relative = path.strip_prefix(output).unwrap_or(&path).to_path_buf();
+        if path.is_dir() {
+            if !MAINTAINED_DIRS.iter().any(|dir| relative == Path::new(dir)) {
+                collect_pages(output, &path, pages)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "html") {
+            pages.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Copies the files of the input tree the generated pages link to (like `media/foo.jpg` or the
+/// `posts/foo.mdwn` sources) into the output
+fn copy_referenced_input_files() -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let cfg = config::Config::get();
+    let input = cfg.input.canonicalize()?;
+
+    let mut pages = Vec::new();
+    collect_pages(&cfg.output, &cfg.output, &mut pages)?;
+
+    let mut copied = 0;
+    for page in pages {
+        let html = std::fs::read_to_string(cfg.output.join(&page))?;
+        for link in extract_links(&html) {
+            let Some(site_link) = resolve_site_link(&page, &cfg.subdir, &link) else {
+                continue;
+            };
+            if site_link.path.is_empty()
+                || MAINTAINED_DIRS
+                    .iter()
+                    .any(|dir| site_link.path.starts_with(&format!("{}/", dir)))
+            {
+                continue;
+            }
+            let Ok(source) = resolve_uri_path(&input, &site_link.path) else {
+                continue;
+            };
+            let Ok(relative) = source.strip_prefix(&input) else {
+                continue;
+            };
+            let target = cfg.output.join(relative);
+            // photos are copied without their metadata by `images::strip_input_images` already
+            let stripped = cfg.images.strip_exif && crate::images::is_strippable(&source);
+            if !source.is_file() || stripped || is_up_to_date(&source, &target) {
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(&source, &target)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// Makes the output directory a self-contained site which can be served by any static host: the
+/// assets are copied by `renderer::assets::copy_assets_to_output` before the build, this adds the
+/// wasm runtime (unless `static_build_only` is set) and the referenced files of the input tree.
+pub fn export_site() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let cfg = config::Config::get();
+    println!("====== Exporting self-contained site to 'output' directory ======");
+
+    let output_wasm = cfg.output.join("wasm");
+    if output_wasm.exists() {
+        std::fs::remove_dir_all(&output_wasm)?;
+    }
+    if !cfg.static_build_only {
+        let copied = copy_dir(&cfg.wasm, &output_wasm)?;
+        println!("Copied {} wasm files", copied);
+    }
+
+    let copied = copy_referenced_input_files()?;
+    println!(
+        "Copied {} referenced files from the input directory",
+        copied
+    );
+    Ok(())
+}
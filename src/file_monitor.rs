@@ -1,6 +1,7 @@
 use crate::registry::PubSubRegistry;
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
+use notify::event::ModifyKind;
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::Path;
 use std::path::PathBuf;
@@ -92,41 +93,71 @@ fn handle_event(pool: &DbPool, event: &Event) {
     let input_path: PathBuf = cfg.input.clone();
 
     for path in &event.paths {
-        if let Some(extension) = path.extension() {
-            if extension == "mdwn" {
-                if let Ok(relative_path) = path.strip_prefix(std::env::current_dir().unwrap()) {
-                    let relative_article_path: PathBuf = relative_path
-                        .strip_prefix(input_path.clone())
-                        .unwrap()
-                        .to_path_buf();
-                    // let event_type = match event.kind {
-                    //     EventKind::Create(_) => "📝 created",
-                    //     EventKind::Modify(_) => "✏️ modified",
-                    //     EventKind::Remove(_) => "🗑️ removed",
-                    //     _ => return,
-                    // };
-                    // println!(
-                    //     "  📍 Path: {} was {}",
-                    //     relative_article_path.display(),
-                    //     event_type
-                    // );
-
-                    let pankat_event: PankatFileMonitorEvent = PankatFileMonitorEvent {
-                        kind: event.kind,
-                        path: relative_article_path.to_path_buf(),
-                    };
-                    debounce(
-                        pool,
-                        pankat_event,
-                        relative_article_path.display().to_string(),
-                    );
+        let Ok(relative_path) = path.strip_prefix(std::env::current_dir().unwrap()) else {
+            continue;
+        };
+        let Ok(relative_path) = relative_path.strip_prefix(input_path.clone()) else {
+            continue;
+        };
+        if relative_path
+            .extension()
+            .is_some_and(|extension| extension == "mdwn")
+        {
+            let relative_article_path: PathBuf = relative_path.to_path_buf();
+            // let event_type = match event.kind {
+            //     EventKind::Create(_) => "📝 created",
+            //     EventKind::Modify(_) => "✏️ modified",
+            //     EventKind::Remove(_) => "🗑️ removed",
+            //     _ => return,
+            // };
+            // println!(
+            //     "  📍 Path: {} was {}",
+            //     relative_article_path.display(),
+            //     event_type
+            // );
+
+            let pankat_event: PankatFileMonitorEvent = PankatFileMonitorEvent {
+                kind: event.kind,
+                path: relative_article_path.to_path_buf(),
+            };
+            debounce(pool, pankat_event);
+        }
+        if matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            // e.g. an image of a [[!gallery]] or an [[!include]]d file, the articles built from it
+            // are rendered again, even if their own source didn't change
+            let dependents = match pool.get() {
+                Ok(mut conn) => crate::articles::dependent_articles(&mut conn, relative_path),
+                Err(e) => {
+                    println!("Error getting a connection from the pool: {}", e);
+                    continue;
+                }
+            };
+            for relative_article_path in dependents {
+                // an article including itself is already handled above
+                if relative_article_path == relative_path {
+                    continue;
                 }
+                println!(
+                    "Re-rendering {} as {} changed",
+                    relative_article_path.display(),
+                    relative_path.display()
+                );
+                let pankat_event: PankatFileMonitorEvent = PankatFileMonitorEvent {
+                    kind: EventKind::Modify(ModifyKind::Any),
+                    path: relative_article_path.clone(),
+                };
+                debounce(pool, pankat_event);
             }
         }
     }
 }
 
-fn debounce(pool: &DbPool, pankat_event: PankatFileMonitorEvent, a: String) {
+/// Processes `pankat_event` after a moment, further events for its path are merged into it, the
+/// rendered article is sent to the websocket viewers of that path
+fn debounce(pool: &DbPool, pankat_event: PankatFileMonitorEvent) {
     // Debounce logic
     use std::collections::HashMap;
     use std::sync::Mutex;
@@ -154,9 +185,6 @@ fn debounce(pool: &DbPool, pankat_event: PankatFileMonitorEvent, a: String) {
     // );
     let lock = Arc::new(tokio::sync::Mutex::new(()));
     tokio::spawn(async move {
-        let (sender, _) = PubSubRegistry::instance()
-            .get_sender_receiver_by_name(a)
-            .await;
         loop {
             let next_event = {
                 let cache = EVENT_CACHE.lock().unwrap();
@@ -170,18 +198,51 @@ fn debounce(pool: &DbPool, pankat_event: PankatFileMonitorEvent, a: String) {
                 Some((event, instant)) => {
                     let now = Instant::now();
                     if instant <= now {
-                        // Remove the entry
-                        {
-                            let mut cache = EVENT_CACHE.lock().unwrap();
-                            cache.remove(&event);
+                        // the loops of all pending events share the cache, only the one removing the
+                        // entry processes it
+                        if EVENT_CACHE.lock().unwrap().remove(&event).is_none() {
+                            continue;
                         }
                         let _guard = lock.lock().await;
 
                         println!("Processing cached event for: {}", event.path.display());
-                        match crate::articles::file_monitor_articles_change(
-                            &mut pool.get().unwrap(),
-                            &event,
-                        ) {
+                        let mut conn = pool.get().unwrap();
+                        let src_file_name = event.path.display().to_string();
+                        let before = crate::db::article::get_article_with_tags_by_src_file_name(
+                            &mut conn,
+                            src_file_name.clone(),
+                        )
+                        .unwrap_or(None);
+                        let result =
+                            crate::articles::file_monitor_articles_change(&mut conn, &event);
+                        let after = crate::db::article::get_article_with_tags_by_src_file_name(
+                            &mut conn,
+                            src_file_name,
+                        )
+                        .unwrap_or(None);
+                        // the [[!pages]] listing the article show its new title, tags, ...
+                        for listing_path in
+                            crate::articles::listing_articles(&mut conn, &before, &after)
+                        {
+                            println!(
+                                "Re-rendering {} as it lists {}",
+                                listing_path.display(),
+                                event.path.display()
+                            );
+                            debounce(
+                                &pool,
+                                PankatFileMonitorEvent {
+                                    kind: EventKind::Modify(ModifyKind::Any),
+                                    path: listing_path,
+                                },
+                            );
+                        }
+                        drop(conn);
+                        // the viewers of this event's article, not of the one that spawned the loop
+                        let (sender, _) = PubSubRegistry::instance()
+                            .get_sender_receiver_by_name(event.path.display().to_string())
+                            .await;
+                        match result {
                             Ok(data) => {
                                 // println!("sending the good news");
                                 //println!("sending the good news: {}", data);
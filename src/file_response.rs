@@ -0,0 +1,274 @@
+use crate::compression::{is_compressible, negotiate, Encoding};
+use crate::config;
+use crate::error::AppError;
+use crate::renderer::html::create_html_from_not_found_template;
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use std::io::SeekFrom;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// `404 Not Found` rendered from the themable `templates/404.html`
+pub fn not_found(uri_path: &str) -> Response {
+    match create_html_from_not_found_template(uri_path) {
+        Ok(html) => (StatusCode::NOT_FOUND, Html(html)).into_response(),
+        Err(e) => {
+            println!("Error rendering 404 page: {}", e);
+            AppError::NotFound.into_response()
+        }
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Derived from size and modification time, so no file has to be read to answer a conditional GET,
+/// each `Content-Encoding` is a representation of its own
+fn create_etag(len: u64, modified: SystemTime, encoding: Option<Encoding>) -> String {
+    let nanos = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    match encoding {
+        Some(encoding) => format!("\"{:x}-{:x}-{}\"", len, nanos, encoding.name()),
+        None => format!("\"{:x}-{:x}\"", len, nanos),
+    }
+}
+
+/// A precompressed `.br`/`.gz` sibling is only used if it isn't older than the file itself
+async fn precompressed_sibling(
+    path: &Path,
+    encoding: Encoding,
+    modified: SystemTime,
+) -> Option<PathBuf> {
+    let sibling = encoding.sibling(path);
+    let metadata = tokio::fs::metadata(&sibling).await.ok()?;
+    (metadata.is_file() && metadata.modified().ok()? >= modified).then_some(sibling)
+}
+
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.trim() == "*"
+        || if_none_match.split(',').any(|candidate| {
+            let candidate = candidate.trim();
+            candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+        })
+}
+
+/// `Last-Modified` is transmitted with a precision of seconds
+fn not_modified_since(if_modified_since: &str, modified: SystemTime) -> bool {
+    match httpdate::parse_http_date(if_modified_since) {
+        Ok(since) => unix_seconds(modified) <= unix_seconds(since),
+        Err(_) => false,
+    }
+}
+
+/// Parses a `Range` header for a file of `len` bytes
+///
+/// Returns `None` if the whole file should be served (no or unsupported range, e.g. multiple
+/// ranges) and `Some(Err(()))` if the range can't be satisfied (`416 Range Not Satisfiable`).
+fn parse_range(range: &str, len: u64) -> Option<Result<RangeInclusive<u64>, ()>> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // suffix range: the last `end` bytes
+        let suffix: u64 = end.parse().ok()?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        return Some(Ok(len.saturating_sub(suffix)..=len - 1));
+    }
+
+    let start: u64 = start.parse().ok()?;
+    let end: u64 = if end.is_empty() {
+        u64::MAX
+    } else {
+        end.parse().ok()?
+    };
+    if start > end {
+        return None;
+    }
+    if start >= len {
+        return Some(Err(()));
+    }
+    Some(Ok(start..=end.min(len - 1)))
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Serves a file with `ETag`, `Last-Modified` and `Cache-Control: max-age`, answers conditional
+/// requests with `304 Not Modified` and single byte ranges with `206 Partial Content`.
+/// HTML, CSS, JS and wasm are sent with the `Content-Encoding` negotiated from `Accept-Encoding`,
+/// from a precompressed sibling if there is one or compressed on the fly.
+/// Missing files and directories result in the 404 page for `uri_path`.
+pub async fn file_response(
+    path: &Path,
+    uri_path: &str,
+    request_headers: &HeaderMap,
+    max_age: u64,
+) -> Response {
+    let metadata = match tokio::fs::metadata(path).await {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return not_found(uri_path),
+    };
+    let len = metadata.len();
+    let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+    let compressible = is_compressible(path);
+
+    let mut encoding = None;
+    let mut precompressed = None;
+    if let Some(accepted) = header_str(request_headers, header::ACCEPT_ENCODING)
+        .filter(|_| compressible)
+        .and_then(negotiate)
+    {
+        precompressed = precompressed_sibling(path, accepted, modified).await;
+        if precompressed.is_some() || config::Config::get().compression.dynamic {
+            encoding = Some(accepted);
+        }
+    }
+
+    let etag = create_etag(len, modified, encoding);
+    let last_modified = httpdate::fmt_http_date(modified);
+
+    let mut headers = HeaderMap::new();
+    let mut set_header = |name: header::HeaderName, value: &str| {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(name, value);
+        }
+    };
+    set_header(header::ETAG, &etag);
+    set_header(header::LAST_MODIFIED, &last_modified);
+    set_header(
+        header::CACHE_CONTROL,
+        &format!("public, max-age={}", max_age),
+    );
+    set_header(header::ACCEPT_RANGES, "bytes");
+    if compressible {
+        set_header(header::VARY, "accept-encoding");
+    }
+
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110, 13.2.2)
+    let not_modified = match header_str(request_headers, header::IF_NONE_MATCH) {
+        Some(if_none_match) => etag_matches(if_none_match, &etag),
+        None => header_str(request_headers, header::IF_MODIFIED_SINCE)
+            .is_some_and(|since| not_modified_since(since, modified)),
+    };
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+
+    let mime_type = mime_guess::from_path(path).first_or_text_plain();
+    set_header(header::CONTENT_TYPE, mime_type.as_ref());
+
+    // ranges are only served from the uncompressed file
+    if let Some(encoding) = encoding {
+        let contents = match precompressed {
+            Some(sibling) => tokio::fs::read(sibling).await,
+            None => match tokio::fs::read(path).await {
+                Ok(contents) => encoding.compress(&contents, false),
+                Err(e) => Err(e),
+            },
+        };
+        set_header(header::CONTENT_ENCODING, encoding.name());
+        return match contents {
+            Ok(contents) => (StatusCode::OK, headers, Body::from(contents)).into_response(),
+            Err(_) => AppError::InternalError.into_response(),
+        };
+    }
+
+    // a range is only applied to the representation the client already has parts of
+    let if_range_matches = match header_str(request_headers, header::IF_RANGE) {
+        Some(if_range) => if_range.trim() == etag || if_range.trim() == last_modified,
+        None => true,
+    };
+    let range = header_str(request_headers, header::RANGE)
+        .filter(|_| if_range_matches)
+        .and_then(|range| parse_range(range, len));
+
+    match range {
+        None => match tokio::fs::read(path).await {
+            Ok(contents) => (StatusCode::OK, headers, Body::from(contents)).into_response(),
+            Err(_) => AppError::InternalError.into_response(),
+        },
+        Some(Err(())) => {
+            set_header(header::CONTENT_RANGE, &format!("bytes */{}", len));
+            (StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response()
+        }
+        Some(Ok(range)) => {
+            let (start, end) = (*range.start(), *range.end());
+            match read_range(path, start, end - start + 1).await {
+                Ok(contents) => {
+                    set_header(
+                        header::CONTENT_RANGE,
+                        &format!("bytes {}-{}/{}", start, end, len),
+                    );
+                    (StatusCode::PARTIAL_CONTENT, headers, Body::from(contents)).into_response()
+                }
+                Err(_) => AppError::InternalError.into_response(),
+            }
+        }
+    }
+}
+
+async fn read_range(path: &Path, start: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut contents = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut contents).await?;
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok(0..=99)));
+        assert_eq!(parse_range("bytes=900-", 1000), Some(Ok(900..=999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Ok(900..=999)));
+        assert_eq!(parse_range("bytes=-2000", 1000), Some(Ok(0..=999)));
+        assert_eq!(parse_range("bytes=500-5000", 1000), Some(Ok(500..=999)));
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(Err(())));
+        // unsupported or invalid ranges are ignored, the whole file is served
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(parse_range("bytes=9-1", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+    }
+
+    #[test]
+    fn test_conditional_get() {
+        let modified = UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+        let etag = create_etag(42, modified, None);
+
+        assert!(etag_matches(&etag, &etag));
+        assert!(etag_matches(&format!("\"foo\", W/{}", etag), &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(!etag_matches("\"foo\"", &etag));
+        assert_ne!(etag, create_etag(43, modified, None));
+        assert_ne!(etag, create_etag(42, modified, Some(Encoding::Gzip)));
+
+        let last_modified = httpdate::fmt_http_date(modified);
+        assert!(not_modified_since(&last_modified, modified));
+        assert!(!not_modified_since(
+            &last_modified,
+            modified + Duration::from_secs(1)
+        ));
+        assert!(!not_modified_since("yesterday", modified));
+    }
+}
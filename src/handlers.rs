@@ -1,14 +1,34 @@
-use crate::auth::{create_token, validate_token, UserLevel};
+use crate::articles::scaffold::ArticleScaffold;
+use crate::articles::ArticleWithTags;
+use crate::auth::{
+    build_cookie, create_refresh_token, create_token, get_cookie, hash_refresh_token, random_hex,
+    timestamp_to_naive, AuthenticatedUser, UserLevel, ACCESS_TOKEN_LIFETIME, CSRF_COOKIE,
+    REFRESH_COOKIE, REFRESH_TOKEN_LIFETIME, SESSION_COOKIE,
+};
 use crate::config;
-use crate::db::users::{create_user, get_user_by_username};
+use crate::db::article::{
+    get_all_articles, get_article_with_tags_by_id, get_article_with_tags_by_src_file_name,
+};
+use crate::db::article_revisions::{
+    get_previous_revision, get_revision, get_revisions, ArticleRevision,
+};
+use crate::db::cache::compute_hash;
+use crate::db::tokens::{
+    create_refresh_token as store_refresh_token, del_expired_tokens, revoke_jti,
+    revoke_refresh_token, revoke_refresh_tokens_for_user, use_refresh_token,
+};
+use crate::db::users::{create_user, get_user_by_id, get_user_by_username};
 use crate::error::AppError;
+use crate::file_response::{file_response, not_found};
 use crate::registry::*;
+use crate::renderer::diff::side_by_side_rows;
+use crate::renderer::html::create_html_from_revision_diff_template;
+use crate::safe_path::{resolve_path, resolve_uri_path};
 use axum::extract::ws::{Message, WebSocket};
-use axum::http::{header, StatusCode};
-use axum::response::Response;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
 use axum::{
-    extract::{ws::WebSocketUpgrade, State},
-    http::header::{HeaderMap, AUTHORIZATION},
+    extract::{ws::WebSocketUpgrade, Path, Query, State},
     Json,
 };
 use bcrypt::{hash, verify, DEFAULT_COST};
@@ -27,17 +47,104 @@ type DbPool = Pool<ConnectionManager<SqliteConnection>>;
 pub struct AuthRequest {
     username: String,
     password: String,
+    /// Issue an HttpOnly cookie session instead of returning the tokens as JSON
+    #[serde(default)]
+    cookie: bool,
 }
 
 #[derive(Serialize)]
 pub struct AuthResponse {
     token: String,
+    refresh_token: String,
+}
+
+#[derive(Serialize)]
+pub struct CookieSessionResponse {
+    csrf_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    /// Cookie sessions send `{}` and the refresh token is taken from the `REFRESH_COOKIE`
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LogoutRequest {
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeRequest {
+    username: String,
+}
+
+#[derive(Serialize)]
+pub struct RevokeResponse {
+    revoked: usize,
+}
+
+/// Issues a new access token together with a new refresh token, which is stored hashed
+fn issue_tokens(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    level: UserLevel,
+    csrf: Option<String>,
+) -> Result<AuthResponse, AppError> {
+    let token =
+        create_token(user_id.to_string(), level, csrf).map_err(|_| AppError::InternalError)?;
+
+    let (refresh_token, refresh_token_hash, expires_at) = create_refresh_token();
+    store_refresh_token(conn, user_id, &refresh_token_hash, expires_at)
+        .map_err(AppError::DatabaseError)?;
+
+    Ok(AuthResponse {
+        token,
+        refresh_token,
+    })
+}
+
+/// Replies with the tokens as JSON or, for cookie sessions, sets them as HttpOnly cookies
+fn auth_response(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    level: UserLevel,
+    cookie: bool,
+) -> Result<Response, AppError> {
+    if !cookie {
+        return Ok(Json(issue_tokens(conn, user_id, level, None)?).into_response());
+    }
+
+    let csrf_token = random_hex(16);
+    let tokens = issue_tokens(conn, user_id, level, Some(csrf_token.clone()))?;
+
+    let mut response = Json(CookieSessionResponse {
+        csrf_token: csrf_token.clone(),
+    })
+    .into_response();
+    let cookies = [
+        build_cookie(SESSION_COOKIE, &tokens.token, ACCESS_TOKEN_LIFETIME, true),
+        build_cookie(
+            REFRESH_COOKIE,
+            &tokens.refresh_token,
+            REFRESH_TOKEN_LIFETIME,
+            true,
+        ),
+        build_cookie(CSRF_COOKIE, &csrf_token, REFRESH_TOKEN_LIFETIME, false),
+    ];
+    for cookie in cookies {
+        response.headers_mut().append(
+            header::SET_COOKIE,
+            HeaderValue::from_str(&cookie).map_err(|_| AppError::InternalError)?,
+        );
+    }
+    Ok(response)
 }
 
 pub async fn register(
     State(pool): State<DbPool>,
     Json(req): Json<AuthRequest>,
-) -> Result<Json<AuthResponse>, AppError> {
+) -> Result<Response, AppError> {
     let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
 
     let hashed =
@@ -46,16 +153,13 @@ pub async fn register(
     let user_id = create_user(&mut conn, &req.username, &hashed, UserLevel::User)
         .map_err(AppError::DatabaseError)?;
 
-    let token =
-        create_token(user_id.to_string(), UserLevel::User).map_err(|_| AppError::InternalError)?;
-
-    Ok(Json(AuthResponse { token }))
+    auth_response(&mut conn, user_id, UserLevel::User, req.cookie)
 }
 
 pub async fn login(
     State(pool): State<DbPool>,
     Json(req): Json<AuthRequest>,
-) -> Result<Json<AuthResponse>, AppError> {
+) -> Result<Response, AppError> {
     let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
 
     let user = get_user_by_username(&mut conn, &req.username)
@@ -69,154 +173,648 @@ pub async fn login(
         return Err(AppError::AuthError);
     }
 
-    let level = serde_json::from_str(&user.level).unwrap_or(UserLevel::Guest);
+    let level = UserLevel::from_db(&user.level);
 
-    let token = create_token(user.id.to_string(), level).map_err(|_| AppError::InternalError)?;
+    let _ = del_expired_tokens(&mut conn, chrono::Utc::now().naive_utc());
 
-    Ok(Json(AuthResponse { token }))
+    auth_response(&mut conn, user.id, level, req.cookie)
 }
 
-pub async fn protected(headers: HeaderMap) -> Result<Json<&'static str>, AppError> {
-    let auth_header = headers
-        .get(AUTHORIZATION)
-        .and_then(|value| value.to_str().ok())
-        .and_then(|header| header.strip_prefix("Bearer "))
+/// Exchanges a refresh token for a new access token, the refresh token is rotated on every use
+pub async fn refresh(
+    State(pool): State<DbPool>,
+    headers: HeaderMap,
+    Json(req): Json<RefreshRequest>,
+) -> Result<Response, AppError> {
+    let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+
+    let (refresh_token, cookie) = match req.refresh_token {
+        Some(refresh_token) => (refresh_token, false),
+        None => (
+            get_cookie(&headers, REFRESH_COOKIE).ok_or(AppError::AuthError)?,
+            true,
+        ),
+    };
+
+    let user_id = use_refresh_token(
+        &mut conn,
+        &hash_refresh_token(&refresh_token),
+        chrono::Utc::now().naive_utc(),
+    )
+    .map_err(AppError::DatabaseError)?
+    .ok_or(AppError::AuthError)?;
+
+    let user = get_user_by_id(&mut conn, user_id)
+        .map_err(AppError::DatabaseError)?
         .ok_or(AppError::AuthError)?;
 
-    match validate_token(auth_header) {
-        Ok(token_data) => {
-            let _level = token_data.claims.level;
-            let _uid = token_data.claims.user_id;
-            Ok(Json("Welcome '{_uid}' to protected route with '{_level}'!"))
+    auth_response(&mut conn, user.id, UserLevel::from_db(&user.level), cookie)
+}
+
+/// Revokes the access token used for this request and, if given, the refresh token
+pub async fn logout(
+    State(pool): State<DbPool>,
+    headers: HeaderMap,
+    user: AuthenticatedUser,
+    Json(req): Json<LogoutRequest>,
+) -> Result<Response, AppError> {
+    let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+
+    revoke_jti(
+        &mut conn,
+        &user.claims.jti,
+        timestamp_to_naive(user.claims.exp),
+    )
+    .map_err(AppError::DatabaseError)?;
+
+    let refresh_token = match req.refresh_token {
+        Some(refresh_token) => Some(refresh_token),
+        None => get_cookie(&headers, REFRESH_COOKIE),
+    };
+    if let Some(refresh_token) = refresh_token {
+        revoke_refresh_token(&mut conn, &hash_refresh_token(&refresh_token))
+            .map_err(AppError::DatabaseError)?;
+    }
+
+    let mut response = Json("Logged out").into_response();
+    if user.via_cookie {
+        for (name, http_only) in [
+            (SESSION_COOKIE, true),
+            (REFRESH_COOKIE, true),
+            (CSRF_COOKIE, false),
+        ] {
+            response.headers_mut().append(
+                header::SET_COOKIE,
+                HeaderValue::from_str(&build_cookie(name, "", 0, http_only))
+                    .map_err(|_| AppError::InternalError)?,
+            );
         }
-        Err(_) => return Err(AppError::AuthError),
     }
+    Ok(response)
 }
 
-pub async fn serve_output(uri: axum::http::Uri) -> Result<Response, AppError> {
-    println!("Received request for URI (serve_output): {}", uri);
-    let cfg = config::Config::get();
-    let mut path = PathBuf::from(cfg.output.clone());
-
-    let path_str = uri.path();
-    if path_str == "/" {
-        path.push("index.html");
-    } else {
-        path.push(&path_str[1..]);
+/// Revokes all refresh tokens of a user, so access ends when the current access token expires
+pub async fn revoke(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Json(req): Json<RevokeRequest>,
+) -> Result<Json<RevokeResponse>, AppError> {
+    if user.claims.level != UserLevel::Admin {
+        return Err(AppError::Forbidden);
     }
 
-    match fs::read(&path).await {
-        Ok(contents) => {
-            let mime_type = mime_guess::from_path(&path).first_or_text_plain();
+    let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
 
-            let response = Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, mime_type.as_ref())
-                .body(contents.into())
-                .map_err(|_| AppError::InternalError)?;
+    let target = get_user_by_username(&mut conn, &req.username)
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
 
-            Ok(response)
-        }
-        Err(_) => {
-            if path_str != "/" {
-                return Err(AppError::InternalError);
-            }
+    let revoked =
+        revoke_refresh_tokens_for_user(&mut conn, target.id).map_err(AppError::DatabaseError)?;
+    println!(
+        "Revoked {} refresh token(s) of user '{}'",
+        revoked, req.username
+    );
 
-            let file_path = PathBuf::from(cfg.output.clone()).join("index.html");
-            let html = fs::read_to_string(file_path)
-                .await
-                .map_err(|_| AppError::InternalError)?;
+    Ok(Json(RevokeResponse { revoked }))
+}
 
-            let response = Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, "text/html")
-                .body(html.into())
-                .map_err(|_| AppError::InternalError)?;
+pub async fn protected(user: AuthenticatedUser) -> Result<Json<String>, AppError> {
+    let level = user.claims.level;
+    let uid = user.claims.user_id;
+    Ok(Json(format!(
+        "Welcome '{uid}' to protected route with '{level:?}'!"
+    )))
+}
 
-            Ok(response)
-        }
+#[derive(Serialize)]
+pub struct ArticleSource {
+    /// The id in the `articles` table, `None` until the file monitor picked up a new article
+    id: Option<i32>,
+    src_file_name: String,
+    dst_file_name: Option<String>,
+    content: String,
+    hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct ArticleSourceUpdate {
+    content: String,
+    /// The `hash` of the source the edit is based on, see `get_article_source`
+    hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct NewArticleRequest {
+    title: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    series: Option<String>,
+    #[serde(default)]
+    draft: bool,
+}
+
+/// Articles can only be read and written by admins, anyone may register a `User` account
+fn require_admin(user: &AuthenticatedUser) -> Result<(), AppError> {
+    if user.claims.level != UserLevel::Admin {
+        return Err(AppError::Forbidden);
     }
+    Ok(())
 }
 
-pub async fn serve_input(uri: axum::http::Uri) -> Result<Response, AppError> {
-    println!("Received request for URI (serve_input): {}", uri);
+/// Serializes the read-compare-write of `put_article_source`
+static ARTICLE_WRITE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Maps a `src_file_name` like `posts/foo.mdwn` to its location below `cfg.input`
+fn article_source_path(src_file_name: &str) -> Result<PathBuf, AppError> {
     let cfg = config::Config::get();
-    let mut input = PathBuf::from(cfg.input.clone());
 
-    let uri_path = PathBuf::from(uri.path());
-    let path_str = uri_path
-        .strip_prefix("/")
+    if std::path::Path::new(src_file_name)
+        .extension()
+        .is_none_or(|ext| ext != "mdwn")
+    {
+        return Err(AppError::BadRequest(
+            "Only .mdwn files can be edited".to_string(),
+        ));
+    }
+    resolve_path(&cfg.input, src_file_name)
+}
+
+/// Writes next to the article and renames, so the file monitor never sees a partial file
+async fn write_article_source(src_file_name: &str, content: &str) -> Result<(), AppError> {
+    let path = article_source_path(src_file_name)?;
+    let mut tmp_path = path.clone();
+    tmp_path.set_extension("mdwn.pankat-tmp");
+    fs::write(&tmp_path, content.as_bytes())
+        .await
         .map_err(|_| AppError::InternalError)?;
+    fs::rename(&tmp_path, &path)
+        .await
+        .map_err(|_| AppError::InternalError)
+}
+
+async fn read_article_source(
+    pool: &DbPool,
+    src_file_name: &str,
+) -> Result<ArticleSource, AppError> {
+    let path = article_source_path(src_file_name)?;
+    let content = fs::read_to_string(&path)
+        .await
+        .map_err(|_| AppError::NotFound)?;
+
+    let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+    let article = match get_article_with_tags_by_src_file_name(&mut conn, src_file_name.to_string())
+    {
+        Ok(Some(article)) => Some(article),
+        _ => None,
+    };
 
-    input.push(&path_str);
+    Ok(ArticleSource {
+        id: article.as_ref().and_then(|article| article.id),
+        src_file_name: src_file_name.to_string(),
+        dst_file_name: article.map(|article| article.dst_file_name),
+        hash: compute_hash(content.clone()),
+        content,
+    })
+}
 
-    match fs::read(&input).await {
-        Ok(contents) => {
-            let mime_type = mime_guess::from_path(&input).first_or_text_plain();
+/// Lists the `src_file_name` of all known articles, including drafts
+pub async fn list_article_sources(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<String>>, AppError> {
+    require_admin(&user)?;
+    let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+    let articles = get_all_articles(&mut conn).map_err(AppError::DatabaseError)?;
+    Ok(Json(
+        articles
+            .into_iter()
+            .map(|article| article.src_file_name)
+            .collect(),
+    ))
+}
 
-            let response = Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, mime_type.as_ref())
-                .body(contents.into())
-                .map_err(|_| AppError::InternalError)?;
+/// Returns the markdown source of an article, the `hash` has to be passed back on `PUT`
+async fn get_article_source(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Path(src_file_name): Path<String>,
+) -> Result<Response, AppError> {
+    require_admin(&user)?;
+    let source = read_article_source(&pool, &src_file_name).await?;
+    let etag = format!("\"{}\"", source.hash);
+
+    let mut response = Json(source).into_response();
+    response.headers_mut().insert(
+        header::ETAG,
+        HeaderValue::from_str(&etag).map_err(|_| AppError::InternalError)?,
+    );
+    Ok(response)
+}
 
-            Ok(response)
+/// Creates a new article in `posts/` like `pankat new`, replies with `409 Conflict` if it exists
+pub async fn create_article(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Json(req): Json<NewArticleRequest>,
+) -> Result<Response, AppError> {
+    require_admin(&user)?;
+    let scaffold = ArticleScaffold {
+        title: req.title,
+        tags: req.tags,
+        series: req.series,
+        draft: req.draft,
+    };
+    let src_file_name = scaffold.create().map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => AppError::Conflict(e.to_string()),
+        std::io::ErrorKind::InvalidInput => AppError::BadRequest(e.to_string()),
+        _ => {
+            println!("Error creating article: {}", e);
+            AppError::InternalError
         }
-        Err(_) => Err(AppError::InternalError),
+    })?;
+    println!(
+        "Article '{}' created by user '{}'",
+        src_file_name, user.claims.user_id
+    );
+
+    let source = read_article_source(&pool, &src_file_name).await?;
+    Ok((StatusCode::CREATED, Json(source)).into_response())
+}
+
+/// Overwrites the markdown source of an article if it wasn't changed since it was loaded,
+/// otherwise replies with `409 Conflict` and the current source. The file monitor picks up
+/// the write and updates the database, the output and the live preview as usual.
+pub async fn put_article_source(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Path(src_file_name): Path<String>,
+    Json(req): Json<ArticleSourceUpdate>,
+) -> Result<Response, AppError> {
+    require_admin(&user)?;
+    let _guard = ARTICLE_WRITE_LOCK.lock().await;
+
+    let current = read_article_source(&pool, &src_file_name).await?;
+    if current.hash != req.hash {
+        println!(
+            "Rejecting edit of '{}' by user '{}': source changed in the meantime",
+            src_file_name, user.claims.user_id
+        );
+        return Ok((StatusCode::CONFLICT, Json(current)).into_response());
     }
+
+    write_article_source(&src_file_name, &req.content).await?;
+    println!(
+        "Article '{}' saved by user '{}'",
+        src_file_name, user.claims.user_id
+    );
+
+    Ok(Json(read_article_source(&pool, &src_file_name).await?).into_response())
 }
 
-pub async fn serve_internals(uri: axum::http::Uri) -> Result<Response, AppError> {
-    println!("Received request for URI (serve_internals): {}", uri);
-    let mut path_to_serve = PathBuf::new();
-    let uri_path = PathBuf::from(uri.path());
+#[derive(Serialize)]
+pub struct RevisionSummary {
+    id: i32,
+    hash: String,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct Revision {
+    id: i32,
+    hash: String,
+    created_at: String,
+    markdown: String,
+    source: String,
+}
+
+#[derive(Deserialize)]
+pub struct RevisionDiffQuery {
+    /// Revision to compare with, defaults to the one before
+    against: Option<i32>,
+}
+
+fn format_revision_date(date: &chrono::NaiveDateTime) -> String {
+    date.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Returns the article with `id`, revisions are stored by `src_file_name`
+fn get_article_by_id(conn: &mut SqliteConnection, id: i32) -> Result<ArticleWithTags, AppError> {
+    match get_article_with_tags_by_id(conn, id) {
+        Ok(Some(article)) => Ok(article),
+        Ok(None) | Err(diesel::result::Error::NotFound) => Err(AppError::NotFound),
+        Err(e) => Err(AppError::DatabaseError(e)),
+    }
+}
+
+fn get_article_revision(
+    conn: &mut SqliteConnection,
+    src_file_name: &str,
+    revision_id: i32,
+) -> Result<ArticleRevision, AppError> {
+    get_revision(conn, src_file_name, revision_id)
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)
+}
+
+/// Lists the revisions of an article, newest first
+async fn list_revisions(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<RevisionSummary>>, AppError> {
+    require_admin(&user)?;
+    let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+    let article = get_article_by_id(&mut conn, id)?;
+    let revisions =
+        get_revisions(&mut conn, &article.src_file_name).map_err(AppError::DatabaseError)?;
+
+    Ok(Json(
+        revisions
+            .into_iter()
+            .map(|revision| RevisionSummary {
+                id: revision.id,
+                hash: revision.hash,
+                created_at: format_revision_date(&revision.created_at),
+            })
+            .collect(),
+    ))
+}
+
+async fn get_article_revision_by_id(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Path((id, revision_id)): Path<(i32, i32)>,
+) -> Result<Json<Revision>, AppError> {
+    require_admin(&user)?;
+    let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+    let article = get_article_by_id(&mut conn, id)?;
+    let revision = get_article_revision(&mut conn, &article.src_file_name, revision_id)?;
+
+    Ok(Json(Revision {
+        id: revision.id,
+        hash: revision.hash,
+        created_at: format_revision_date(&revision.created_at),
+        markdown: revision.markdown,
+        source: revision.source,
+    }))
+}
+
+/// Renders a side-by-side diff of the refined markdown of a revision and its predecessor
+async fn revision_diff(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Path((id, revision_id)): Path<(i32, i32)>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<Response, AppError> {
+    require_admin(&user)?;
     let cfg = config::Config::get();
+    let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+    let article = get_article_by_id(&mut conn, id)?;
+    let revision = get_article_revision(&mut conn, &article.src_file_name, revision_id)?;
+    let against = match query.against {
+        Some(against_id) => Some(get_article_revision(
+            &mut conn,
+            &article.src_file_name,
+            against_id,
+        )?),
+        None => get_previous_revision(&mut conn, &article.src_file_name, revision_id)
+            .map_err(AppError::DatabaseError)?,
+    };
+
+    let rows = side_by_side_rows(
+        against
+            .as_ref()
+            .map(|against| against.markdown.as_str())
+            .unwrap_or(""),
+        &revision.markdown,
+    );
+    let data = serde_json::json!({
+        "Title": article.title,
+        "Subdir": cfg.subdir,
+        "Revision": {
+            "Id": revision.id,
+            "Date": format_revision_date(&revision.created_at),
+        },
+        "Against": against.as_ref().map(|against| serde_json::json!({
+            "Id": against.id,
+            "Date": format_revision_date(&against.created_at),
+        })),
+        "Rows": rows,
+        "RestoreUrl": format!(
+            "{}/api/articles/{}/revisions/{}/restore",
+            cfg.subdir.display(),
+            id,
+            revision.id
+        ),
+    });
+
+    match create_html_from_revision_diff_template(data) {
+        Ok(html) => Ok(Html(html).into_response()),
+        Err(e) => {
+            println!("Error rendering revision diff: {}", e);
+            Err(AppError::InternalError)
+        }
+    }
+}
+
+/// Overwrites the article source with the one of a revision, which in turn creates a new revision
+async fn restore_revision(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Path((id, revision_id)): Path<(i32, i32)>,
+) -> Result<Json<ArticleSource>, AppError> {
+    require_admin(&user)?;
+    let _guard = ARTICLE_WRITE_LOCK.lock().await;
+
+    let revision = {
+        let mut conn = pool.get().map_err(|_| AppError::InternalError)?;
+        let article = get_article_by_id(&mut conn, id)?;
+        get_article_revision(&mut conn, &article.src_file_name, revision_id)?
+    };
+
+    write_article_source(&revision.src_file_name, &revision.source).await?;
+    println!(
+        "Article '{}' restored to revision {} by user '{}'",
+        revision.src_file_name, revision.id, user.claims.user_id
+    );
+
+    Ok(Json(
+        read_article_source(&pool, &revision.src_file_name).await?,
+    ))
+}
+
+/// The revision routes `/api/articles/<id>/revisions[/<revision_id>[/diff|/restore]]`, they share
+/// the wildcard of the article sources, which always end with `.mdwn`
+#[derive(Debug, PartialEq, Eq)]
+enum RevisionRoute {
+    List(i32),
+    Get(i32, i32),
+    Diff(i32, i32),
+    Restore(i32, i32),
+}
 
-    if let Some(first_path) = uri.path().split('/').nth(1) {
-        //println!("First path segment: {}", first_path);
-        match first_path {
-            "assets" => {
-                path_to_serve = PathBuf::from(cfg.assets.clone());
-                let path_str = uri_path
-                    .strip_prefix("/assets/")
-                    .map_err(|_| AppError::InternalError)?;
-                path_to_serve.push(&path_str);
+impl RevisionRoute {
+    fn parse(path: &str) -> Option<RevisionRoute> {
+        let number = |segment: &str| segment.parse::<i32>().ok();
+        let segments: Vec<&str> = path.split('/').collect();
+        match segments.as_slice() {
+            [id, "revisions"] => Some(RevisionRoute::List(number(id)?)),
+            [id, "revisions", revision_id] => {
+                Some(RevisionRoute::Get(number(id)?, number(revision_id)?))
             }
-            "wasm" => {
-                path_to_serve = PathBuf::from(cfg.wasm.clone());
-                let path_str = uri_path
-                    .strip_prefix("/wasm/")
-                    .map_err(|_| AppError::InternalError)?;
-                path_to_serve.push(&path_str);
+            [id, "revisions", revision_id, "diff"] => {
+                Some(RevisionRoute::Diff(number(id)?, number(revision_id)?))
             }
-            _ => {}
+            [id, "revisions", revision_id, "restore"] => {
+                Some(RevisionRoute::Restore(number(id)?, number(revision_id)?))
+            }
+            _ => None,
         }
-    } else {
-        return Err(AppError::InternalError);
     }
+}
+
+/// `GET /api/articles/*path`, the source of an article or its revisions, see `RevisionRoute`
+pub async fn get_article_path(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Path(path): Path<String>,
+    query: Query<RevisionDiffQuery>,
+) -> Result<Response, AppError> {
+    match RevisionRoute::parse(&path) {
+        Some(RevisionRoute::List(id)) => list_revisions(State(pool), user, Path(id))
+            .await
+            .map(IntoResponse::into_response),
+        Some(RevisionRoute::Get(id, revision_id)) => {
+            get_article_revision_by_id(State(pool), user, Path((id, revision_id)))
+                .await
+                .map(IntoResponse::into_response)
+        }
+        Some(RevisionRoute::Diff(id, revision_id)) => {
+            revision_diff(State(pool), user, Path((id, revision_id)), query).await
+        }
+        Some(RevisionRoute::Restore(..)) => Err(AppError::NotFound),
+        None => get_article_source(State(pool), user, Path(path)).await,
+    }
+}
+
+/// `POST /api/articles/<id>/revisions/<revision_id>/restore`
+pub async fn post_article_path(
+    State(pool): State<DbPool>,
+    user: AuthenticatedUser,
+    Path(path): Path<String>,
+) -> Result<Response, AppError> {
+    match RevisionRoute::parse(&path) {
+        Some(RevisionRoute::Restore(id, revision_id)) => {
+            restore_revision(State(pool), user, Path((id, revision_id)))
+                .await
+                .map(IntoResponse::into_response)
+        }
+        _ => Err(AppError::NotFound),
+    }
+}
+
+/// Maps `path_str` below `root`, anything which isn't allowed to be served is reported as missing
+async fn serve_file(
+    root: &std::path::Path,
+    path_str: &str,
+    uri_path: &str,
+    headers: &HeaderMap,
+    max_age: u64,
+) -> Response {
+    match resolve_uri_path(root, path_str) {
+        Ok(path) => file_response(&path, uri_path, headers, max_age).await,
+        Err(_) => not_found(uri_path),
+    }
+}
+
+pub async fn serve_output(uri: axum::http::Uri, headers: HeaderMap) -> Result<Response, AppError> {
+    println!("Received request for URI (serve_output): {}", uri);
+    let cfg = config::Config::get();
 
-    match fs::read(&path_to_serve).await {
-        Ok(contents) => {
-            let mime_type = mime_guess::from_path(&path_to_serve).first_or_text_plain();
+    let path_str = match uri.path() {
+        "/" => "/index.html",
+        path_str => path_str,
+    };
+
+    Ok(serve_file(
+        &cfg.output,
+        path_str,
+        uri.path(),
+        &headers,
+        cfg.cache.output_max_age,
+    )
+    .await)
+}
 
-            let response = Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, mime_type.as_ref())
-                .body(contents.into())
-                .map_err(|_| AppError::InternalError)?;
+pub async fn serve_input(uri: axum::http::Uri, headers: HeaderMap) -> Result<Response, AppError> {
+    println!("Received request for URI (serve_input): {}", uri);
+    let cfg = config::Config::get();
 
-            Ok(response)
+    if cfg.images.strip_exif && crate::images::is_strippable(std::path::Path::new(uri.path())) {
+        let uri_path = uri.path().to_string();
+        let stripped = tokio::task::spawn_blocking(move || {
+            crate::images::update_stripped_copy(&cfg.input, &cfg.output, &uri_path)
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|_| AppError::InternalError)?;
+        if let Err(e) = stripped {
+            println!("Error stripping metadata of {}: {}", uri.path(), e);
         }
-        Err(_) => Err(AppError::InternalError),
+        return Ok(serve_file(
+            &cfg.output,
+            uri.path(),
+            uri.path(),
+            &headers,
+            cfg.cache.input_max_age,
+        )
+        .await);
     }
+
+    Ok(serve_file(
+        &cfg.input,
+        uri.path(),
+        uri.path(),
+        &headers,
+        cfg.cache.input_max_age,
+    )
+    .await)
+}
+
+pub async fn serve_internals(
+    uri: axum::http::Uri,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    println!("Received request for URI (serve_internals): {}", uri);
+    let cfg = config::Config::get();
+
+    let uri_path = uri.path();
+    let (root, path_str, max_age) = if let Some(path_str) = uri_path.strip_prefix("/assets/") {
+        (&cfg.assets, path_str, cfg.cache.assets_max_age)
+    } else if let Some(path_str) = uri_path.strip_prefix("/wasm/") {
+        (&cfg.wasm, path_str, cfg.cache.wasm_max_age)
+    } else {
+        return Ok(not_found(uri_path));
+    };
+
+    Ok(serve_file(root, path_str, uri_path, &headers, max_age).await)
 }
 
-pub async fn websocket_route(ws: WebSocketUpgrade) -> Response {
+/// Anyone can subscribe to live updates of published articles, drafts require a session
+/// (a bearer token or, as browsers can't set headers on the handshake, the session cookie)
+pub async fn websocket_route(
+    ws: WebSocketUpgrade,
+    State(pool): State<DbPool>,
+    user: Option<AuthenticatedUser>,
+) -> Response {
     println!("Received request for new ws connection request");
-    ws.on_upgrade(handle_socket)
+    let authenticated = user.is_some();
+    ws.on_upgrade(move |socket| handle_socket(socket, pool, authenticated))
 }
 
-async fn handle_socket(mut socket: WebSocket) {
+async fn handle_socket(mut socket: WebSocket, pool: DbPool, authenticated: bool) {
     // Step 1: Wait for the initial message to determine the subscription
     let register_name = if let Some(Ok(Message::Text(text))) = socket.recv().await {
         match serde_json::from_str::<Value>(&text) {
@@ -238,6 +836,25 @@ async fn handle_socket(mut socket: WebSocket) {
         }
     };
 
+    if !authenticated {
+        let is_draft = match pool.get() {
+            Ok(mut conn) => {
+                match get_article_with_tags_by_src_file_name(&mut conn, register_name.clone()) {
+                    Ok(Some(article)) => article.draft == Some(true),
+                    _ => false,
+                }
+            }
+            Err(_) => true,
+        };
+        if is_draft {
+            println!(
+                "Refusing unauthenticated subscription to draft: {}",
+                register_name
+            );
+            return;
+        }
+    }
+
     println!("Registering for: {}", register_name);
 
     // Step 2: Get the sender-receiver based on the register name
@@ -0,0 +1,308 @@
+use crate::db::images::ImageMetadata;
+use chrono::{NaiveDate, NaiveDateTime};
+use exif::{In, Tag, Value};
+use std::error::Error;
+use std::io::Cursor;
+
+fn read_exif(content: &[u8]) -> Option<exif::Exif> {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(content))
+        .ok()
+}
+
+fn ascii_field(exif: &exif::Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .iter()
+            .map(|value| String::from_utf8_lossy(value).trim().to_string())
+            .find(|value| !value.is_empty()),
+        _ => None,
+    }
+}
+
+fn date_field(exif: &exif::Exif, tag: Tag) -> Option<NaiveDateTime> {
+    let Value::Ascii(values) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let date = exif::DateTime::from_ascii(values.first()?).ok()?;
+    NaiveDate::from_ymd_opt(date.year as i32, date.month as u32, date.day as u32)?.and_hms_opt(
+        date.hour as u32,
+        date.minute as u32,
+        date.second as u32,
+    )
+}
+
+/// The exif orientation, 1 (upright) if there is none
+pub fn orientation(content: &[u8]) -> u8 {
+    read_exif(content)
+        .and_then(|exif| {
+            exif.get_field(Tag::Orientation, In::PRIMARY)?
+                .value
+                .get_uint(0)
+        })
+        .filter(|orientation| (1..=8).contains(orientation))
+        .unwrap_or(1) as u8
+}
+
+/// Size, orientation, caption (`ImageDescription`) and date (`DateTimeOriginal`, `DateTime`) of a
+/// raster image, the size is the one after rotating the image according to its orientation
+pub fn extract_metadata(hash: &str, content: &[u8]) -> Result<ImageMetadata, Box<dyn Error>> {
+    let (width, height) = image::ImageReader::new(Cursor::new(content))
+        .with_guessed_format()?
+        .into_dimensions()?;
+    let exif = read_exif(content);
+    let orientation = orientation(content);
+    // 5 to 8 are rotated by 90 or 270 degrees
+    let (width, height) = if orientation >= 5 {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    Ok(ImageMetadata {
+        hash: hash.to_string(),
+        width: width as i32,
+        height: height as i32,
+        orientation: orientation as i32,
+        caption: exif
+            .as_ref()
+            .and_then(|exif| ascii_field(exif, Tag::ImageDescription)),
+        date: exif.as_ref().and_then(|exif| {
+            date_field(exif, Tag::DateTimeOriginal).or_else(|| date_field(exif, Tag::DateTime))
+        }),
+    })
+}
+
+/// An APP1 segment with an exif block which only contains the orientation, so stripped photos are
+/// still displayed upright
+fn orientation_segment(orientation: u8) -> Vec<u8> {
+    let mut segment = vec![0xFF, 0xE1, 0x00, 34];
+    segment.extend_from_slice(b"Exif\0\0");
+    // big endian tiff header, the first ifd follows directly
+    segment.extend_from_slice(&[b'M', b'M', 0x00, 0x2A, 0x00, 0x00, 0x00, 0x08]);
+    segment.extend_from_slice(&[0x00, 0x01]);
+    // tag 0x0112 (Orientation), type SHORT, count 1, value
+    segment.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01]);
+    segment.extend_from_slice(&[0x00, orientation, 0x00, 0x00]);
+    // no next ifd
+    segment.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+    segment
+}
+
+/// Drops the APP1 (exif, xmp) and APP13 (iptc) segments, the image data is copied as it is
+fn strip_jpeg(content: &[u8], orientation: u8) -> Option<Vec<u8>> {
+    let mut stripped = content.get(..2)?.to_vec();
+    let mut pos = 2;
+    let mut orientation_written = orientation == 1;
+    loop {
+        // markers may be preceded by fill bytes
+        while content.get(pos) == Some(&0xFF) && content.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        if *content.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *content.get(pos + 1)?;
+        if marker == 0xD9 || marker == 0xDA {
+            if !orientation_written {
+                stripped.extend(orientation_segment(orientation));
+            }
+            // the entropy coded data up to the end
+            stripped.extend_from_slice(&content[pos..]);
+            return Some(stripped);
+        }
+        if (0xD0..=0xD7).contains(&marker) || marker == 0x01 {
+            stripped.extend_from_slice(&content[pos..pos + 2]);
+            pos += 2;
+            continue;
+        }
+        let length = u16::from_be_bytes([*content.get(pos + 2)?, *content.get(pos + 3)?]) as usize;
+        let segment = content.get(pos..pos + 2 + length)?;
+        pos += 2 + length;
+        if marker == 0xE1 || marker == 0xED {
+            continue;
+        }
+        // after JFIF (APP0), which has to be the first segment
+        if !orientation_written && marker != 0xE0 {
+            stripped.extend(orientation_segment(orientation));
+            orientation_written = true;
+        }
+        stripped.extend_from_slice(segment);
+    }
+}
+
+/// Drops the `eXIf` and text chunks
+fn strip_png(content: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = content.get(..8)?.to_vec();
+    let mut pos = 8;
+    while pos < content.len() {
+        let length = u32::from_be_bytes(content.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let chunk = content.get(pos..pos + 12 + length)?;
+        pos += 12 + length;
+        if !matches!(&chunk[4..8], b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt") {
+            stripped.extend_from_slice(chunk);
+        }
+    }
+    Some(stripped)
+}
+
+/// Drops the `EXIF` and `XMP ` chunks and clears their flags in `VP8X`
+fn strip_webp(content: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = content.get(..12)?.to_vec();
+    let mut pos = 12;
+    while pos < content.len() {
+        let length = u32::from_le_bytes(content.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        // chunks are padded to an even size
+        let chunk = content.get(pos..(pos + 8 + length + length % 2).min(content.len()))?;
+        pos += 8 + length + length % 2;
+        match &chunk[..4] {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" if chunk.len() > 8 => {
+                let flags = stripped.len() + 8;
+                stripped.extend_from_slice(chunk);
+                stripped[flags] &= !(0x08 | 0x04);
+            }
+            _ => stripped.extend_from_slice(chunk),
+        }
+    }
+    let riff_size = (stripped.len() - 8) as u32;
+    stripped[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(stripped)
+}
+
+/// Removes exif (including GPS positions), xmp and iptc metadata from jpeg, png and webp files
+/// without re-encoding them, jpegs keep their orientation. `None` for other or broken files.
+pub fn strip_metadata(content: &[u8]) -> Option<Vec<u8>> {
+    if content.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg(content, orientation(content))
+    } else if content.starts_with(b"\x89PNG\r\n\x1a\n") {
+        strip_png(content)
+    } else if content.starts_with(b"RIFF") && content.get(8..12) == Some(b"WEBP".as_slice()) {
+        strip_webp(content)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::Field;
+
+    fn ascii(tag: Tag, value: &str) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        }
+    }
+
+    /// A 40x20 jpeg with a caption, a date, a GPS position and the given orientation
+    fn create_jpeg(orientation: u16) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(40, 20, image::Rgb([200, 100, 0]));
+        let mut jpeg = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+
+        let fields = [
+            ascii(Tag::ImageDescription, "Sunset at the lake"),
+            ascii(Tag::DateTimeOriginal, "2024:05:01 19:30:00"),
+            ascii(Tag::GPSLatitudeRef, "N"),
+            Field {
+                tag: Tag::Orientation,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![orientation]),
+            },
+        ];
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        let mut with_exif = jpeg[..2].to_vec();
+        with_exif.extend_from_slice(&[0xFF, 0xE1]);
+        with_exif.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+        with_exif.extend_from_slice(b"Exif\0\0");
+        with_exif.extend_from_slice(&tiff);
+        with_exif.extend_from_slice(&jpeg[2..]);
+        with_exif
+    }
+
+    #[test]
+    fn test_extract_metadata() {
+        let metadata = extract_metadata("abc", &create_jpeg(6)).unwrap();
+        assert_eq!(
+            metadata,
+            ImageMetadata {
+                hash: "abc".to_string(),
+                width: 20,
+                height: 40,
+                orientation: 6,
+                caption: Some("Sunset at the lake".to_string()),
+                date: NaiveDate::from_ymd_opt(2024, 5, 1)
+                    .unwrap()
+                    .and_hms_opt(19, 30, 0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_strip_jpeg() {
+        let jpeg = create_jpeg(6);
+        let stripped = strip_metadata(&jpeg).unwrap();
+        assert!(stripped.len() < jpeg.len());
+
+        let metadata = extract_metadata("abc", &stripped).unwrap();
+        assert_eq!((metadata.width, metadata.height), (20, 40));
+        assert_eq!(metadata.orientation, 6);
+        assert_eq!(metadata.caption, None);
+        assert_eq!(metadata.date, None);
+        let exif = read_exif(&stripped).unwrap();
+        assert_eq!(exif.fields().count(), 1);
+
+        let decoded = image::load_from_memory(&stripped).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (40, 20));
+
+        // upright photos have no exif at all
+        let stripped = strip_metadata(&create_jpeg(1)).unwrap();
+        assert!(read_exif(&stripped).is_none());
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn test_strip_png_and_webp() {
+        let image = image::RgbaImage::from_pixel(4, 4, image::Rgba([0, 0, 255, 255]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image.clone())
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        // a tEXt chunk before IEND
+        let end = png.len() - 12;
+        let text = b"tEXtComment\0secret";
+        let mut chunk = ((text.len() - 4) as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(text);
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        png.splice(end..end, chunk);
+
+        let stripped = strip_metadata(&png).unwrap();
+        assert!(!stripped.windows(6).any(|window| window == b"secret"));
+        assert!(image::load_from_memory(&stripped).is_ok());
+
+        let webp = webp::Encoder::from_rgba(&image, 4, 4).encode(80.0).to_vec();
+        let mut with_exif = webp.clone();
+        with_exif.extend_from_slice(b"EXIF");
+        with_exif.extend_from_slice(&3u32.to_le_bytes());
+        with_exif.extend_from_slice(b"gps\0");
+        let riff_size = (with_exif.len() - 8) as u32;
+        with_exif[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        let stripped = strip_metadata(&with_exif).unwrap();
+        assert_eq!(stripped, webp);
+        assert_eq!(strip_metadata(b"GIF89a"), None);
+    }
+}
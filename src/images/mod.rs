@@ -0,0 +1,934 @@
+use crate::articles::plugins::arguments::Parameter;
+use crate::config::{self, ImagesConfig};
+use crate::db::cache::compute_hash_bytes;
+use crate::db::images::{
+    add_image_variant, del_image, del_image_variant, del_unused_image_metadata,
+    get_all_image_variants, get_all_images, get_image, get_image_metadata, get_image_variants,
+    is_image_hash_used, set_image, set_image_metadata, Image, ImageVariant,
+};
+use crate::renderer::utils::escape_attribute;
+use chrono::NaiveDateTime;
+use diesel::SqliteConnection;
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+pub mod metadata;
+
+/// Directory of the output the variants are written to, maintained by `collect_garbage`
+pub const IMAGES_DIR: &str = "images";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Avif,
+    Webp,
+    Png,
+    Jpeg,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name.to_lowercase().as_str() {
+            "avif" => Some(Format::Avif),
+            "webp" => Some(Format::Webp),
+            "png" => Some(Format::Png),
+            "jpeg" | "jpg" => Some(Format::Jpeg),
+            _ => None,
+        }
+    }
+
+    /// Originals which get resized, animated gifs and svgs are used as they are
+    fn of_original(format: ImageFormat) -> Option<Format> {
+        match format {
+            ImageFormat::Png => Some(Format::Png),
+            ImageFormat::Jpeg => Some(Format::Jpeg),
+            ImageFormat::WebP => Some(Format::Webp),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Format::Avif => "avif",
+            Format::Webp => "webp",
+            Format::Png => "png",
+            Format::Jpeg => "jpeg",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Jpeg => "jpg",
+            _ => self.name(),
+        }
+    }
+
+    fn mime(&self) -> String {
+        format!("image/{}", self.name())
+    }
+
+    fn encode(
+        &self,
+        image: &DynamicImage,
+        settings: &ImagesConfig,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut encoded = Vec::new();
+        match self {
+            Format::Avif => {
+                let encoder = AvifEncoder::new_with_speed_quality(
+                    &mut encoded,
+                    settings.avif_speed.clamp(1, 10),
+                    settings.quality,
+                );
+                DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(encoder)?;
+            }
+            Format::Webp => {
+                let quality = settings.quality as f32;
+                if image.color().has_alpha() {
+                    let rgba = image.to_rgba8();
+                    let webp = webp::Encoder::from_rgba(&rgba, image.width(), image.height());
+                    encoded.extend_from_slice(&webp.encode(quality));
+                } else {
+                    let rgb = image.to_rgb8();
+                    let webp = webp::Encoder::from_rgb(&rgb, image.width(), image.height());
+                    encoded.extend_from_slice(&webp.encode(quality));
+                }
+            }
+            Format::Png => image.write_with_encoder(PngEncoder::new(&mut encoded))?,
+            Format::Jpeg => {
+                let encoder = JpegEncoder::new_with_quality(&mut encoded, settings.quality);
+                DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+            }
+        }
+        Ok(encoded)
+    }
+}
+
+/// An image of a `[[!img]]` with its variants, grouped by format and ordered by width
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponsiveImage {
+    pub width: u32,
+    pub height: u32,
+    pub caption: Option<String>,
+    pub date: Option<NaiveDateTime>,
+    pub variants: Vec<ImageVariant>,
+}
+
+/// The variants of an image: every configured format in every configured width up to the width of
+/// the original and the format of the original in the smaller widths, the original itself is the
+/// largest candidate of its format
+fn planned_variants(
+    stem: &str,
+    hash: &str,
+    width: u32,
+    height: u32,
+    original: Format,
+    settings: &ImagesConfig,
+) -> Vec<ImageVariant> {
+    let mut formats: Vec<Format> = Vec::new();
+    for name in &settings.formats {
+        match Format::from_name(name) {
+            Some(format) if format != original && !formats.contains(&format) => {
+                formats.push(format)
+            }
+            Some(_) => {}
+            None => println!("Unknown image format '{}' in [images] formats", name),
+        }
+    }
+    formats.push(original);
+
+    let mut widths: Vec<u32> = settings
+        .widths
+        .iter()
+        .copied()
+        .filter(|w| *w > 0 && *w < width)
+        .collect();
+    widths.sort();
+    widths.dedup();
+
+    let stem: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let mut variants = Vec::new();
+    for format in formats {
+        let mut format_widths = widths.clone();
+        if format != original {
+            format_widths.push(width);
+        }
+        for w in format_widths {
+            let h = ((height as u64 * w as u64 + width as u64 / 2) / width as u64).max(1) as u32;
+            variants.push(ImageVariant {
+                file_name: format!(
+                    "{}/{}-{}-{}.{}",
+                    IMAGES_DIR,
+                    stem,
+                    hash,
+                    w,
+                    format.extension()
+                ),
+                hash: hash.to_string(),
+                format: format.name().to_string(),
+                width: w as i32,
+                height: h as i32,
+            });
+        }
+    }
+    variants
+}
+
+/// Removes the variants of `hash` if no source image has this content anymore
+fn remove_unused_variants(
+    conn: &mut SqliteConnection,
+    output: &Path,
+    hash: &str,
+) -> Result<(), Box<dyn Error>> {
+    if is_image_hash_used(conn, hash)? {
+        return Ok(());
+    }
+    for variant in get_image_variants(conn, hash)? {
+        println!("Removing unused image variant: {}", variant.file_name);
+        let _ = std::fs::remove_file(output.join(&variant.file_name));
+        del_image_variant(conn, &variant.file_name)?;
+    }
+    Ok(())
+}
+
+/// Writes the missing variants of the image at `url` (relative to `input`) to `output`, the database
+/// remembers the metadata and variants of each source by the hash of its content, so unchanged
+/// images are neither decoded nor encoded again. `None` if there is no such file.
+///
+/// The encoding happens synchronously on the rendering thread, avif in particular takes seconds for
+/// large photos, see `avif_speed`.
+pub fn process_image(
+    conn: &mut SqliteConnection,
+    settings: &ImagesConfig,
+    input: &Path,
+    output: &Path,
+    url: &str,
+) -> Result<Option<ResponsiveImage>, Box<dyn Error>> {
+    let Ok(source) = crate::safe_path::resolve_uri_path(input, url) else {
+        return Ok(None);
+    };
+    if !source.is_file() {
+        return Ok(None);
+    }
+    let src_file_name = source
+        .strip_prefix(input.canonicalize()?)?
+        .to_string_lossy()
+        .replace('\\', "/");
+    let src_file_name = src_file_name.as_str();
+    let content = std::fs::read(&source)?;
+    let hash = compute_hash_bytes(&content);
+    // svgs and other files which are no raster images are used as they are
+    let Ok(format) = image::guess_format(&content) else {
+        return Ok(None);
+    };
+
+    let image_metadata = match get_image_metadata(conn, &hash)? {
+        Some(image_metadata) => image_metadata,
+        None => {
+            let image_metadata = metadata::extract_metadata(&hash, &content)?;
+            set_image_metadata(conn, &image_metadata)?;
+            image_metadata
+        }
+    };
+    let (width, height) = (image_metadata.width as u32, image_metadata.height as u32);
+
+    let previous = get_image(conn, src_file_name)?;
+    if previous.as_ref().map(|image| image.hash.as_str()) != Some(hash.as_str()) {
+        set_image(
+            conn,
+            &Image {
+                src_file_name: src_file_name.to_string(),
+                hash: hash.clone(),
+                width: width as i32,
+                height: height as i32,
+            },
+        )?;
+        if let Some(previous) = previous {
+            remove_unused_variants(conn, output, &previous.hash)?;
+        }
+    }
+
+    let mut responsive_image = ResponsiveImage {
+        width,
+        height,
+        caption: image_metadata.caption,
+        date: image_metadata.date,
+        variants: Vec::new(),
+    };
+    let Some(original) = Format::of_original(format).filter(|_| settings.responsive) else {
+        return Ok(Some(responsive_image));
+    };
+
+    let stem = source
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let variants = planned_variants(&stem, &hash, width, height, original, settings);
+    let existing: HashSet<String> = get_image_variants(conn, &hash)?
+        .into_iter()
+        .map(|variant| variant.file_name)
+        .collect();
+    let missing: Vec<&ImageVariant> = variants
+        .iter()
+        .filter(|variant| {
+            !existing.contains(&variant.file_name) || !output.join(&variant.file_name).is_file()
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        println!(
+            "Writing {} variants of image {}",
+            missing.len(),
+            src_file_name
+        );
+        let mut image = image::load_from_memory_with_format(&content, format)?;
+        // the variants have no exif data, so they are rotated instead
+        if let Some(orientation) =
+            image::metadata::Orientation::from_exif(image_metadata.orientation as u8)
+        {
+            image.apply_orientation(orientation);
+        }
+        std::fs::create_dir_all(output.join(IMAGES_DIR))?;
+        for variant in missing {
+            let resized = image.resize_exact(
+                variant.width as u32,
+                variant.height as u32,
+                FilterType::Lanczos3,
+            );
+            let format = Format::from_name(&variant.format).unwrap_or(original);
+            let encoded = format.encode(&resized, settings)?;
+            std::fs::write(output.join(&variant.file_name), encoded)?;
+            add_image_variant(conn, variant)?;
+        }
+    }
+
+    responsive_image.variants = variants;
+    Ok(Some(responsive_image))
+}
+
+/// The caption and the date the image was taken, e.g. `Sunset (2024-05-01)`
+pub fn image_title(image: &ResponsiveImage) -> Option<String> {
+    let date = image.date.map(|date| date.format("%Y-%m-%d").to_string());
+    match (&image.caption, date) {
+        (Some(caption), Some(date)) => Some(format!("{} ({})", caption, date)),
+        (Some(caption), None) => Some(caption.clone()),
+        (None, date) => date,
+    }
+}
+
+/// The html attributes of the `[[!img]]` parameters after the path, valueless ones like `hidden`
+/// are written as they are
+pub fn attributes_html(attributes: &[Parameter]) -> String {
+    attributes
+        .iter()
+        .map(|parameter| match &parameter.key {
+            Some(key) => format!(r#"{}="{}""#, key, escape_attribute(&parameter.value)),
+            None => escape_attribute(&parameter.value),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// `<picture>` with a `<source>` per format and an `<img>` of the original format as fallback, `alt`,
+/// `title`, `width` and `height` are filled from the metadata unless they are in `attributes`
+pub fn picture_html(
+    image: &ResponsiveImage,
+    url: &str,
+    attributes: &[Parameter],
+    settings: &ImagesConfig,
+    subdir: &Path,
+) -> String {
+    let srcset = |format: &str| {
+        image
+            .variants
+            .iter()
+            .filter(|variant| variant.format == format)
+            .map(|variant| {
+                format!(
+                    "{}/{} {}w",
+                    subdir.display(),
+                    variant.file_name,
+                    variant.width
+                )
+            })
+            .collect::<Vec<String>>()
+    };
+
+    let mut formats: Vec<&str> = Vec::new();
+    for variant in &image.variants {
+        if !formats.contains(&variant.format.as_str()) {
+            formats.push(variant.format.as_str());
+        }
+    }
+    // the original format is planned last
+    let original = formats.pop();
+
+    let url = escape_attribute(url);
+    let mut img = format!(r#"<img src="{}""#, url);
+    if let Some(original) = original {
+        let mut candidates = srcset(original);
+        candidates.push(format!("{} {}w", url, image.width));
+        img += &format!(
+            r#" srcset="{}" sizes="{}""#,
+            candidates.join(", "),
+            settings.sizes
+        );
+    }
+    let given = |name: &str| {
+        attributes.iter().any(|parameter| {
+            parameter
+                .key
+                .as_deref()
+                .is_some_and(|key| key.eq_ignore_ascii_case(name))
+        })
+    };
+    if !given("width") && !given("height") {
+        img += &format!(r#" width="{}" height="{}""#, image.width, image.height);
+    }
+    if let Some(caption) = image.caption.as_ref().filter(|_| !given("alt")) {
+        img += &format!(r#" alt="{}""#, escape_attribute(caption));
+    }
+    if let Some(title) = image_title(image).filter(|_| !given("title")) {
+        img += &format!(r#" title="{}""#, escape_attribute(&title));
+    }
+    if settings.lazy && !given("loading") {
+        img += r#" loading="lazy" decoding="async""#;
+    }
+    if !attributes.is_empty() {
+        img += " ";
+        img += &attributes_html(attributes);
+    }
+    img += ">";
+
+    if formats.is_empty() {
+        return img;
+    }
+    let sources: String = formats
+        .iter()
+        .map(|format| {
+            let mime = Format::from_name(format)
+                .map(|format| format.mime())
+                .unwrap_or_default();
+            format!(
+                r#"<source type="{}" srcset="{}" sizes="{}">"#,
+                mime,
+                srcset(format).join(", "),
+                settings.sizes
+            )
+        })
+        .collect();
+    format!("<picture>{}{}</picture>", sources, img)
+}
+
+/// The `<img>` (or `<picture>`) of `[[!img url attributes]]` with variants, metadata and lazy
+/// loading, `None` if `url` is not a raster image of the input directory
+pub fn img_html(
+    conn: &mut SqliteConnection,
+    url: &str,
+    attributes: &[Parameter],
+) -> Option<String> {
+    let cfg = config::Config::try_get()?;
+    if url.contains("://") || url.starts_with('/') {
+        return None;
+    }
+    match process_image(conn, &cfg.images, &cfg.input, &cfg.output, url) {
+        Ok(image) => {
+            image.map(|image| picture_html(&image, url, attributes, &cfg.images, &cfg.subdir))
+        }
+        Err(e) => {
+            println!("Error processing image {}: {}", url, e);
+            None
+        }
+    }
+}
+
+/// Photos are served and exported without their metadata with `[images] strip_exif`
+pub fn is_strippable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            matches!(
+                ext.to_ascii_lowercase().as_str(),
+                "jpg" | "jpeg" | "png" | "webp"
+            )
+        })
+}
+
+/// Writes the copy of `source` without metadata to `target` unless it is up to date, files which
+/// can't be parsed are not copied, so they are never served with their metadata
+///
+/// A `target` newer than `source` is up to date, with `verify` its content is compared instead, it
+/// might as well be a plain copy of the original, e.g. by an earlier `--export`.
+fn write_stripped_copy(source: &Path, target: &Path, verify: bool) -> Result<bool, Box<dyn Error>> {
+    if !verify {
+        let source_modified = source.metadata()?.modified()?;
+        if let Ok(target_modified) = target.metadata().and_then(|metadata| metadata.modified()) {
+            if target_modified >= source_modified {
+                return Ok(false);
+            }
+        }
+    }
+    let content = std::fs::read(source)?;
+    let stripped = metadata::strip_metadata(&content)
+        .ok_or_else(|| format!("Can't strip the metadata of {}", source.display()))?;
+    if verify && std::fs::read(target).is_ok_and(|copy| copy == stripped) {
+        return Ok(false);
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(target, stripped)?;
+    Ok(true)
+}
+
+/// Updates the stripped copy in `output` of the input file at `uri_path` if the file changed, the
+/// copy is served instead of the original
+pub fn update_stripped_copy(
+    input: &Path,
+    output: &Path,
+    uri_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let source = crate::safe_path::resolve_uri_path(input, uri_path)?;
+    let relative = source.strip_prefix(input.canonicalize()?)?;
+    write_stripped_copy(&source, &output.join(relative), false)?;
+    Ok(())
+}
+
+fn collect_strippable(dir: &Path, skip: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden || path == skip {
+            continue;
+        }
+        if path.is_dir() {
+            collect_strippable(&path, skip, files)?;
+        } else if is_strippable(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Writes copies without exif, xmp and iptc metadata of all photos in `input` to `output`, existing
+/// copies are compared with the stripped originals
+pub fn strip_input_images(input: &Path, output: &Path) -> Result<(), Box<dyn Error>> {
+    println!("====== Stripping metadata of images in 'input' directory ======");
+    // the output might be inside of the input directory
+    let input = input.canonicalize()?;
+    let output = output.canonicalize()?;
+    let (input, output) = (input.as_path(), output.as_path());
+    let mut files = Vec::new();
+    collect_strippable(input, output, &mut files)?;
+    let mut stripped = 0;
+    for source in files {
+        let target = output.join(source.strip_prefix(input)?);
+        match write_stripped_copy(&source, &target, true) {
+            Ok(true) => stripped += 1,
+            Ok(false) => {}
+            Err(e) => println!("Error: {}", e),
+        }
+    }
+    println!("Stripped metadata of {} images", stripped);
+    Ok(())
+}
+
+/// Removes stripped (or exported) copies of photos which are gone from the input directory
+fn remove_orphaned_copies(input: &Path, output: &Path, dir: &Path) -> std::io::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let relative = path.strip_prefix(output).unwrap_or(&path).to_path_buf();
+        if path.is_dir() {
+            let maintained = crate::export::MAINTAINED_DIRS
+                .iter()
+                .chain([IMAGES_DIR].iter())
+                .any(|maintained| relative == Path::new(maintained));
+            if !maintained {
+                remove_orphaned_copies(input, output, &path)?;
+            }
+        } else if dir != output && is_strippable(&path) && !input.join(&relative).exists() {
+            println!("Removing garbage 'output' entry: {:?}", relative);
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes entries of source images which are gone or changed, metadata and variants no source uses
+/// anymore, copies of removed photos and files in `output/images` which are not a known variant
+pub fn collect_garbage(
+    conn: &mut SqliteConnection,
+    input: &Path,
+    output: &Path,
+) -> Result<(), Box<dyn Error>> {
+    println!("====== Running GC on 'images' ======");
+    for image in get_all_images(conn)? {
+        let current = std::fs::read(input.join(&image.src_file_name))
+            .ok()
+            .map(|content| compute_hash_bytes(&content));
+        if current.as_deref() != Some(image.hash.as_str()) {
+            println!(
+                "Removing garbage 'images table' entry: {:?}",
+                image.src_file_name
+            );
+            del_image(conn, &image.src_file_name)?;
+        }
+    }
+    del_unused_image_metadata(conn)?;
+    remove_orphaned_copies(input, output, output)?;
+
+    let mut variants: HashSet<String> = HashSet::new();
+    for variant in get_all_image_variants(conn)? {
+        let path = output.join(&variant.file_name);
+        if is_image_hash_used(conn, &variant.hash)? && path.is_file() {
+            variants.insert(variant.file_name);
+            continue;
+        }
+        println!("Removing garbage image variant: {:?}", variant.file_name);
+        let _ = std::fs::remove_file(&path);
+        del_image_variant(conn, &variant.file_name)?;
+    }
+
+    let images_dir = output.join(IMAGES_DIR);
+    if !images_dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(&images_dir)? {
+        let path = entry?.path();
+        let relative = format!(
+            "{}/{}",
+            IMAGES_DIR,
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        if !variants.contains(&relative) {
+            println!("Removing garbage 'output' entry: {:?}", relative);
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::establish_connection_and_initialize_schema;
+    use crate::test_utils::create_temp_dir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn settings() -> ImagesConfig {
+        ImagesConfig {
+            widths: vec![16, 32, 128],
+            formats: vec!["webp".to_string()],
+            ..ImagesConfig::default()
+        }
+    }
+
+    fn create_dirs(name: &str) -> (PathBuf, PathBuf) {
+        let dir = create_temp_dir(name);
+        fs::create_dir_all(dir.join("input/media")).unwrap();
+        fs::create_dir_all(dir.join("output")).unwrap();
+        (dir.join("input"), dir.join("output"))
+    }
+
+    fn write_png(path: &Path, width: u32, height: u32, red: u8) {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb([red, 128, 0]));
+        image.save_with_format(path, ImageFormat::Png).unwrap();
+    }
+
+    fn file_names(image: &ResponsiveImage) -> Vec<String> {
+        image
+            .variants
+            .iter()
+            .map(|variant| variant.file_name.replace(&variant.hash, "HASH"))
+            .collect()
+    }
+
+    #[test]
+    fn test_process_image() {
+        let (input, output) = create_dirs("process-image");
+        let mut conn = establish_connection_and_initialize_schema();
+        write_png(&input.join("media/cat photo.png"), 64, 32, 255);
+
+        let image = process_image(
+            &mut conn,
+            &settings(),
+            &input,
+            &output,
+            "media/cat photo.png",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!((image.width, image.height), (64, 32));
+        assert_eq!(
+            file_names(&image),
+            vec![
+                "images/cat_photo-HASH-16.webp",
+                "images/cat_photo-HASH-32.webp",
+                "images/cat_photo-HASH-64.webp",
+                "images/cat_photo-HASH-16.png",
+                "images/cat_photo-HASH-32.png"
+            ]
+        );
+        assert_eq!(image.variants[0].height, 8);
+        for variant in &image.variants {
+            let written = image::open(output.join(&variant.file_name)).unwrap();
+            assert_eq!(written.width(), variant.width as u32);
+        }
+
+        // cached, nothing is encoded again
+        let webp = output.join(&image.variants[0].file_name);
+        fs::write(&webp, "cached").unwrap();
+        let cached = process_image(
+            &mut conn,
+            &settings(),
+            &input,
+            &output,
+            "media/cat photo.png",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(cached, image);
+        assert_eq!(fs::read_to_string(&webp).unwrap(), "cached");
+
+        // a changed image replaces the variants of the old one
+        write_png(&input.join("media/cat photo.png"), 64, 32, 0);
+        let changed = process_image(
+            &mut conn,
+            &settings(),
+            &input,
+            &output,
+            "media/cat photo.png",
+        )
+        .unwrap()
+        .unwrap();
+        assert_ne!(changed.variants[0].hash, image.variants[0].hash);
+        assert!(!webp.exists());
+        assert_eq!(fs::read_dir(output.join(IMAGES_DIR)).unwrap().count(), 5);
+
+        assert_eq!(
+            process_image(&mut conn, &settings(), &input, &output, "media/missing.png").unwrap(),
+            None
+        );
+
+        fs::remove_dir_all(input.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_picture_html() {
+        let variant = |format: &str, width: i32| ImageVariant {
+            file_name: format!("images/cat-abc-{}.{}", width, format),
+            hash: "abc".to_string(),
+            format: format.to_string(),
+            width,
+            height: width / 2,
+        };
+        let image = ResponsiveImage {
+            width: 64,
+            height: 32,
+            caption: None,
+            date: None,
+            variants: vec![variant("webp", 32), variant("webp", 64), variant("png", 32)],
+        };
+        let settings = ImagesConfig {
+            sizes: "100vw".to_string(),
+            ..ImagesConfig::default()
+        };
+        let attribute = |key: &str, value: &str| Parameter {
+            key: Some(key.to_string()),
+            value: value.to_string(),
+        };
+
+        assert_eq!(
+            picture_html(
+                &image,
+                "media/cat.png",
+                &[attribute("class", "noFancy")],
+                &settings,
+                Path::new("/blog")
+            ),
+            concat!(
+                r#"<picture><source type="image/webp" srcset="/blog/images/cat-abc-32.webp 32w, "#,
+                r#"/blog/images/cat-abc-64.webp 64w" sizes="100vw">"#,
+                r#"<img src="media/cat.png" srcset="/blog/images/cat-abc-32.png 32w, media/cat.png 64w" "#,
+                r#"sizes="100vw" width="64" height="32" loading="lazy" decoding="async" class="noFancy">"#,
+                r#"</picture>"#
+            )
+        );
+
+        // gifs and svgs are not resized
+        let gif = ResponsiveImage {
+            width: 64,
+            height: 32,
+            caption: Some(r#"The "pankat" logo"#.to_string()),
+            date: chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(19, 30, 0),
+            variants: Vec::new(),
+        };
+        assert_eq!(
+            picture_html(
+                &gif,
+                "media/logo.gif",
+                &[attribute("width", "200px")],
+                &settings,
+                Path::new("")
+            ),
+            concat!(
+                r#"<img src="media/logo.gif" alt="The &quot;pankat&quot; logo" "#,
+                r#"title="The &quot;pankat&quot; logo (2024-05-01)" loading="lazy" decoding="async" width="200px">"#
+            )
+        );
+        // given attributes win
+        assert_eq!(
+            picture_html(
+                &gif,
+                "media/logo.gif",
+                &[attribute("alt", "logo")],
+                &ImagesConfig {
+                    lazy: false,
+                    ..settings.clone()
+                },
+                Path::new("")
+            ),
+            concat!(
+                r#"<img src="media/logo.gif" width="64" height="32" "#,
+                r#"title="The &quot;pankat&quot; logo (2024-05-01)" alt="logo">"#
+            )
+        );
+        // only the keys count, not attributes which happen to contain them
+        assert_eq!(
+            picture_html(
+                &gif,
+                "media/logo.gif",
+                &[attribute("data-alt", "width=1 title=2")],
+                &ImagesConfig {
+                    lazy: false,
+                    ..settings.clone()
+                },
+                Path::new("")
+            ),
+            concat!(
+                r#"<img src="media/logo.gif" width="64" height="32" alt="The &quot;pankat&quot; logo" "#,
+                r#"title="The &quot;pankat&quot; logo (2024-05-01)" data-alt="width=1 title=2">"#
+            )
+        );
+        // the url can't end the attributes
+        assert_eq!(
+            picture_html(
+                &image,
+                r#"media/"cat".png"#,
+                &[],
+                &ImagesConfig {
+                    lazy: false,
+                    ..settings.clone()
+                },
+                Path::new("")
+            ),
+            concat!(
+                r#"<picture><source type="image/webp" srcset="/images/cat-abc-32.webp 32w, "#,
+                r#"/images/cat-abc-64.webp 64w" sizes="100vw">"#,
+                r#"<img src="media/&quot;cat&quot;.png" srcset="/images/cat-abc-32.png 32w, "#,
+                r#"media/&quot;cat&quot;.png 64w" sizes="100vw" width="64" height="32"></picture>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_strip_input_images() {
+        let (input, output) = create_dirs("strip-input-images");
+        let mut conn = establish_connection_and_initialize_schema();
+        fs::create_dir_all(input.join("posts/trip")).unwrap();
+        write_png(&input.join("posts/trip/beach.png"), 8, 8, 255);
+        fs::write(input.join("posts/trip/broken.jpg"), "no jpeg").unwrap();
+
+        strip_input_images(&input, &output).unwrap();
+        assert!(output.join("posts/trip/beach.png").is_file());
+        assert!(!output.join("posts/trip/broken.jpg").exists());
+
+        // a plain copy of the original is stripped although it is newer
+        let png = fs::read(input.join("posts/trip/beach.png")).unwrap();
+        let mut with_text = png[..33].to_vec();
+        with_text.extend_from_slice(&6u32.to_be_bytes());
+        with_text.extend_from_slice(b"tEXtGPS\0N1\0\0\0\0");
+        with_text.extend_from_slice(&png[33..]);
+        fs::write(input.join("posts/trip/lake.png"), &with_text).unwrap();
+        fs::write(output.join("posts/trip/lake.png"), &with_text).unwrap();
+        strip_input_images(&input, &output).unwrap();
+        assert_eq!(fs::read(output.join("posts/trip/lake.png")).unwrap(), png);
+
+        // requests only strip originals which are newer than their copy
+        fs::write(output.join("posts/trip/lake.png"), &with_text).unwrap();
+        update_stripped_copy(&input, &output, "/posts/trip/lake.png").unwrap();
+        assert_eq!(
+            fs::read(output.join("posts/trip/lake.png")).unwrap(),
+            with_text
+        );
+        fs::File::options()
+            .write(true)
+            .open(input.join("posts/trip/lake.png"))
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        update_stripped_copy(&input, &output, "/posts/trip/lake.png").unwrap();
+        assert_eq!(fs::read(output.join("posts/trip/lake.png")).unwrap(), png);
+
+        update_stripped_copy(&input, &output, "/posts/trip/beach.png").unwrap();
+        assert!(update_stripped_copy(&input, &output, "/posts/trip/broken.jpg").is_err());
+        assert!(update_stripped_copy(&input, &output, "/../secret.png").is_err());
+
+        fs::remove_file(input.join("posts/trip/beach.png")).unwrap();
+        collect_garbage(&mut conn, &input, &output).unwrap();
+        assert!(!output.join("posts/trip/beach.png").exists());
+
+        fs::remove_dir_all(input.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let (input, output) = create_dirs("images-gc");
+        let mut conn = establish_connection_and_initialize_schema();
+        write_png(&input.join("media/cat.png"), 40, 20, 255);
+        let image = process_image(&mut conn, &settings(), &input, &output, "media/cat.png")
+            .unwrap()
+            .unwrap();
+        fs::write(output.join(IMAGES_DIR).join("stray.webp"), "").unwrap();
+
+        collect_garbage(&mut conn, &input, &output).unwrap();
+        assert!(!output.join(IMAGES_DIR).join("stray.webp").exists());
+        assert!(output.join(&image.variants[0].file_name).exists());
+
+        fs::remove_file(input.join("media/cat.png")).unwrap();
+        collect_garbage(&mut conn, &input, &output).unwrap();
+        assert_eq!(fs::read_dir(output.join(IMAGES_DIR)).unwrap().count(), 0);
+        assert!(get_all_images(&mut conn).unwrap().is_empty());
+        assert!(get_all_image_variants(&mut conn).unwrap().is_empty());
+
+        fs::remove_dir_all(input.parent().unwrap()).unwrap();
+    }
+}
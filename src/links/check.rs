@@ -0,0 +1,459 @@
+use crate::config;
+use crate::db::external_links::{get_external_link, set_external_link, ExternalLink};
+use crate::links::{extract_ids, extract_links, is_external, resolve_site_link};
+use crate::safe_path::resolve_uri_path;
+use colored::Colorize;
+use diesel::SqliteConnection;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Checks external urls, a trait so tests (and offline builds) don't need the network
+pub trait HttpClient {
+    /// The status code of `url`, `Err` if there was no response at all
+    fn status(&self, url: &str) -> Result<u16, String>;
+}
+
+pub struct UreqClient {
+    agent: ureq::Agent,
+}
+
+impl UreqClient {
+    pub fn new(timeout: Duration) -> Self {
+        UreqClient {
+            agent: ureq::AgentBuilder::new()
+                .timeout(timeout)
+                .redirects(5)
+                .user_agent("pankat link checker")
+                .build(),
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> Result<u16, String> {
+        match self.agent.request(method, url).call() {
+            Ok(response) => Ok(response.status()),
+            Err(ureq::Error::Status(status, _)) => Ok(status),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+impl HttpClient for UreqClient {
+    fn status(&self, url: &str) -> Result<u16, String> {
+        match self.request("HEAD", url)? {
+            // not every server implements HEAD
+            405 | 501 => self.request("GET", url),
+            status => Ok(status),
+        }
+    }
+}
+
+/// The directories a link of a generated page may point to, see `check_links`
+pub struct Site {
+    pub output: PathBuf,
+    pub input: PathBuf,
+    pub assets: PathBuf,
+    pub wasm: PathBuf,
+    pub subdir: PathBuf,
+    /// paths like `draft` or `api/` served by the pankat server instead of a file
+    pub ignore: Vec<String>,
+}
+
+impl Site {
+    pub fn from_config() -> Self {
+        let cfg = config::Config::get();
+        Site {
+            output: cfg.output.clone(),
+            input: cfg.input.clone(),
+            assets: cfg.assets.clone(),
+            wasm: cfg.wasm.clone(),
+            subdir: cfg.subdir.clone(),
+            ignore: cfg.link_check.ignore.clone(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BrokenLink {
+    pub link: String,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub struct PageReport {
+    pub page: PathBuf,
+    pub broken: Vec<BrokenLink>,
+}
+
+#[derive(Debug, Default)]
+pub struct LinkReport {
+    pub pages: Vec<PageReport>,
+    pub checked: usize,
+}
+
+impl LinkReport {
+    pub fn broken_count(&self) -> usize {
+        self.pages.iter().map(|page| page.broken.len()).sum()
+    }
+
+    pub fn print(&self) {
+        for page in self.pages.iter().filter(|page| !page.broken.is_empty()) {
+            let message = format!(
+                "{}: {} broken link(s)",
+                page.page.display(),
+                page.broken.len()
+            );
+            println!("{}", message.red());
+            for broken in &page.broken {
+                println!("   '{}': {}", broken.link, broken.reason);
+            }
+        }
+        println!(
+            "Checked {} links on {} pages, {} broken",
+            self.checked,
+            self.pages.len(),
+            self.broken_count()
+        );
+    }
+}
+
+/// Caches the ids of the pages, so each one is only read once
+struct Anchors(HashMap<PathBuf, Option<HashSet<String>>>);
+
+impl Anchors {
+    fn contains(&mut self, page: &Path, fragment: &str) -> bool {
+        let ids = self.0.entry(page.to_path_buf()).or_insert_with(|| {
+            std::fs::read_to_string(page)
+                .ok()
+                .map(|html| extract_ids(&html))
+        });
+        let fragment = percent_encoding::percent_decode_str(fragment).decode_utf8_lossy();
+        ids.as_ref()
+            .is_some_and(|ids| ids.contains(fragment.as_ref()))
+    }
+}
+
+/// Where an internal link points to, `None` if there is no such file
+fn resolve_target(site: &Site, path: &str) -> Option<PathBuf> {
+    let path = if path.is_empty() { "index.html" } else { path };
+    let is_file = |root: &Path, path: &str| {
+        resolve_uri_path(root, path)
+            .ok()
+            .filter(|resolved| resolved.is_file())
+    };
+    // the output of `--production`/`--export` builds contains assets and wasm itself
+    is_file(&site.output, path)
+        .or_else(|| is_file(&site.input, path))
+        .or_else(|| {
+            let (root, rest) = match path.split_once('/')? {
+                ("assets", rest) => (&site.assets, rest),
+                ("wasm", rest) => (&site.wasm, rest),
+                _ => return None,
+            };
+            is_file(root, rest)
+        })
+}
+
+fn check_internal(
+    site: &Site,
+    page: &Path,
+    link: &str,
+    anchors: &mut Anchors,
+) -> Option<BrokenLink> {
+    let broken = |reason: &str| {
+        Some(BrokenLink {
+            link: link.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+
+    let Some(site_link) = resolve_site_link(page, &site.subdir, link) else {
+        return broken("points outside of the site");
+    };
+    if site
+        .ignore
+        .iter()
+        .any(|ignored| site_link.path.starts_with(ignored.as_str()))
+    {
+        return None;
+    }
+    let Some(target) = resolve_target(site, &site_link.path) else {
+        return broken("no such file");
+    };
+    match site_link.fragment.as_deref() {
+        Some(fragment)
+            if !fragment.is_empty()
+                && target.extension().is_some_and(|ext| ext == "html")
+                && !anchors.contains(&target, fragment) =>
+        {
+            broken("no such anchor")
+        }
+        _ => None,
+    }
+}
+
+/// Checks the internal links of a single `page` (relative to `site.output`) whose `html` might not
+/// be written to the output yet, used for the diagnostics of the live preview
+pub fn check_page(site: &Site, page: &Path, html: &str) -> Vec<BrokenLink> {
+    let mut anchors = Anchors(HashMap::new());
+    let ids = extract_ids(html);
+    let links: BTreeSet<String> = extract_links(html).into_iter().collect();
+    let mut broken = Vec::new();
+    for link in links.into_iter().filter(|link| !is_external(link)) {
+        // anchors of the page itself are looked up in `html`, not in the file on disk
+        if let Some(fragment) = link.strip_prefix('#') {
+            let fragment = percent_encoding::percent_decode_str(fragment).decode_utf8_lossy();
+            if !fragment.is_empty() && !ids.contains(fragment.as_ref()) {
+                broken.push(BrokenLink {
+                    link: link.clone(),
+                    reason: "no such anchor".to_string(),
+                });
+            }
+            continue;
+        }
+        broken.extend(check_internal(site, page, &link, &mut anchors));
+    }
+    broken
+}
+
+/// Checks external urls with `client`, results are cached in the database for `cache_hours`
+pub struct ExternalCheck<'a> {
+    pub client: &'a dyn HttpClient,
+    pub cache_hours: i64,
+}
+
+fn check_external(
+    conn: &mut SqliteConnection,
+    external: &ExternalCheck,
+    url: &str,
+) -> Option<String> {
+    let now = chrono::Local::now().naive_local();
+    let not_before = now - chrono::Duration::hours(external.cache_hours);
+    let result = match get_external_link(conn, url, not_before) {
+        Ok(Some(cached)) => cached,
+        _ => {
+            let (status, error) = match external.client.status(url) {
+                Ok(status) => (Some(status as i32), None),
+                Err(e) => (None, Some(e)),
+            };
+            let result = ExternalLink {
+                url: url.to_string(),
+                status,
+                error,
+                checked_at: now,
+            };
+            if let Err(e) = set_external_link(conn, &result) {
+                println!("Error caching link check of {}: {}", url, e);
+            }
+            result
+        }
+    };
+    match (result.status, result.error) {
+        (Some(status), _) if status < 400 => None,
+        (Some(status), _) => Some(format!("status {}", status)),
+        (None, error) => Some(error.unwrap_or_else(|| "no response".to_string())),
+    }
+}
+
+/// Checks every `href`/`src` of the generated `pages` (relative to `site.output`): internal links
+/// have to point to a file in the output, input, assets or wasm directory and `#fragment`s to an
+/// id of the page, external `http(s)://` urls are only requested with `external`
+pub fn check_site(
+    conn: &mut SqliteConnection,
+    site: &Site,
+    pages: &[PathBuf],
+    external: Option<&ExternalCheck>,
+) -> LinkReport {
+    let mut report = LinkReport::default();
+    let mut anchors = Anchors(HashMap::new());
+    let mut external_results: HashMap<String, Option<String>> = HashMap::new();
+
+    for page in pages {
+        let html = match std::fs::read_to_string(site.output.join(page)) {
+            Ok(html) => html,
+            Err(e) => {
+                println!("Error reading {}: {}", page.display(), e);
+                continue;
+            }
+        };
+        let mut broken = Vec::new();
+        // each link is reported once per page
+        let links: BTreeSet<String> = extract_links(&html).into_iter().collect();
+        for link in links {
+            report.checked += 1;
+            if !is_external(&link) {
+                broken.extend(check_internal(site, page, &link, &mut anchors));
+                continue;
+            }
+            let Some(external) = external.filter(|_| link.starts_with("http")) else {
+                continue;
+            };
+            let reason = external_results
+                .entry(link.clone())
+                .or_insert_with(|| check_external(conn, external, &link));
+            if let Some(reason) = reason {
+                broken.push(BrokenLink {
+                    link: link.clone(),
+                    reason: reason.clone(),
+                });
+            }
+        }
+        report.pages.push(PageReport {
+            page: page.clone(),
+            broken,
+        });
+    }
+    report
+}
+
+/// Checks the links of all pages in the output directory, see `[link_check]` in pankat.toml
+pub fn check_links(conn: &mut SqliteConnection) -> LinkReport {
+    let cfg = config::Config::get();
+    println!("====== Checking links in 'output' directory ======");
+
+    let mut pages = Vec::new();
+    if let Err(e) = crate::export::collect_pages(&cfg.output, &cfg.output, &mut pages) {
+        println!("Error collecting pages: {}", e);
+    }
+    pages.sort();
+
+    let site = Site::from_config();
+    let client = UreqClient::new(Duration::from_secs(cfg.link_check.external_timeout_seconds));
+    let external = ExternalCheck {
+        client: &client,
+        cache_hours: cfg.link_check.external_cache_hours,
+    };
+
+    check_site(
+        conn,
+        &site,
+        &pages,
+        cfg.link_check.external.then_some(&external),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::establish_connection_and_initialize_schema;
+    use crate::test_utils::create_temp_dir;
+    use std::cell::RefCell;
+    use std::fs;
+
+    struct StubClient {
+        requests: RefCell<Vec<String>>,
+    }
+
+    impl HttpClient for StubClient {
+        fn status(&self, url: &str) -> Result<u16, String> {
+            self.requests.borrow_mut().push(url.to_string());
+            match url {
+                "https://example.com/" => Ok(200),
+                "https://example.com/gone" => Ok(404),
+                _ => Err("connection refused".to_string()),
+            }
+        }
+    }
+
+    fn create_site(name: &str) -> Site {
+        let dir = create_temp_dir(name);
+        for sub in ["output", "input/media", "input/posts", "assets/css", "wasm"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        fs::write(dir.join("input/media/logo.gif"), "GIF89a").unwrap();
+        fs::write(dir.join("input/posts/foo.mdwn"), "# foo").unwrap();
+        fs::write(dir.join("assets/css/style.css"), "body {}").unwrap();
+        fs::write(
+            dir.join("output/bar.html"),
+            r#"<section id="second-heading"><h2 id="second-heading">second</h2></section>"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("output/foo.html"),
+            r##"<link href="/blog/assets/css/style.css"><link href="/blog/assets/css/gone.css">
+            <a href="/blog/draft">draft</a><a href="posts/foo.mdwn">source</a>
+            <img src="media/logo.gif"><img src="media/missing.gif">
+            <a href="bar.html#second-heading">ok</a><a href="bar.html#first-heading">no anchor</a>
+            <a href="#top">top</a><h1 id="top">foo</h1><a href="#">js</a>
+            <a href="https://example.com/">ok</a><a href="https://example.com/gone">gone</a>
+            <a href="https://unreachable.example/">down</a><a href="mailto:joe@example.com">mail</a>"##,
+        )
+        .unwrap();
+        Site {
+            output: dir.join("output"),
+            input: dir.join("input"),
+            assets: dir.join("assets"),
+            wasm: dir.join("wasm"),
+            subdir: PathBuf::from("/blog"),
+            ignore: vec!["draft".to_string(), "api/".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_check_site() {
+        let site = create_site("check-site");
+        let mut conn = establish_connection_and_initialize_schema();
+        let pages = vec![PathBuf::from("bar.html"), PathBuf::from("foo.html")];
+
+        let report = check_site(&mut conn, &site, &pages, None);
+        assert_eq!(report.pages.len(), 2);
+        assert!(report.pages[0].broken.is_empty());
+        let broken: Vec<&str> = report.pages[1]
+            .broken
+            .iter()
+            .map(|broken| broken.link.as_str())
+            .collect();
+        assert_eq!(
+            broken,
+            vec![
+                "/blog/assets/css/gone.css",
+                "bar.html#first-heading",
+                "media/missing.gif"
+            ]
+        );
+        assert_eq!(report.pages[1].broken[1].reason, "no such anchor");
+
+        let client = StubClient {
+            requests: RefCell::new(Vec::new()),
+        };
+        let external = ExternalCheck {
+            client: &client,
+            cache_hours: 24,
+        };
+        let report = check_site(&mut conn, &site, &pages, Some(&external));
+        assert_eq!(report.broken_count(), 5);
+        assert_eq!(client.requests.borrow().len(), 3);
+
+        // the second run is answered from the cache
+        let report = check_site(&mut conn, &site, &pages, Some(&external));
+        assert_eq!(report.broken_count(), 5);
+        assert_eq!(client.requests.borrow().len(), 3);
+
+        fs::remove_dir_all(site.output.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_check_page() {
+        let site = create_site("check-page");
+        // not written to the output yet
+        let html = r##"<h1 id="intro">baz</h1><a href="#intro">ok</a><a href="#outro">no anchor</a>
+            <img src="media/logo.gif"><img src="media/missing.gif"><a href="missing.html">gone</a>
+            <a href="bar.html#second-heading">ok</a><a href="https://example.com/gone">external</a>"##;
+
+        let broken = check_page(&site, Path::new("baz.html"), html);
+        let broken: Vec<(&str, &str)> = broken
+            .iter()
+            .map(|broken| (broken.link.as_str(), broken.reason.as_str()))
+            .collect();
+        assert_eq!(
+            broken,
+            vec![
+                ("#outro", "no such anchor"),
+                ("media/missing.gif", "no such file"),
+                ("missing.html", "no such file")
+            ]
+        );
+
+        fs::remove_dir_all(site.output.parent().unwrap()).unwrap();
+    }
+}
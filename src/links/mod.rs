@@ -0,0 +1,168 @@
+pub mod check;
+
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Links in comments (like the `<!--[if lt IE 9]>` ones) aren't followed by browsers
+fn strip_comments(html: &str) -> String {
+    let re = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    re.replace_all(html, "").to_string()
+}
+
+/// All `href` and `src` attribute values of `html`, except the ones in comments
+pub fn extract_links(html: &str) -> Vec<String> {
+    let html = strip_comments(html);
+    // also matches the unquoted attributes of minified html
+    let re = Regex::new(r#"(?i)\s(?:href|src)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+    re.captures_iter(&html)
+        .filter_map(|captures| captures.iter().skip(1).flatten().next())
+        .map(|value| value.as_str().replace("&amp;", "&"))
+        .collect()
+}
+
+/// All `id` (and legacy `<a name>`) attribute values of `html`, the targets of `#fragment` links
+/// like the heading anchors of `create-anchors.lua`
+pub fn extract_ids(html: &str) -> HashSet<String> {
+    let re = Regex::new(r#"(?i)\s(?:id|name)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+    re.captures_iter(html)
+        .filter_map(|captures| captures.iter().skip(1).flatten().next())
+        .map(|value| value.as_str().to_string())
+        .collect()
+}
+
+/// A link pointing into the site, `path` is relative to the root of the output (and input) tree
+#[derive(Debug, PartialEq)]
+pub struct SiteLink {
+    pub path: String,
+    pub fragment: Option<String>,
+}
+
+/// `mailto:`, `https://` and the like
+pub fn is_external(link: &str) -> bool {
+    link.starts_with("//")
+        || link
+            .split_once(':')
+            .is_some_and(|(scheme, _)| !scheme.is_empty() && !scheme.contains(['/', '?', '#']))
+}
+
+/// Resolves `link` found on the page `page` (relative to the output directory, like `foo.html` or
+/// `posts/foo.html`) against `subdir`, `None` for external links and absolute links outside of
+/// `subdir`
+///
+/// A link to the page itself (`#foo`) has the page as `path`, a link to the site root an empty one.
+/// `..` segments are resolved, but may not leave the site.
+pub fn resolve_site_link(page: &Path, subdir: &Path, link: &str) -> Option<SiteLink> {
+    if is_external(link) {
+        return None;
+    }
+    let (link, fragment) = match link.split_once('#') {
+        Some((link, fragment)) => (link, Some(fragment.to_string())),
+        None => (link, None),
+    };
+    let link = link.split('?').next().unwrap_or_default();
+    if link.is_empty() {
+        return Some(SiteLink {
+            path: page.to_str()?.to_string(),
+            fragment,
+        });
+    }
+
+    let subdir = subdir.display().to_string();
+    let mut segments: Vec<&str> = Vec::new();
+    let relative = match link.strip_prefix('/') {
+        Some(_) => {
+            let subdir = subdir.trim_end_matches('/');
+            let rest = link.strip_prefix(subdir)?;
+            if !rest.is_empty() && !rest.starts_with('/') {
+                return None;
+            }
+            rest
+        }
+        None => {
+            for component in page.parent().into_iter().flat_map(Path::components) {
+                segments.push(component.as_os_str().to_str()?);
+            }
+            link
+        }
+    };
+    for segment in relative.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    Some(SiteLink {
+        path: segments.join("/"),
+        fragment,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let html = r#"<a href="foo.html#bar">x</a><img src='media/a.png'><link href=/blog/assets/css/style.css rel=stylesheet><a title="href=nope" href="b.html?x=1&amp;y=2"><!--[if lt IE 9]><script src="ie.js"></script><![endif]-->"#;
+        assert_eq!(
+            extract_links(html),
+            vec![
+                "foo.html#bar",
+                "media/a.png",
+                "/blog/assets/css/style.css",
+                "b.html?x=1&y=2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_ids() {
+        let html = r##"<section id="intro" class="level1"><h1 id=intro-1>Intro<a href="#intro"></a></h1><a name='legacy'></a>"##;
+        let ids = extract_ids(html);
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains("intro") && ids.contains("intro-1") && ids.contains("legacy"));
+    }
+
+    #[test]
+    fn test_resolve_site_link() {
+        let subdir = Path::new("/blog");
+        let page = Path::new("foo.html");
+        let resolve = |page: &Path, link: &str| resolve_site_link(page, subdir, link);
+        let site_link = |path: &str, fragment: Option<&str>| {
+            Some(SiteLink {
+                path: path.to_string(),
+                fragment: fragment.map(str::to_string),
+            })
+        };
+
+        assert_eq!(resolve(page, "media/a.png"), site_link("media/a.png", None));
+        assert_eq!(
+            resolve(page, "/blog/bar.html#intro"),
+            site_link("bar.html", Some("intro"))
+        );
+        assert_eq!(
+            resolve(page, "#intro"),
+            site_link("foo.html", Some("intro"))
+        );
+        assert_eq!(
+            resolve(page, "./media/a.png?v=1"),
+            site_link("media/a.png", None)
+        );
+        assert_eq!(
+            resolve(Path::new("posts/foo.html"), "../media/a.png"),
+            site_link("media/a.png", None)
+        );
+        assert_eq!(resolve(page, "/blog"), site_link("", None));
+        assert_eq!(resolve(page, "../etc/passwd"), None);
+        assert_eq!(resolve(page, "/blogger/foo.html"), None);
+        assert_eq!(resolve(page, "/other/foo.html"), None);
+        assert_eq!(resolve(page, "https://example.com/"), None);
+        assert_eq!(resolve(page, "//example.com/foo.js"), None);
+        assert_eq!(resolve(page, "mailto:joe@example.com"), None);
+    }
+}
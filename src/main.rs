@@ -1,15 +1,27 @@
 mod articles;
 mod auth;
+mod compression;
 mod config;
 mod db;
 mod error;
+mod export;
 mod file_monitor;
+mod file_response;
 mod handlers;
+mod images;
+mod links;
+mod rate_limit;
 mod registry;
 mod renderer;
+mod safe_path;
+#[cfg(test)]
+mod test_utils;
+#[cfg(test)]
+mod tests;
 use crate::config::*;
 use crate::renderer::pandoc::check_pandoc;
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -116,6 +128,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 .required(false)
                 .action(ArgAction::SetTrue)
         )
+        .arg(
+            Arg::new("production")
+                .long("production")
+                .help("Static build with fingerprinted assets copied to the output and minified html")
+                .required(false)
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("export")
+                .long("export")
+                .help("Static build into a self-contained output directory with assets, wasm and referenced media")
+                .required(false)
+                .action(ArgAction::SetTrue)
+        )
         .arg(
             Arg::new("port")
                 .short('p')
@@ -124,8 +150,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 .help("Port number where pankat listens for incoming connections for browser connections")
                 .default_value("5000"),
         )
+        .subcommand(
+            Command::new("new")
+                .about("Create a new article in <input>/posts and exit")
+                .arg(
+                    Arg::new("title")
+                        .value_name("TITLE")
+                        .help("Title of the article, also used for the file name")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("tags")
+                        .long("tags")
+                        .value_name("TAGS")
+                        .help("Comma separated list of tags")
+                        .value_delimiter(','),
+                )
+                .arg(
+                    Arg::new("series")
+                        .long("series")
+                        .value_name("SERIES")
+                        .help("Series the article belongs to"),
+                )
+                .arg(
+                    Arg::new("draft")
+                        .long("draft")
+                        .help("Mark the article as draft")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("plugins").about("List the [[!...]] directives with their help and exit"),
+        )
         .get_matches();
 
+    // needs neither a configuration nor the input directory
+    if let Some(("plugins", _)) = matches.subcommand() {
+        let registry = articles::plugins::registry::PluginRegistry::instance();
+        if let Some(assets) = matches.get_one::<String>("assets") {
+            registry.register_scripts(&std::path::Path::new(assets).join("plugins"));
+        }
+        print!("{}", registry.help());
+        return Ok(());
+    }
+
     let mut config_values: HashMap<String, ConfigValue> = HashMap::new();
 
     config_values.insert(
@@ -253,7 +321,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     );
 
     config_values.insert(
-        "static".to_string(),
+        "static_build_only".to_string(),
         ConfigValue {
             value: ConfigValueType::Bool(matches.get_one::<bool>("static").copied()),
             is_default: Some(clap::parser::ValueSource::DefaultValue)
@@ -270,6 +338,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         },
     );
 
+    config_values.insert(
+        "production".to_string(),
+        ConfigValue {
+            value: ConfigValueType::Bool(matches.get_one::<bool>("production").copied()),
+            is_default: Some(clap::parser::ValueSource::DefaultValue)
+                == matches.value_source("production"),
+        },
+    );
+
+    config_values.insert(
+        "export".to_string(),
+        ConfigValue {
+            value: ConfigValueType::Bool(matches.get_one::<bool>("export").copied()),
+            is_default: Some(clap::parser::ValueSource::DefaultValue)
+                == matches.value_source("export"),
+        },
+    );
+
     let config = config::Config::new(config_values);
 
     config::Config::initialize(config).expect("Failed to initialize config");
@@ -291,22 +377,72 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     );
     println!("Admin password: {}", "*".repeat(cfg.admin_password.len()));
     println!("Flat filename structure: {}", cfg.flat);
+    println!("Production build: {}", cfg.production);
+    println!("Export: {}", cfg.export);
     println!("-------------------------------------------------");
 
+    if let Some(("new", new_matches)) = matches.subcommand() {
+        let scaffold = articles::scaffold::ArticleScaffold {
+            title: new_matches.get_one::<String>("title").unwrap().clone(),
+            tags: new_matches
+                .get_many::<String>("tags")
+                .map(|tags| tags.cloned().collect())
+                .unwrap_or_default(),
+            series: new_matches.get_one::<String>("series").cloned(),
+            draft: new_matches.get_flag("draft"),
+        };
+        scaffold.create()?;
+        return Ok(());
+    }
+
     check_pandoc()?;
 
+    articles::plugins::registry::PluginRegistry::instance()
+        .register_scripts(&cfg.assets.join("plugins"));
+
     // Initialize SQLite database with Diesel
     let pool = db::establish_connection_pool();
 
     articles::collect_garbage(&pool);
+    if cfg.production || cfg.export {
+        renderer::assets::copy_assets_to_output()?;
+    }
+    if cfg.images.strip_exif {
+        if let Err(e) = images::strip_input_images(&cfg.input, &cfg.output) {
+            println!("Error: {}", e);
+        }
+    }
     articles::scan_articles(&pool);
     articles::build_articles(&pool);
+    if cfg.export {
+        export::export_site()?;
+    }
 
-    if cfg.static_build_only {
+    let static_build = cfg.static_build_only || cfg.production || cfg.export;
+    if cfg.link_check.enabled {
+        let mut conn = pool.get()?;
+        let report = links::check::check_links(&mut conn);
+        report.print();
+        if static_build && cfg.link_check.fail_static_build && report.broken_count() > 0 {
+            return Err(format!("Static build has {} broken links", report.broken_count()).into());
+        }
+    }
+
+    if static_build {
         println!("Static build only, exiting...");
         return Ok(());
     }
 
+    match auth::ensure_admin(&mut *pool.get()?, &cfg.admin_password) {
+        Ok(true) => println!("Set the password of the '{}' account", auth::ADMIN_USERNAME),
+        Ok(false) => {}
+        Err(e) => println!(
+            "Error setting up the '{}' account: {}",
+            auth::ADMIN_USERNAME,
+            e
+        ),
+    }
+
     // Setup broadcast channel for shutdown coordination
     let (shutdown_tx, _) = broadcast::channel::<()>(1);
 
@@ -325,22 +461,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let subdir = binding.as_str();
 
     // Create router
-    let app = Router::new().nest(
-        subdir,
-        Router::new()
-            .route("/posts/*path", get(handlers::serve_input))
-            .route("/media/*path", get(handlers::serve_input))
-            .route("/assets/*path", get(handlers::serve_internals))
-            .route("/wasm/*path", get(handlers::serve_internals))
-            .route("/api/auth/register", post(handlers::register))
-            .route("/api/auth/login", post(handlers::login))
-            .route("/api/protected", get(handlers::protected))
-            .route("/api/ws", get(handlers::websocket_route))
-            .route("/", get(handlers::serve_output))
-            .route("/*path", get(handlers::serve_output))
-            .layer(CorsLayer::permissive())
-            .with_state(pool.clone()),
-    );
+    let app = Router::new().nest(subdir, routes(pool.clone()));
 
     // Start server
     let address_config = format!("[::]:{}", cfg.port);
@@ -372,7 +493,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let s = "Press Ctrl+C to stop the server...".yellow();
     println!("{s}");
     tokio::select! {
-        result = axum::serve(listener, app) => {
+        result = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        ) => {
             if let Err(e) = result {
                 eprintln!("Server error: {}", e);
                 // Initiate cleanup before returning error
@@ -400,3 +524,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     Ok(())
 }
+
+/// The routes below `subdir`
+fn routes(pool: db::DbPool) -> Router {
+    Router::new()
+        .route("/posts/*path", get(handlers::serve_input))
+        .route("/media/*path", get(handlers::serve_input))
+        .route("/assets/*path", get(handlers::serve_internals))
+        .route("/wasm/*path", get(handlers::serve_internals))
+        .merge(
+            Router::new()
+                .route("/api/auth/register", post(handlers::register))
+                .route("/api/auth/login", post(handlers::login))
+                .route_layer(middleware::from_fn(rate_limit::limit_login_attempts)),
+        )
+        .route("/api/auth/refresh", post(handlers::refresh))
+        .route("/api/auth/logout", post(handlers::logout))
+        .route("/api/auth/revoke", post(handlers::revoke))
+        .route("/api/protected", get(handlers::protected))
+        .route(
+            "/api/articles",
+            get(handlers::list_article_sources).post(handlers::create_article),
+        )
+        .route(
+            "/api/articles/*path",
+            get(handlers::get_article_path)
+                .put(handlers::put_article_source)
+                .post(handlers::post_article_path),
+        )
+        .route("/api/ws", get(handlers::websocket_route))
+        .route("/", get(handlers::serve_output))
+        .route("/*path", get(handlers::serve_output))
+        .layer(CorsLayer::permissive())
+        .with_state(pool)
+}
@@ -0,0 +1,303 @@
+use crate::config::{self, RateLimitConfig};
+use crate::error::AppError;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Login/register requests are tiny, anything bigger is rejected before it is parsed
+pub const MAX_BODY_SIZE: usize = 16 * 1024;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct Attempts {
+    window_start: Instant,
+    requests: u32,
+    failures: u32,
+    last_failure: Option<Instant>,
+    blocked_until: Option<Instant>,
+}
+
+impl Attempts {
+    fn new(now: Instant) -> Self {
+        Attempts {
+            window_start: now,
+            requests: 0,
+            failures: 0,
+            last_failure: None,
+            blocked_until: None,
+        }
+    }
+
+    /// Failures are forgotten once no block is pending and the last one is `lockout_seconds` ago,
+    /// so failed logins with random usernames don't pile up
+    fn is_stale(&self, cfg: &RateLimitConfig, now: Instant) -> bool {
+        now.duration_since(self.window_start) >= WINDOW
+            && self.blocked_until.is_none_or(|until| until <= now)
+            && self
+                .last_failure
+                .is_none_or(|at| now.duration_since(at) >= Duration::from_secs(cfg.lockout_seconds))
+    }
+}
+
+/// Tracks requests and failed logins per key, a key is either `ip:<addr>` or `user:<name>`
+#[derive(Debug)]
+pub struct LoginLimiter {
+    entries: Mutex<HashMap<String, Attempts>>,
+}
+
+impl LoginLimiter {
+    /// Get the singleton instance of `LoginLimiter`
+    pub fn instance() -> &'static LoginLimiter {
+        static INSTANCE: OnceLock<LoginLimiter> = OnceLock::new();
+        INSTANCE.get_or_init(LoginLimiter::new)
+    }
+
+    fn new() -> Self {
+        LoginLimiter {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts the request for all keys, returns the seconds to wait if one of them is throttled
+    fn check(&self, cfg: &RateLimitConfig, keys: &[String], now: Instant) -> Result<(), u64> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() > 1000 {
+            entries.retain(|_, attempts| !attempts.is_stale(cfg, now));
+        }
+
+        let mut retry_after: Option<Duration> = None;
+        for key in keys {
+            let attempts = entries
+                .entry(key.clone())
+                .or_insert_with(|| Attempts::new(now));
+
+            if let Some(until) = attempts.blocked_until {
+                if until > now {
+                    retry_after = retry_after.max(Some(until - now));
+                    continue;
+                }
+            }
+
+            if now.duration_since(attempts.window_start) >= WINDOW {
+                attempts.window_start = now;
+                attempts.requests = 0;
+            }
+            attempts.requests += 1;
+            if attempts.requests > cfg.requests_per_minute {
+                let wait = WINDOW - now.duration_since(attempts.window_start);
+                retry_after = retry_after.max(Some(wait));
+            }
+        }
+
+        match retry_after {
+            // round up, a client retrying too early would be throttled again
+            Some(wait) => Err(wait.as_secs() + u64::from(wait.subsec_nanos() > 0)),
+            None => Ok(()),
+        }
+    }
+
+    /// Blocks the keys with an exponential backoff, locks them once `lockout_threshold` is reached
+    fn record_failure(&self, cfg: &RateLimitConfig, keys: &[String], now: Instant) {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys {
+            let attempts = entries
+                .entry(key.clone())
+                .or_insert_with(|| Attempts::new(now));
+            attempts.failures += 1;
+            attempts.last_failure = Some(now);
+
+            let delay = if attempts.failures >= cfg.lockout_threshold {
+                if attempts.failures == cfg.lockout_threshold {
+                    println!(
+                        "Locking out '{}' for {} seconds after {} failed login attempts",
+                        key, cfg.lockout_seconds, attempts.failures
+                    );
+                }
+                cfg.lockout_seconds
+            } else {
+                let exponent = (attempts.failures - 1).min(31);
+                cfg.backoff_seconds
+                    .saturating_mul(1 << exponent)
+                    .min(cfg.backoff_max_seconds)
+            };
+            attempts.blocked_until = Some(now + Duration::from_secs(delay));
+        }
+    }
+
+    fn record_success(&self, keys: &[String]) {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys {
+            if let Some(attempts) = entries.get_mut(key) {
+                attempts.failures = 0;
+                attempts.blocked_until = None;
+            }
+        }
+    }
+}
+
+fn client_ip(request: &Request, trust_forwarded_for: bool) -> String {
+    if trust_forwarded_for {
+        let forwarded = request
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(|ip| ip.trim().to_string());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Middleware for the login and register routes: throttles per client ip and per username
+/// before the handler spends time on bcrypt, and records the outcome afterwards
+pub async fn limit_login_attempts(request: Request, next: Next) -> Result<Response, AppError> {
+    let cfg = config::Config::get();
+    let limiter = LoginLimiter::instance();
+
+    let mut keys: Vec<String> = vec![format!(
+        "ip:{}",
+        client_ip(&request, cfg.rate_limit.trust_forwarded_for)
+    )];
+
+    let (parts, body) = request.into_parts();
+    let bytes = axum::body::to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|_| AppError::PayloadTooLarge)?;
+    let username = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|json| {
+            json.get("username")
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string())
+        });
+    if let Some(username) = username {
+        keys.push(format!("user:{}", username));
+    }
+
+    if let Err(retry_after) = limiter.check(&cfg.rate_limit, &keys, Instant::now()) {
+        println!("Throttling login/register request for {:?}", keys);
+        return Err(AppError::RateLimited(retry_after));
+    }
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+
+    if response.status() == StatusCode::UNAUTHORIZED {
+        limiter.record_failure(&cfg.rate_limit, &keys, Instant::now());
+    } else if response.status().is_success() {
+        limiter.record_success(&keys);
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<String> {
+        vec!["ip:127.0.0.1".to_string(), "user:joachim".to_string()]
+    }
+
+    #[test]
+    fn test_requests_per_minute() {
+        let cfg = RateLimitConfig {
+            requests_per_minute: 3,
+            ..RateLimitConfig::default()
+        };
+        let limiter = LoginLimiter::new();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check(&cfg, &keys(), now).is_ok());
+        }
+        assert_eq!(limiter.check(&cfg, &keys(), now), Err(60));
+        assert_eq!(
+            limiter.check(&cfg, &keys(), now + Duration::from_secs(30)),
+            Err(30)
+        );
+        assert!(limiter.check(&cfg, &keys(), now + WINDOW).is_ok());
+    }
+
+    #[test]
+    fn test_exponential_backoff_and_lockout() {
+        let cfg = RateLimitConfig {
+            requests_per_minute: 1000,
+            backoff_seconds: 1,
+            backoff_max_seconds: 4,
+            lockout_threshold: 5,
+            lockout_seconds: 900,
+            trust_forwarded_for: false,
+        };
+        let limiter = LoginLimiter::new();
+        let mut now = Instant::now();
+
+        for expected_delay in [1, 2, 4, 4] {
+            assert!(limiter.check(&cfg, &keys(), now).is_ok());
+            limiter.record_failure(&cfg, &keys(), now);
+            assert!(limiter.check(&cfg, &keys(), now).is_err());
+            now += Duration::from_secs(expected_delay);
+        }
+
+        // 5th failure locks the account
+        assert!(limiter.check(&cfg, &keys(), now).is_ok());
+        limiter.record_failure(&cfg, &keys(), now);
+        assert_eq!(
+            limiter.check(&cfg, &keys(), now + Duration::from_secs(899)),
+            Err(1)
+        );
+
+        // the lockout also applies to other clients using the same username
+        let other_ip = vec!["ip:10.0.0.1".to_string(), "user:joachim".to_string()];
+        assert!(limiter
+            .check(&cfg, &other_ip, now + Duration::from_secs(10))
+            .is_err());
+
+        now += Duration::from_secs(900);
+        assert!(limiter.check(&cfg, &keys(), now).is_ok());
+        limiter.record_success(&keys());
+        limiter.record_failure(&cfg, &keys(), now);
+        assert_eq!(limiter.check(&cfg, &keys(), now), Err(1));
+    }
+
+    #[test]
+    fn test_stale_entries() {
+        let cfg = RateLimitConfig {
+            requests_per_minute: 1000,
+            ..RateLimitConfig::default()
+        };
+        let limiter = LoginLimiter::new();
+        let now = Instant::now();
+
+        for i in 0..1001 {
+            let keys = vec![format!("user:random{}", i)];
+            assert!(limiter.check(&cfg, &keys, now).is_ok());
+            limiter.record_failure(&cfg, &keys, now);
+        }
+        assert_eq!(limiter.entries.lock().unwrap().len(), 1001);
+
+        // still blocked or failed recently
+        let later = now + Duration::from_secs(cfg.lockout_seconds - 1);
+        assert!(limiter.check(&cfg, &keys(), later).is_ok());
+        assert_eq!(limiter.entries.lock().unwrap().len(), 1003);
+
+        let later = now + Duration::from_secs(cfg.lockout_seconds);
+        assert!(limiter.check(&cfg, &keys(), later).is_ok());
+        assert_eq!(limiter.entries.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::config;
+use crate::db::cache::compute_hash;
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderErrorReason,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Maps asset paths like `css/style.css` to their fingerprinted name like `css/style.1a2b3c4d.css`
+static MANIFEST: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Only used while rendering, not served to the browser
+const SKIPPED: [&str; 4] = ["templates", "pandoc-lua", "plugins", "editor.html"];
+
+/// Stylesheets and scripts are referenced from the templates and get fingerprinted, everything else
+/// (fonts, images) is referenced relatively from within them and keeps its name
+fn is_fingerprinted(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "css" || ext == "js")
+}
+
+pub fn fingerprinted_name(path: &Path, content: &[u8]) -> PathBuf {
+    let hash = compute_hash(String::from_utf8_lossy(content).to_string());
+    let hash: String = hash.chars().take(8).collect();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, hash, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, hash),
+    };
+    path.with_file_name(name)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Production and export builds copy the assets into `output/assets`, production builds name
+/// stylesheets and scripts by their content hash, so browsers never keep a stale theme after a deploy
+pub fn copy_assets_to_output() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let cfg = config::Config::get();
+    let output_assets = cfg.output.join("assets");
+
+    println!("====== Copying assets to 'output' directory ======");
+    // only pankat writes here, so old fingerprints are removed instead of collected
+    if output_assets.exists() {
+        std::fs::remove_dir_all(&output_assets)?;
+    }
+
+    let mut files = Vec::new();
+    collect_files(&cfg.assets, &mut files)?;
+
+    let mut manifest = HashMap::new();
+    for file in files {
+        let relative = file.strip_prefix(&cfg.assets)?.to_path_buf();
+        if relative
+            .components()
+            .next()
+            .is_some_and(|first| SKIPPED.contains(&first.as_os_str().to_string_lossy().as_ref()))
+        {
+            continue;
+        }
+        let content = std::fs::read(&file)?;
+        let target = if cfg.production && is_fingerprinted(&relative) {
+            fingerprinted_name(&relative, &content)
+        } else {
+            relative.clone()
+        };
+
+        let target_path = output_assets.join(&target);
+        if let Some(parent) = target_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target_path, &content)?;
+        crate::compression::write_siblings(&target_path, &content, cfg.compression.precompress);
+
+        manifest.insert(
+            relative.to_string_lossy().replace('\\', "/"),
+            target.to_string_lossy().replace('\\', "/"),
+        );
+    }
+
+    println!("Copied {} assets", manifest.len());
+    MANIFEST
+        .set(manifest)
+        .map_err(|_| "Assets can only be copied once".into())
+}
+
+/// `{{Subdir}}/assets/<path>`, with the fingerprinted file name in production builds
+pub fn asset_url(path: &str) -> String {
+    let cfg = config::Config::get();
+    let path = path.trim_start_matches('/');
+    let path = MANIFEST
+        .get()
+        .filter(|_| cfg.production)
+        .and_then(|manifest| manifest.get(path))
+        .map(String::as_str)
+        .unwrap_or(path);
+    format!("{}/assets/{}", cfg.subdir.display(), path)
+}
+
+/// `{{asset "css/style.css"}}`
+fn asset_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let path = h
+        .param(0)
+        .and_then(|param| param.value().as_str())
+        .ok_or(RenderErrorReason::ParamNotFoundForIndex("asset", 0))?;
+    out.write(&asset_url(path))?;
+    Ok(())
+}
+
+/// Every template is rendered with the helpers from here
+pub fn create_handlebars() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("asset", Box::new(asset_helper));
+    handlebars
+}
+
+#[test]
+fn test_fingerprinted_name() {
+    let name = fingerprinted_name(Path::new("css/style.css"), b"body {}");
+    let name = name.to_string_lossy();
+    assert!(name.starts_with("css/style."));
+    assert!(name.ends_with(".css"));
+    assert_eq!(name.len(), "css/style..css".len() + 8);
+
+    assert_ne!(
+        fingerprinted_name(Path::new("css/style.css"), b"body {}"),
+        fingerprinted_name(Path::new("css/style.css"), b"body { color: red }")
+    );
+}
@@ -0,0 +1,51 @@
+use serde_json::json;
+use similar::{DiffTag, TextDiff};
+
+/// Line based side-by-side diff, one row per line pair as rendered by `revision-diff-template.html`
+///
+/// Each row has `OldLine`/`Old` and `NewLine`/`New` (line numbers start at 1, missing on the side
+/// without a line) and a `Kind` of `equal`, `delete`, `insert` or `replace`.
+pub fn side_by_side_rows(old: &str, new: &str) -> Vec<serde_json::Value> {
+    let diff = TextDiff::from_lines(old, new);
+    let old_lines: Vec<&str> = diff.old_slices().to_vec();
+    let new_lines: Vec<&str> = diff.new_slices().to_vec();
+
+    let mut rows = Vec::new();
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let kind = match tag {
+            DiffTag::Equal => "equal",
+            DiffTag::Delete => "delete",
+            DiffTag::Insert => "insert",
+            DiffTag::Replace => "replace",
+        };
+        for i in 0..old_range.len().max(new_range.len()) {
+            let old_index = (i < old_range.len()).then(|| old_range.start + i);
+            let new_index = (i < new_range.len()).then(|| new_range.start + i);
+            rows.push(json!({
+                "Kind": kind,
+                "OldLine": old_index.map(|index| index + 1),
+                "Old": old_index.map(|index| old_lines[index].trim_end_matches('\n')),
+                "NewLine": new_index.map(|index| index + 1),
+                "New": new_index.map(|index| new_lines[index].trim_end_matches('\n')),
+            }));
+        }
+    }
+    rows
+}
+
+#[test]
+fn test_side_by_side_rows() {
+    let rows = side_by_side_rows("a\nb\nc\n", "a\nB\nc\nd\n");
+
+    let kinds: Vec<&str> = rows
+        .iter()
+        .map(|row| row["Kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, vec!["equal", "replace", "equal", "insert"]);
+    assert_eq!(rows[1]["Old"], "b");
+    assert_eq!(rows[1]["New"], "B");
+    assert_eq!(rows[3]["OldLine"], serde_json::Value::Null);
+    assert_eq!(rows[3]["NewLine"], 4);
+    assert_eq!(rows[3]["New"], "d");
+}
@@ -2,28 +2,41 @@ use crate::articles::timeline::tag_links_to_timeline;
 use crate::articles::ArticleWithTags;
 use crate::config;
 use crate::db::article::ArticleNeighbours;
+use crate::db::git::GitCommit;
+use crate::renderer::assets::create_handlebars;
 use crate::renderer::utils::date_and_time;
-use handlebars::Handlebars;
 use serde_json::json;
 use std::error::Error;
 use std::fs;
-use std::path::{PathBuf, MAIN_SEPARATOR};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
 
 pub fn create_html_from_standalone_template_by_article(
     article: ArticleWithTags,
     html: String,
 ) -> Result<String, Box<dyn Error>> {
     let cfg = config::Config::get();
+    // there is no server to receive updates from
+    let live_updates = article.live_updates == Some(true) && !cfg.static_build_only;
 
     let data: serde_json::Value = json!({
         "SiteBrandTitle": cfg.brand,
         "Subdir": cfg.subdir,
         "Title": article.title,
         "NavAndContent": html,
-        "LiveUpdates": article.live_updates,
+        "LiveUpdates": live_updates,
         "SpecialPage": article.special_page,
         "Tocify": article.tocify,
         "Timeline": false,
+        // colorbox for [[!gallery]], live updates might add one later
+        "Gallery": html.contains(r#"class="pankatGallery""#) || live_updates,
+        // click-to-load of [[!youtube]] and [[!asciinema]]
+        "Embeds": html.contains(r#"class="pankatEmbed "#) || live_updates,
+        // KaTeX for the formulas pandoc left to the browser, see [[!math]]
+        "Katex": html.contains(r#"class="math "#)
+            && crate::articles::plugins::math::math_mode(&html, &cfg.math).as_deref()
+                == Some("katex")
+            || live_updates && cfg.math.mode == "katex",
+        "KatexUrl": cfg.math.katex_url,
         "ArticleSrcFileName": article.src_file_name,
         "ArticleDstFileName": article.dst_file_name,
     });
@@ -36,7 +49,7 @@ pub fn create_html_from_standalone_template(
 ) -> Result<String, Box<dyn Error>> {
     let cfg = config::Config::get();
 
-    let mut handlebars = Handlebars::new();
+    let mut handlebars = create_handlebars();
 
     let mut assets: PathBuf = PathBuf::from(cfg.assets.clone());
     assets.push("templates/standalone-template.html");
@@ -49,11 +62,93 @@ pub fn create_html_from_standalone_template(
     Ok(result)
 }
 
+/// Renders the partial `templates/partials/<name>.html` of `assets`, the themable html of
+/// directives like `[[!note]]`
+///
+/// The lines of the partial are unindented, as its html ends up in the markdown and pandoc would
+/// take indented lines for code blocks.
+pub fn render_partial(
+    assets: &Path,
+    name: &str,
+    data: &serde_json::Value,
+) -> Result<String, Box<dyn Error>> {
+    let path = assets
+        .join("templates/partials")
+        .join(format!("{}.html", name));
+    let template_content: String = fs::read_to_string(&path)
+        .map_err(|e| format!("Reading partial {}: {}", path.display(), e))?
+        .lines()
+        .map(str::trim_start)
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    let handlebars = create_handlebars();
+    let result = handlebars.render_template(&template_content, data)?;
+    Ok(result)
+}
+
+/// Renders `templates/404.html` into the standalone template, `path` is the requested URI path
+pub fn create_html_from_not_found_template(path: &str) -> Result<String, Box<dyn Error>> {
+    let cfg = config::Config::get();
+
+    let mut handlebars = create_handlebars();
+
+    let mut assets: PathBuf = cfg.assets.clone();
+    assets.push("templates/404.html");
+    let template_content = fs::read_to_string(assets.as_path())?;
+
+    handlebars.register_template_string("not_found_template", &template_content)?;
+
+    let html = handlebars.render(
+        "not_found_template",
+        &json!({
+            "Path": path,
+            "Subdir": cfg.subdir,
+        }),
+    )?;
+
+    let data: serde_json::Value = json!({
+        "SiteBrandTitle": cfg.brand,
+        "Title": "page not found",
+        "NavAndContent": html,
+        "ArticleSrcURL": "",
+        "ArticleSrcFileName": "",
+        "ArticleDstFileName": "",
+        "LiveUpdates": false,
+        "SpecialPage": true,
+        "Anchorjs": false,
+        "Tocify": false,
+        "Timeline": false,
+        "Subdir": cfg.subdir,
+    });
+
+    create_html_from_standalone_template(data)
+}
+
+/// Renders the side-by-side diff page of an article revision, see `handlers::revision_diff`
+pub fn create_html_from_revision_diff_template(
+    data: serde_json::Value,
+) -> Result<String, Box<dyn Error>> {
+    let cfg = config::Config::get();
+
+    let mut handlebars = create_handlebars();
+
+    let mut assets: PathBuf = cfg.assets.clone();
+    assets.push("templates/revision-diff-template.html");
+    let template_content = fs::read_to_string(assets.as_path())?;
+
+    handlebars.register_template_string("revision_diff_template", &template_content)?;
+
+    let result = handlebars.render("revision_diff_template", &data)?;
+    Ok(result)
+}
+
 pub fn create_html_from_content_template(
     article: ArticleWithTags,
     html: String,
     article_neighbours: ArticleNeighbours,
     article_series_neighbours: ArticleNeighbours,
+    history: Vec<GitCommit>,
 ) -> Result<String, Box<dyn Error>> {
     let cfg = config::Config::get();
 
@@ -82,7 +177,7 @@ pub fn create_html_from_content_template(
     //     article_series_neighbours
     // );
 
-    let mut handlebars = Handlebars::new();
+    let mut handlebars = create_handlebars();
 
     let mut assets: PathBuf = PathBuf::from(cfg.assets.clone());
     assets.push("templates/content-template.html");
@@ -117,6 +212,24 @@ pub fn create_html_from_content_template(
         None => "".to_string(),
     };
 
+    // only worth mentioning if the article changed after the date it is listed with
+    let mut last_updated: String = date_and_time(&history.first().map(|commit| commit.date));
+    if last_updated == date_and_time(&article.modification_date) {
+        last_updated = String::new();
+    }
+    let history: Vec<serde_json::Value> = history
+        .iter()
+        .map(|commit| {
+            json!({
+                "Commit": commit.commit_id,
+                "ShortCommit": commit.commit_id.chars().take(7).collect::<String>(),
+                "Author": commit.author,
+                "Date": date_and_time(&Some(commit.date)),
+                "Message": commit.message,
+            })
+        })
+        .collect();
+
     let date_and_time: String = format!(
         r#"<div id="date"><p><span id="lastupdated">{}</span></p></div>"#,
         date_and_time(&article.modification_date)
@@ -135,6 +248,8 @@ pub fn create_html_from_content_template(
         "SeriesNAV": series_nav,
         "Title": article.title,
         "DateAndTime": date_and_time,
+        "LastUpdated": last_updated,
+        "History": history,
         "Tags": tags,
         "ArticleContent": html,
         "ArticleSrcURL": relative_path,
@@ -151,7 +266,7 @@ pub fn create_html_from_navigation_articles_template(
 ) -> Result<String, Box<dyn Error>> {
     let cfg = config::Config::get();
 
-    let mut handlebars = Handlebars::new();
+    let mut handlebars = create_handlebars();
 
     let mut assets: PathBuf = PathBuf::from(cfg.assets.clone());
     assets.push("templates/navigation-articles-template.html");
@@ -178,7 +293,7 @@ pub fn create_html_from_navigation_series_template(
 ) -> Result<String, Box<dyn Error>> {
     let cfg = config::Config::get();
 
-    let mut handlebars = Handlebars::new();
+    let mut handlebars = create_handlebars();
 
     let mut assets: PathBuf = PathBuf::from(cfg.assets.clone());
     assets.push("templates/navigation-series-template.html");
@@ -204,7 +319,7 @@ pub fn create_index_from_most_recent_article_template(
 ) -> Result<String, Box<dyn Error>> {
     let cfg = config::Config::get();
 
-    let mut handlebars = Handlebars::new();
+    let mut handlebars = create_handlebars();
 
     let mut assets: PathBuf = PathBuf::from(cfg.assets.clone());
     assets.push("templates/most-recent-article.html");
@@ -1,3 +1,5 @@
+pub mod assets;
+pub mod diff;
 pub mod html;
 pub mod pandoc;
 mod tests;
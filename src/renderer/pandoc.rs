@@ -41,6 +41,7 @@ pub fn check_pandoc() -> Result<(), Box<dyn Error + Send + Sync>> {
 pub fn pandoc_mdwn_2_html(
     article_markdown: String,
     tocify: bool,
+    math: Option<&str>,
 ) -> Result<String, Box<dyn Error>> {
     // println!("-------------------------");
     // println!("{}", article_markdown.clone());
@@ -74,6 +75,13 @@ pub fn pandoc_mdwn_2_html(
     args.push("--highlight-style");
     args.push("kate");
 
+    // without one pandoc leaves the formulas as TeX
+    match math {
+        Some("mathml") => args.push("--mathml"),
+        Some("katex") => args.push("--katex"),
+        _ => {}
+    }
+
     let mut pandoc_process = std::process::Command::new("pandoc")
         .args(args)
         .stdin(std::process::Stdio::piped())
@@ -1,96 +1,11 @@
 #[cfg(test)]
 mod tests {
     use crate::articles::ArticleWithTags;
-    use crate::config;
     use crate::renderer::html::create_html_from_standalone_template_by_article;
-    use crate::Config;
-    use crate::ConfigValue;
-    use std::collections::HashMap;
-
-    fn create_hacky_config() -> Config {
-        let mut config_values: HashMap<String, ConfigValue> = HashMap::new();
-
-        config_values.insert(
-            "input".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::Path(Some("documents/blog.lastlog.de".into())),
-                is_default: false,
-            },
-        );
-
-        config_values.insert(
-            "output".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::Path(Some("documents/output".into())),
-                is_default: false,
-            },
-        );
-
-        config_values.insert(
-            "assets".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::Path(Some("documents/assets".into())),
-                is_default: false,
-            },
-        );
-
-        config_values.insert(
-            "wasm".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::Path(Some("documents/wasm".into())),
-                is_default: false,
-            },
-        );
-
-        config_values.insert(
-            "database".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::Path(Some("documents".into())),
-                is_default: false,
-            },
-        );
-
-        config_values.insert(
-            "brand".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::String(Some("".to_string())),
-                is_default: false,
-            },
-        );
-
-        config_values.insert(
-            "port".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::Number(Some(5000)),
-                is_default: false,
-            },
-        );
-
-        config_values.insert(
-            "static".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::Bool(Some(false)),
-                is_default: false,
-            },
-        );
-
-        config_values.insert(
-            "flat".to_string(),
-            ConfigValue {
-                value: config::ConfigValueType::Bool(Some(false)),
-                is_default: false,
-            },
-        );
-
-        let config = config::Config::new(config_values);
-
-        config
-    }
 
     #[test]
     fn test_create_html_from_standalone_template() {
-        let config = create_hacky_config();
-        config::Config::initialize(config).expect("Failed to initialize config");
+        crate::test_utils::initialize_config();
 
         let article = ArticleWithTags {
             id: None,
@@ -116,6 +31,8 @@ mod tests {
         println!("{}", rendered_html);
         assert!(rendered_html.contains(&html_content));
         assert!(rendered_html.contains("Test NewArticle"));
+        // dev/live mode references the assets by their unhashed names
+        assert!(rendered_html.contains("/assets/css/style.css\""));
     }
 
     #[test]
@@ -131,4 +48,15 @@ mod tests {
         let formatted_date_none = date_and_time(&None);
         assert_eq!(formatted_date_none, "");
     }
+
+    #[test]
+    fn test_minify_html() {
+        use crate::renderer::utils::minify_html;
+        let html = "<!doctype html>\n<html>\n  <head>\n    <title>foo</title>\n  </head>\n  <body>\n    <!-- comment -->\n    <p>\n      some   text\n    </p>\n    <pre>keep\n   this</pre>\n  </body>\n</html>\n";
+        let minified = minify_html(html);
+        assert!(minified.starts_with("<!doctype html>"));
+        assert!(!minified.contains("comment"));
+        assert!(minified.contains("<pre>keep\n   this</pre>"));
+        assert!(minified.len() < html.len());
+    }
 }
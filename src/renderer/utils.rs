@@ -7,3 +7,23 @@ pub fn date_and_time(modification_date: &Option<chrono::NaiveDateTime>) -> Strin
         None => String::new(),
     }
 }
+
+/// Used for the html written by production builds, inline scripts are kept as they are
+pub fn minify_html(html: &str) -> String {
+    let mut cfg = minify_html::Cfg::new();
+    cfg.do_not_minify_doctype = true;
+    cfg.ensure_spec_compliant_unquoted_attribute_values = true;
+    cfg.keep_closing_tags = true;
+    cfg.keep_html_and_head_opening_tags = true;
+    cfg.minify_css = true;
+    String::from_utf8_lossy(&minify_html::minify(html.as_bytes(), &cfg)).to_string()
+}
+
+/// Escapes `value` for a double quoted html attribute or text
+pub fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
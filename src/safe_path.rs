@@ -0,0 +1,209 @@
+use crate::error::AppError;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// Resolves the percent-encoded path of a request URI to a location below `root`
+///
+/// Segments are decoded before they are checked, so `%2e%2e` is treated like `..`, see
+/// `resolve_path` for the rules.
+pub fn resolve_uri_path(root: &Path, uri_path: &str) -> Result<PathBuf, AppError> {
+    let segments = uri_path
+        .split('/')
+        .map(|segment| percent_encoding::percent_decode_str(segment).decode_utf8())
+        .collect::<Result<Vec<Cow<str>>, _>>()
+        .map_err(|_| invalid_path(uri_path))?;
+    resolve(root, uri_path, &segments)
+}
+
+/// Resolves `relative` (like a `src_file_name`) to a location below `root`
+///
+/// `.`/`..` segments, hidden files (like `.pankat_maintained_output_folder`), backslashes and NUL
+/// bytes are rejected. The result is canonicalized, so a symlink can't point outside of `root`
+/// either. The file itself doesn't have to exist, its deepest existing parent is checked instead.
+pub fn resolve_path(root: &Path, relative: &str) -> Result<PathBuf, AppError> {
+    let segments: Vec<&str> = relative.split('/').collect();
+    resolve(root, relative, &segments)
+}
+
+fn invalid_path(path: &str) -> AppError {
+    AppError::BadRequest(format!("Invalid path '{}'", path))
+}
+
+fn resolve<S: AsRef<str>>(
+    root: &Path,
+    relative: &str,
+    segments: &[S],
+) -> Result<PathBuf, AppError> {
+    let invalid = || invalid_path(relative);
+
+    let mut path = PathBuf::new();
+    for segment in segments.iter().map(AsRef::as_ref) {
+        if segment.is_empty() {
+            continue;
+        }
+        if segment.starts_with('.') || segment.contains(['/', '\\', '\0']) {
+            return Err(invalid());
+        }
+        path.push(segment);
+    }
+
+    let root = root.canonicalize().map_err(|_| AppError::NotFound)?;
+    let path = root.join(path);
+
+    // walks up to the deepest existing ancestor, which is at least `root` itself
+    let mut missing = Vec::new();
+    let mut existing = path.as_path();
+    let canonical = loop {
+        match existing.canonicalize() {
+            Ok(canonical) => break canonical,
+            // a dangling symlink can't be checked, but would be followed on write
+            Err(_) if existing.symlink_metadata().is_ok() => return Err(AppError::Forbidden),
+            Err(_) => {
+                missing.push(existing.file_name().ok_or_else(invalid)?);
+                existing = existing.parent().ok_or_else(invalid)?;
+            }
+        }
+    };
+    if !canonical.starts_with(&root) {
+        println!(
+            "Rejected path '{}' as it resolves to '{}' outside of '{}'",
+            relative,
+            canonical.display(),
+            root.display()
+        );
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(missing
+        .iter()
+        .rev()
+        .fold(canonical, |path, segment| path.join(segment)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_temp_dir;
+    use std::fs;
+
+    fn create_root(name: &str) -> PathBuf {
+        let dir = create_temp_dir(name);
+        fs::create_dir_all(dir.join("root/posts")).unwrap();
+        fs::write(dir.join("root/posts/foo.mdwn"), "foo").unwrap();
+        fs::write(dir.join("root/.pankat_maintained_output_folder"), "").unwrap();
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let dir = create_root("resolve-path");
+        let root = dir.join("root");
+
+        assert_eq!(
+            resolve_path(&root, "/posts/foo.mdwn").unwrap(),
+            root.join("posts/foo.mdwn")
+        );
+        assert_eq!(
+            resolve_path(&root, "posts//foo.mdwn").unwrap(),
+            root.join("posts/foo.mdwn")
+        );
+        assert_eq!(
+            resolve_uri_path(&root, "/posts/foo%20bar.html").unwrap(),
+            root.join("posts/foo bar.html")
+        );
+        // files which don't exist (yet) are fine as long as they stay below root
+        assert_eq!(
+            resolve_path(&root, "drafts/new/bar.mdwn").unwrap(),
+            root.join("drafts/new/bar.mdwn")
+        );
+        assert_eq!(resolve_uri_path(&root, "/").unwrap(), root);
+        // without decoding, `%2e%2e` is just a file name
+        assert_eq!(resolve_path(&root, "%2e%2e").unwrap(), root.join("%2e%2e"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_path_traversal() {
+        let dir = create_root("resolve-path-traversal");
+        let root = dir.join("root");
+
+        for payload in [
+            "../secret.txt",
+            "/../secret.txt",
+            "posts/../../secret.txt",
+            "posts/./foo.mdwn",
+            "%2e%2e/secret.txt",
+            "%2E%2E/secret.txt",
+            "posts/%2e%2e/%2e%2e/secret.txt",
+            "..%2fsecret.txt",
+            "%2e%2e%2fsecret.txt",
+            "..%5csecret.txt",
+            "posts%2f..%2f..%2fsecret.txt",
+            "..\\secret.txt",
+            "posts/foo.mdwn%00.html",
+            "%c0%ae%c0%ae/secret.txt",
+            ".pankat_maintained_output_folder",
+            "/%2epankat_maintained_output_folder",
+            ".git/config",
+        ] {
+            assert!(
+                resolve_uri_path(&root, payload).is_err(),
+                "'{}' must be rejected",
+                payload
+            );
+        }
+        for payload in [
+            "../secret.txt",
+            "posts/../../secret.txt",
+            "posts/./foo.mdwn",
+            "..\\secret.txt",
+            "posts/foo.mdwn\0.html",
+            ".pankat_maintained_output_folder",
+        ] {
+            assert!(
+                resolve_path(&root, payload).is_err(),
+                "'{}' must be rejected",
+                payload
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_path_symlinks() {
+        let dir = create_root("resolve-path-symlinks");
+        let root = dir.join("root");
+        std::os::unix::fs::symlink(dir.join("secret.txt"), root.join("secret.txt")).unwrap();
+        std::os::unix::fs::symlink(&dir, root.join("escape")).unwrap();
+        std::os::unix::fs::symlink(root.join("posts"), root.join("articles")).unwrap();
+        std::os::unix::fs::symlink(dir.join("missing.txt"), root.join("dangling.txt")).unwrap();
+
+        assert!(matches!(
+            resolve_path(&root, "secret.txt"),
+            Err(AppError::Forbidden)
+        ));
+        assert!(matches!(
+            resolve_path(&root, "escape/secret.txt"),
+            Err(AppError::Forbidden)
+        ));
+        assert!(matches!(
+            resolve_path(&root, "escape/new.mdwn"),
+            Err(AppError::Forbidden)
+        ));
+        assert!(matches!(
+            resolve_path(&root, "dangling.txt"),
+            Err(AppError::Forbidden)
+        ));
+        // links within root are fine
+        assert_eq!(
+            resolve_path(&root, "articles/foo.mdwn").unwrap(),
+            root.join("posts/foo.mdwn")
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+use crate::config::{self, Config, ConfigValue};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// An empty directory below the system's temp directory, unique per test `name` and test run
+pub fn create_temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pankat-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn create_hacky_config() -> Config {
+    let mut config_values: HashMap<String, ConfigValue> = HashMap::new();
+
+    config_values.insert(
+        "input".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::Path(Some("documents/blog.lastlog.de".into())),
+            is_default: false,
+        },
+    );
+
+    config_values.insert(
+        "output".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::Path(Some("documents/output".into())),
+            is_default: false,
+        },
+    );
+
+    config_values.insert(
+        "assets".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::Path(Some("documents/assets".into())),
+            is_default: false,
+        },
+    );
+
+    config_values.insert(
+        "wasm".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::Path(Some("documents/wasm".into())),
+            is_default: false,
+        },
+    );
+
+    config_values.insert(
+        "database".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::Path(Some("documents".into())),
+            is_default: false,
+        },
+    );
+
+    config_values.insert(
+        "brand".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::String(Some("".to_string())),
+            is_default: false,
+        },
+    );
+
+    config_values.insert(
+        "port".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::Number(Some(5000)),
+            is_default: false,
+        },
+    );
+
+    config_values.insert(
+        "static".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::Bool(Some(false)),
+            is_default: false,
+        },
+    );
+
+    config_values.insert(
+        "flat".to_string(),
+        ConfigValue {
+            value: config::ConfigValueType::Bool(Some(false)),
+            is_default: false,
+        },
+    );
+
+    config::Config::new(config_values)
+}
+
+/// Initializes the global configuration for the tests which need one, the first call wins
+pub fn initialize_config() {
+    config::Config::initialize(create_hacky_config()).expect("Failed to initialize config");
+}
@@ -0,0 +1,191 @@
+use crate::auth::{ensure_admin, ADMIN_USERNAME};
+use crate::db::tests::create_pool_and_initialize_schema;
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn send(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+    let response = app
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn token(app: &Router, path: &str, username: &str, password: &str) -> String {
+    let (status, response) = send(
+        app,
+        Method::POST,
+        path,
+        None,
+        Some(json!({ "username": username, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", response);
+    response["token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_admin_account() {
+    crate::test_utils::initialize_config();
+    let pool = create_pool_and_initialize_schema();
+    let app = crate::routes(pool.clone());
+
+    // a fresh instance has no admin until admin_password is set
+    assert!(!ensure_admin(&mut pool.get().unwrap(), "not set").unwrap());
+    assert!(ensure_admin(&mut pool.get().unwrap(), "secret").unwrap());
+    assert!(!ensure_admin(&mut pool.get().unwrap(), "secret").unwrap());
+
+    let admin = token(&app, "/api/auth/login", ADMIN_USERNAME, "secret").await;
+    let (status, articles) = send(&app, Method::GET, "/api/articles", Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(articles, json!([]));
+
+    // registered accounts are no admins
+    let user = token(&app, "/api/auth/register", "joe", "password").await;
+    let (status, _) = send(&app, Method::GET, "/api/articles", Some(&user), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // a changed admin_password replaces the old one
+    assert!(ensure_admin(&mut pool.get().unwrap(), "new secret").unwrap());
+    token(&app, "/api/auth/login", ADMIN_USERNAME, "new secret").await;
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/api/auth/login",
+        None,
+        Some(json!({ "username": ADMIN_USERNAME, "password": "secret" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_revision_routes() {
+    use crate::articles::ArticleWithTags;
+    use crate::auth::{create_token, UserLevel};
+    use crate::db::article_revisions::{add_revision, NewArticleRevision};
+    use crate::db::users::create_user;
+
+    crate::test_utils::initialize_config();
+    let pool = create_pool_and_initialize_schema();
+    let app = crate::routes(pool.clone());
+    let (id, first, admin, user) = {
+        let mut conn = pool.get().unwrap();
+        let mut token = |level: UserLevel| {
+            let user_id = create_user(&mut conn, &format!("{:?}", level), "", level.clone());
+            create_token(user_id.unwrap().to_string(), level, None).unwrap()
+        };
+        let (admin, user) = (token(UserLevel::Admin), token(UserLevel::User));
+        let article = ArticleWithTags {
+            id: None,
+            src_file_name: "posts/foo.mdwn".to_string(),
+            dst_file_name: "foo.html".to_string(),
+            title: Some("Foo".to_string()),
+            modification_date: None,
+            summary: None,
+            tags: None,
+            series: None,
+            draft: None,
+            special_page: None,
+            anchorjs: None,
+            tocify: None,
+            live_updates: None,
+        };
+        crate::db::article::set(&mut conn, &article).unwrap();
+        let id = crate::db::article::get_article_with_tags_by_src_file_name(
+            &mut conn,
+            "posts/foo.mdwn".to_string(),
+        )
+        .unwrap()
+        .unwrap()
+        .id
+        .unwrap();
+        let mut revision = |markdown: &str| {
+            add_revision(
+                &mut conn,
+                &NewArticleRevision {
+                    src_file_name: "posts/foo.mdwn",
+                    hash: "hash",
+                    markdown,
+                    source: markdown,
+                    created_at: chrono::Utc::now().naive_utc(),
+                },
+            )
+            .unwrap()
+        };
+        let first = revision("foo 1");
+        revision("foo 2");
+        (id, first, admin, user)
+    };
+    let get = |uri: String| {
+        let (app, admin) = (app.clone(), admin.clone());
+        async move { send(&app, Method::GET, &uri, Some(&admin), None).await }
+    };
+
+    let (status, revisions) = get(format!("/api/articles/{}/revisions", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(revisions.as_array().unwrap().len(), 2);
+    let (status, revision) = get(format!("/api/articles/{}/revisions/{}", id, first)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(revision["markdown"], "foo 1");
+    let (status, _) = get(format!("/api/articles/{}/revisions", id + 1)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = get(format!("/api/articles/{}/revisions/{}/restore", id, first)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/api/articles/posts/foo.mdwn",
+        Some(&admin),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let uri = format!("/api/articles/{}/revisions", id);
+    let (status, _) = send(&app, Method::GET, &uri, Some(&user), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_login_body_too_large() {
+    crate::test_utils::initialize_config();
+    let app = crate::routes(create_pool_and_initialize_schema());
+
+    let password = "x".repeat(crate::rate_limit::MAX_BODY_SIZE);
+    let (status, response) = send(
+        &app,
+        Method::POST,
+        "/api/auth/login",
+        None,
+        Some(json!({ "username": "joe", "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(response["error"], "Payload too large");
+}